@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde_json::json;
+
+use crate::cli::{Cli, Command, ExitStatus, Output, OutputFormat};
+use crate::config::Config;
+use crate::odds_interface::api_requests::error::OddsApiError;
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType, odds::Odds};
+
+fn parse(args: &[&str]) -> Result<Command, clap::Error> {
+    return Cli::try_parse_from([&["arb-finder"], args].concat()).map(|x| x.command);
+}
+
+#[test]
+fn test_parse_scan() {
+    let Command::Scan(args) = parse(&[
+        "scan",
+        "--sport",
+        "aussierules_afl,baseball_kbo",
+        "--markets",
+        "h2h,totals",
+        "--regions",
+        "au",
+        "--min-ev",
+        "2.5",
+        "--notify",
+        "--format",
+        "csv",
+    ])
+    .unwrap() else {
+        panic!("expected a scan");
+    };
+    assert_eq!(args.sports.sports, ["aussierules_afl", "baseball_kbo"]);
+    assert_eq!(args.markets.markets, [MarketType::H2h, MarketType::Totals]);
+    assert_eq!(args.markets.regions, [Region::Au]);
+    assert_eq!(args.min_ev, Some(2.5));
+    assert!(args.notify);
+    assert_eq!(args.output.format, OutputFormat::Csv);
+
+    // overrides only replace what was given
+    let mut config = Config {
+        sports: [String::from("basketball_nba")].to_vec(),
+        regions: [Region::Us].to_vec(),
+        ..Default::default()
+    };
+    args.markets.apply(&mut config);
+    assert_eq!(config.markets, [MarketType::H2h, MarketType::Totals]);
+    assert_eq!(config.regions, [Region::Au]);
+    assert_eq!(args.sports.or_configured(&config).len(), 2);
+
+    let Command::Scan(args) = parse(&["scan"]).unwrap() else {
+        panic!("expected a scan");
+    };
+    assert_eq!(args.output.format, OutputFormat::Table);
+    assert_eq!(args.min_ev, None);
+    assert_eq!(args.sports.or_configured(&config), ["basketball_nba"]);
+}
+
+#[test]
+fn test_parse_rejects_bad_arguments() {
+    assert!(parse(&["scan", "--markets", "moneyline"]).is_err());
+    assert!(parse(&["scan", "--format", "xml"]).is_err());
+    assert!(parse(&["arbs"]).is_err());
+    assert!(parse(&["backtest", "--sport", "x", "--to", "2024-08-01T00:00:00Z"]).is_err());
+    assert!(parse(&["menu"]).is_err());
+}
+
+#[test]
+fn test_parse_bet_and_backtest() {
+    let Command::Bet(args) = parse(&[
+        "bet",
+        "--event",
+        "132905fc8e35fe3551638c40c80fe9e6",
+        "--market",
+        "spreads",
+        "--outcome",
+        "Brisbane Lions",
+        "--point",
+        "-6.5",
+        "--bookmaker",
+        "sportsbet",
+        "--odds",
+        "-110",
+        "--stake",
+        "10",
+    ])
+    .unwrap() else {
+        panic!("expected a bet");
+    };
+    assert_eq!(args.market, MarketType::Spreads);
+    assert_eq!(args.point, Some(-6.5));
+    assert_eq!(args.odds, Odds::American(-110));
+    assert_eq!(args.stake, 10.0);
+
+    let Command::Backtest(args) = parse(&[
+        "backtest",
+        "--sport",
+        "aussierules_afl",
+        "--from",
+        "2024-08-01T00:00:00Z",
+    ])
+    .unwrap() else {
+        panic!("expected a backtest");
+    };
+    assert!(args.from.is_some());
+    assert!(args.to.is_none());
+
+    let Command::Replay(args) = parse(&["replay", "odds.json"]).unwrap() else {
+        panic!("expected a replay");
+    };
+    assert_eq!(args.file, PathBuf::from("odds.json"));
+}
+
+#[test]
+fn test_exit_status() {
+    let message = String::from("nope");
+    assert_eq!(
+        ExitStatus::from_api_error(&OddsApiError::Unauthorized {
+            message: message.clone()
+        }),
+        ExitStatus::Unauthorized
+    );
+    assert_eq!(
+        ExitStatus::from_api_error(&OddsApiError::QuotaExceeded {
+            message: message.clone()
+        }),
+        ExitStatus::QuotaExceeded
+    );
+    assert_eq!(
+        ExitStatus::from_api_error(&OddsApiError::BudgetExceeded {
+            cost: 2,
+            available: 1
+        }),
+        ExitStatus::QuotaExceeded
+    );
+    assert_eq!(
+        ExitStatus::from_api_error(&OddsApiError::InvalidRequest { message }),
+        ExitStatus::Usage
+    );
+    assert_eq!(
+        ExitStatus::from_api_error(&OddsApiError::InvalidUrl(String::new())),
+        ExitStatus::Failure
+    );
+
+    assert_eq!(
+        ExitStatus::Success.and(ExitStatus::Success),
+        ExitStatus::Success
+    );
+    assert_eq!(
+        ExitStatus::Success.and(ExitStatus::Unauthorized),
+        ExitStatus::Unauthorized
+    );
+    assert_eq!(
+        ExitStatus::Config.and(ExitStatus::Failure),
+        ExitStatus::Config
+    );
+    assert_eq!(ExitStatus::QuotaExceeded as u8, 5);
+}
+
+#[test]
+fn test_output_formats() {
+    let mut output = Output::new(&["bookmaker", "odds", "note"]);
+    output.push(json!({"bookmaker": "tab", "odds": 2.126, "note": "a, \"b\"", "extra": 1}));
+    output.push(json!({"bookmaker": "sportsbet", "odds": 10}));
+    // not an object
+    output.push(json!(3));
+
+    let table = output.render(OutputFormat::Table);
+    assert_eq!(
+        table,
+        "bookmaker  odds  note\n\
+         tab        2.13  a, \"b\"\n\
+         sportsbet    10\n"
+    );
+
+    let csv = output.render(OutputFormat::Csv);
+    assert_eq!(
+        csv,
+        "bookmaker,odds,note\n\
+         tab,2.126,\"a, \"\"b\"\"\"\n\
+         sportsbet,10,\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&output.render(OutputFormat::Json)).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[0]["extra"], 1);
+    assert_eq!(json[1]["odds"], 10);
+
+    assert_eq!(Output::new(&["a"]).render(OutputFormat::Json), "[]\n");
+    assert_eq!(OutputFormat::Csv.to_string(), "csv");
+}
@@ -0,0 +1,430 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use crate::odds_interface::api_requests::error::OddsApiError;
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType, odds::Odds};
+
+#[cfg(test)]
+mod cli_test;
+
+/// Finds +EV bets, arbitrages and matched bets in Odds API prices
+#[derive(Parser, Debug)]
+#[command(name = "arb-finder", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List in-season sports
+    Sports(OutputArgs),
+    /// Find +EV opportunities, and books that haven't followed steam moves
+    Scan(ScanArgs),
+    /// Find arbitrages in a sport
+    Arbs(ArbsArgs),
+    /// Find matched bets against exchange lay prices
+    Matched(MatchedArgs),
+    /// Show how many api requests have been used and are left
+    Usage(OutputArgs),
+    /// Send a test message to the discord channel
+    NotifyTest,
+    /// Find +EV opportunities in a saved odds response or cassette, without
+    /// calling the api
+    Replay(ReplayArgs),
+    /// Record a bet in the ledger
+    Bet(BetArgs),
+    /// Settle pending bets and report profit and loss
+    Settle,
+    /// Capture closing lines and report closing line value
+    Clv,
+    /// Backtest parameter sets against stored or historical odds
+    Backtest(BacktestArgs),
+    /// Report how well calibrated the fair probabilities are
+    Calibration(SportsArgs),
+    /// Rank bookmakers by sharpness and learn consensus weights
+    Sharpness(SportsArgs),
+    /// Show the stored events of a sport, or the stored prices of an
+    /// event's outcome
+    History(HistoryArgs),
+    /// Keep scanning, polling each sport more often as its next event
+    /// approaches, until stopped with SIGINT or SIGTERM
+    Daemon(DaemonArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for reading in a terminal
+    #[default]
+    Table,
+    /// An array of objects
+    Json,
+    /// A header row then one row per result
+    Csv,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+/// Overrides of the configured markets and regions
+#[derive(Args, Debug, Clone, Default)]
+pub struct MarketArgs {
+    /// Markets to fetch, comma separated, e.g. h2h,totals
+    #[arg(long, value_delimiter = ',', value_parser = MarketType::from_str)]
+    pub markets: Vec<MarketType>,
+    /// Bookmaker regions to fetch, comma separated, e.g. au,uk
+    #[arg(long, value_delimiter = ',', value_parser = Region::from_str)]
+    pub regions: Vec<Region>,
+}
+
+impl MarketArgs {
+    pub fn apply(&self, config: &mut Config) {
+        if (!self.markets.is_empty()) {
+            config.markets = self.markets.clone();
+        }
+        if (!self.regions.is_empty()) {
+            config.regions = self.regions.clone();
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct SportsArgs {
+    /// Sport keys, comma separated, instead of the configured sports
+    #[arg(long = "sport", value_delimiter = ',')]
+    pub sports: Vec<String>,
+}
+
+impl SportsArgs {
+    pub fn or_configured(&self, config: &Config) -> Vec<String> {
+        if (self.sports.is_empty()) {
+            return config.sports.clone();
+        }
+        return self.sports.clone();
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub sports: SportsArgs,
+    #[command(flatten)]
+    pub markets: MarketArgs,
+    /// Lowest EV percentage to report, for every sport
+    #[arg(long)]
+    pub min_ev: Option<f64>,
+    /// Also post every result to the discord channel
+    #[arg(long)]
+    pub notify: bool,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ArbsArgs {
+    #[arg(long)]
+    pub sport: String,
+    /// Amount to split across each arbitrage, instead of the staking bankroll
+    #[arg(long)]
+    pub bankroll: Option<f64>,
+    #[command(flatten)]
+    pub markets: MarketArgs,
+    #[arg(long)]
+    pub notify: bool,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct MatchedArgs {
+    #[arg(long)]
+    pub sport: String,
+    /// Back stake of each matched bet
+    #[arg(long)]
+    pub stake: f64,
+    #[command(flatten)]
+    pub markets: MarketArgs,
+    #[arg(long)]
+    pub notify: bool,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ReplayArgs {
+    /// An odds response saved from the api, or a recorded cassette of one
+    pub file: PathBuf,
+    #[arg(long)]
+    pub min_ev: Option<f64>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BetArgs {
+    /// Id of an event with a stored snapshot
+    #[arg(long)]
+    pub event: String,
+    #[arg(long, value_parser = MarketType::from_str)]
+    pub market: MarketType,
+    /// e.g. a team name, Over or Under
+    #[arg(long)]
+    pub outcome: String,
+    /// Line of a totals or spreads bet
+    #[arg(long, allow_negative_numbers = true)]
+    pub point: Option<f64>,
+    #[arg(long)]
+    pub bookmaker: String,
+    /// Odds taken, in any format, e.g. 2.5, +150 or 3/2
+    #[arg(long, value_parser = Odds::from_str, allow_negative_numbers = true)]
+    pub odds: Odds,
+    #[arg(long)]
+    pub stake: f64,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct BacktestArgs {
+    #[arg(long)]
+    pub sport: String,
+    /// Fill the database from the historical odds endpoint from this time,
+    /// e.g. 2024-08-01T00:00:00Z
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+    /// End of the historical odds to fetch, now if not given
+    #[arg(long, requires = "from")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct HistoryArgs {
+    /// List the stored events of this sport
+    #[arg(long, required_unless_present = "event")]
+    pub sport: Option<String>,
+    /// Id of a stored event, to show the price history of one of its
+    /// outcomes
+    #[arg(long, requires_all = ["market", "outcome"])]
+    pub event: Option<String>,
+    #[arg(long, value_parser = MarketType::from_str)]
+    pub market: Option<MarketType>,
+    /// e.g. a team name, Over or Under
+    #[arg(long)]
+    pub outcome: Option<String>,
+    /// Only this bookmaker's prices
+    #[arg(long)]
+    pub bookmaker: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct DaemonArgs {
+    #[command(flatten)]
+    pub sports: SportsArgs,
+    #[command(flatten)]
+    pub markets: MarketArgs,
+    #[arg(long)]
+    pub min_ev: Option<f64>,
+    /// Post every result to the discord channel
+    #[arg(long)]
+    pub notify: bool,
+    /// Where to write the heartbeat and schedule, instead of the configured
+    /// file
+    #[arg(long)]
+    pub status_file: Option<PathBuf>,
+}
+
+/// Exit codes scripts and cron can rely on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    /// A request, file or database operation failed. Whatever did succeed
+    /// is still written out.
+    Failure = 1,
+    /// The arguments were invalid
+    Usage = 2,
+    /// The config couldn't be loaded, or is missing a setting the command
+    /// needs
+    Config = 3,
+    /// The api key was rejected
+    Unauthorized = 4,
+    /// The api key, or the configured quota budget, has run out of requests
+    QuotaExceeded = 5,
+}
+
+impl ExitStatus {
+    pub fn from_api_error(error: &OddsApiError) -> Self {
+        match error {
+            OddsApiError::Unauthorized { .. } => return ExitStatus::Unauthorized,
+            OddsApiError::QuotaExceeded { .. } | OddsApiError::BudgetExceeded { .. } => {
+                return ExitStatus::QuotaExceeded
+            }
+            OddsApiError::InvalidRequest { .. } => return ExitStatus::Usage,
+            _ => return ExitStatus::Failure,
+        }
+    }
+
+    /// Keeps the first failure when a command runs several steps
+    pub fn and(self, other: ExitStatus) -> Self {
+        if (self == ExitStatus::Success) {
+            return other;
+        }
+        return self;
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        return ExitCode::from(status as u8);
+    }
+}
+
+/// Rows of a command's results, written out in any `OutputFormat`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Output {
+    columns: Vec<&'static str>,
+    rows: Vec<Map<String, Value>>,
+}
+
+impl Output {
+    pub fn new(columns: &[&'static str]) -> Self {
+        return Output {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        };
+    }
+
+    /// Adds a row from a JSON object. Keys that aren't columns only show up
+    /// in JSON, and missing columns are left blank.
+    pub fn push(&mut self, row: Value) {
+        if let Value::Object(x) = row {
+            self.rows.push(x);
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Table => return self.render_table(),
+            OutputFormat::Json => return self.render_json(),
+            OutputFormat::Csv => return self.render_csv(),
+        }
+    }
+
+    fn render_table(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                return self
+                    .columns
+                    .iter()
+                    .map(|x| table_cell(row.get(*x)))
+                    .collect();
+            })
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                return cells
+                    .iter()
+                    .map(|x| x[i].chars().count())
+                    .fold(column.len(), usize::max);
+            })
+            .collect();
+        // numbers line up on the right, everything else on the left
+        let numeric: Vec<bool> = self
+            .columns
+            .iter()
+            .map(|column| {
+                return self
+                    .rows
+                    .iter()
+                    .filter_map(|x| x.get(*column))
+                    .all(|x| x.is_number() || x.is_null());
+            })
+            .collect();
+
+        let format_line = |values: Vec<String>| {
+            let line: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, x)| match numeric[i] {
+                    true => format!("{x:>0$}", widths[i]),
+                    false => format!("{x:<0$}", widths[i]),
+                })
+                .collect();
+            return line.join("  ").trim_end().to_string() + "\n";
+        };
+
+        let mut table = format_line(self.columns.iter().map(|x| x.to_string()).collect());
+        for row in cells {
+            table += &format_line(row);
+        }
+        return table;
+    }
+
+    fn render_json(&self) -> String {
+        let rows: Vec<Value> = self.rows.iter().cloned().map(Value::Object).collect();
+        return serde_json::to_string_pretty(&rows).unwrap_or_default() + "\n";
+    }
+
+    fn render_csv(&self) -> String {
+        let mut csv = self.columns.join(",") + "\n";
+        for row in &self.rows {
+            let fields: Vec<String> = self
+                .columns
+                .iter()
+                .map(|x| csv_field(&plain_cell(row.get(*x))))
+                .collect();
+            csv += &(fields.join(",") + "\n");
+        }
+        return csv;
+    }
+}
+
+/// A value as text, at full precision
+fn plain_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => return String::new(),
+        Some(Value::String(x)) => return x.clone(),
+        Some(x) => return x.to_string(),
+    }
+}
+
+/// A value as text, with fractions rounded to two places
+fn table_cell(value: Option<&Value>) -> String {
+    if let Some(Value::Number(number)) = value {
+        if (number.is_f64()) {
+            return format!("{0:.2}", number.as_f64().unwrap_or_default());
+        }
+    }
+    return plain_cell(value);
+}
+
+fn csv_field(raw: &str) -> String {
+    if (raw.contains([',', '"', '\n'])) {
+        return format!("\"{0}\"", raw.replace('"', "\"\""));
+    }
+    return raw.to_string();
+}
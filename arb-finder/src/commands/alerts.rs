@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+
+use super::open_store;
+use super::scan::ScanResults;
+use crate::config::Config;
+use crate::odds_interface::logic::alerts::{
+    format_gone_message, format_realert_note, gone_alerts, AlertRecord, OpportunityKey,
+};
+use crate::storage::SnapshotStore;
+
+/// `message` if the alert settings say to alert on `alert` at `now`, noting
+/// the last alert, which `alert` then replaces
+fn alert_if_due(
+    config: &Config,
+    store: &mut SnapshotStore,
+    alert: &AlertRecord,
+    message: String,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let previous = match store.alert_for(&alert.key) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to read alert history: {e}");
+            None
+        }
+    };
+    let reason = config
+        .alerts
+        .alert_reason(previous.as_ref(), alert.percent_ev, now)?;
+
+    let mut message = message;
+    if let Some(previous) = &previous {
+        if let Some(note) = format_realert_note(reason, previous, &config.odds_format) {
+            message += &note;
+        }
+    }
+    if let Err(e) = store.save_alert(alert) {
+        eprintln!("Failed to save alert for {0}: {e}", alert.key);
+    }
+    return Some(message);
+}
+
+/// Discord messages for each steam move and opportunity in `results` the
+/// alert settings say to alert on at `now`, followed by any opportunities
+/// alerted earlier that weren't found again. A steam move is alerted again
+/// if it has grown by `alerts.ev_delta` percentage points. Without a
+/// snapshot database there's no alert history, so everything is alerted.
+pub fn alert_messages(config: &Config, results: &ScanResults, now: DateTime<Utc>) -> Vec<String> {
+    if (config.snapshot_db.is_none()) {
+        return results.messages(&config.odds_format);
+    }
+    let mut store = match open_store(config) {
+        Ok(x) => x,
+        Err(_) => return results.messages(&config.odds_format),
+    };
+
+    let mut messages = Vec::new();
+    for steam_move in &results.steam_moves {
+        let message = steam_move.format_message(&config.odds_format);
+        if let Some(x) = alert_if_due(config, &mut store, &steam_move.alert(now), message, now) {
+            messages.push(x);
+        }
+    }
+    for opportunity in &results.opportunities {
+        let message = opportunity.format_message(&config.odds_format);
+        if let Some(x) = alert_if_due(config, &mut store, &opportunity.alert(now), message, now) {
+            messages.push(x);
+        }
+    }
+
+    let found: Vec<OpportunityKey> = results.opportunities.iter().map(|x| x.key()).collect();
+    for (sport_key, coverage) in &results.fetched {
+        let active = match store.active_alerts(sport_key) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Failed to read alert history: {e}");
+                continue;
+            }
+        };
+        for alert in gone_alerts(&active, &found, coverage) {
+            if (config.alerts.notify_gone) {
+                messages.push(format_gone_message(alert, &config.odds_format));
+            }
+            if let Err(e) = store.set_alert_gone(&alert.key, now) {
+                eprintln!("Failed to save alert for {0}: {e}", alert.key);
+            }
+        }
+    }
+    return messages;
+}
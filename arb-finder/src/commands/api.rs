@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::cli::Output;
+use crate::odds_interface::api_requests::{quota::QuotaTracker, util::ApiKeyUsage};
+use crate::odds_interface::logic::sport::Sport;
+
+/// A row per sport the api has odds for
+pub fn sports_output(sports: &[Sport]) -> Output {
+    let mut rows = Output::new(&[
+        "key",
+        "group",
+        "title",
+        "description",
+        "active",
+        "has_outrights",
+    ]);
+    for sport in sports {
+        rows.push(serde_json::to_value(sport).unwrap_or_default());
+    }
+    return rows;
+}
+
+/// What the key has used, and how long what's left of the budget lasts
+/// from `now`
+pub fn usage_output(usage: &ApiKeyUsage, quota: &QuotaTracker, now: DateTime<Utc>) -> Output {
+    let mut rows = Output::new(&[
+        "requests_used",
+        "requests_remaining",
+        "available",
+        "projected_exhaustion",
+    ]);
+    rows.push(json!({
+        "requests_used": usage.requests_used,
+        "requests_remaining": usage.requests_remaining,
+        "requests_last": usage.requests_last,
+        "available": quota.available(now),
+        "projected_exhaustion": quota.projected_exhaustion(now),
+    }));
+    return rows;
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use crate::commands::arbitrage::find_arbitrages;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    transport::{TransportMode, TransportSettings},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+
+const CASSETTE_DIR: &str = "./example_data/cassettes";
+
+fn get_replay_client() -> OddsApiClient {
+    // the host doesn't matter, nothing is sent when replaying
+    return OddsApiClient::with_base_url("any-key", "http://replay.invalid/v4").with_transport(
+        TransportSettings {
+            mode: TransportMode::Replay,
+            cassette_dir: PathBuf::from(CASSETTE_DIR),
+            ..Default::default()
+        },
+    );
+}
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_replay_arbitrage_scan_tracks_quota() {
+    let client = get_replay_client();
+    let config = get_replay_config();
+
+    let find_messages = || -> Vec<String> {
+        return find_arbitrages(&client, &config, "aussierules_afl", 1000.0)
+            .unwrap()
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+    };
+    assert_eq!(find_messages(), find_messages());
+
+    // usage comes from the recorded quota headers
+    let usage = client.quota().latest_usage().unwrap();
+    assert_eq!(usage.requests_used, 14);
+    assert_eq!(usage.requests_last, Some(2));
+}
@@ -0,0 +1,126 @@
+use serde_json::json;
+
+use super::scan::save_snapshot;
+use crate::cli::Output;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient, error::OddsApiError, get_odds_for_sport,
+};
+use crate::odds_interface::logic::{
+    arbitrage::Arbitrage,
+    exchange::{lay_market_for, MatchedBet},
+};
+
+#[cfg(test)]
+mod arbitrage_test;
+
+/// Every arbitrage in `sport_key`
+pub fn find_arbitrages(
+    client: &OddsApiClient,
+    config: &Config,
+    sport_key: &str,
+    bankroll: f64,
+) -> Result<Vec<Arbitrage>, OddsApiError> {
+    let events_raw = get_odds_for_sport(client, sport_key, &config.markets, &config.regions)?;
+    let _ = save_snapshot(config, &events_raw);
+
+    let mut arbitrages = Vec::new();
+    for event in events_raw {
+        arbitrages.append(&mut event.find_arbitrages(bankroll, &config.scan));
+    }
+    return Ok(arbitrages);
+}
+
+/// A row per leg of each arbitrage
+pub fn arbitrage_output(arbitrages: &[Arbitrage]) -> Output {
+    let mut output = Output::new(&[
+        "sport",
+        "matchup",
+        "market",
+        "profit_margin",
+        "guaranteed_profit",
+        "outcome",
+        "point",
+        "bookmaker",
+        "odds",
+        "stake",
+    ]);
+    for arbitrage in arbitrages {
+        for leg in &arbitrage.legs {
+            output.push(json!({
+                "sport": arbitrage.sport_title,
+                "matchup": arbitrage.matchup(),
+                "market": arbitrage.market_key.to_string(),
+                "profit_margin": arbitrage.profit_margin,
+                "guaranteed_profit": arbitrage.guaranteed_profit,
+                "outcome": leg.outcome_key,
+                "point": leg.point,
+                "bookmaker": leg.bookie_name,
+                "odds": leg.odds.get_decimal(),
+                "stake": leg.stake,
+            }));
+        }
+    }
+    return output;
+}
+
+/// Every back/lay pair in `sport_key` within the qualifying loss
+pub fn find_matched_bets(
+    client: &OddsApiClient,
+    config: &Config,
+    sport_key: &str,
+    back_stake: f64,
+) -> Result<Vec<MatchedBet>, OddsApiError> {
+    let mut markets = config.markets.clone();
+    for market in &config.markets {
+        if let Some(lay_market) = lay_market_for(market) {
+            if (!markets.contains(&lay_market)) {
+                markets.push(lay_market);
+            }
+        }
+    }
+
+    let events_raw = get_odds_for_sport(client, sport_key, &markets, &config.regions)?;
+    let _ = save_snapshot(config, &events_raw);
+
+    let mut matched_bets = Vec::new();
+    for event in events_raw {
+        matched_bets.append(&mut event.find_matched_bets(back_stake, &config.scan));
+    }
+    return Ok(matched_bets);
+}
+
+pub fn matched_bet_output(matched_bets: &[MatchedBet]) -> Output {
+    let mut output = Output::new(&[
+        "sport",
+        "matchup",
+        "market",
+        "outcome",
+        "bookmaker",
+        "back_odds",
+        "back_stake",
+        "exchange",
+        "lay_odds",
+        "lay_stake",
+        "liability",
+        "profit",
+    ]);
+    for matched_bet in matched_bets {
+        output.push(json!({
+            "sport": matched_bet.sport_title,
+            "matchup": matched_bet.matchup(),
+            "market": matched_bet.market_key.to_string(),
+            "outcome": matched_bet.outcome_key,
+            "bookmaker": matched_bet.bookie_name,
+            "back_odds": matched_bet.back_odds.get_decimal(),
+            "back_stake": matched_bet.back_stake,
+            "exchange": matched_bet.exchange_name,
+            "lay_odds": matched_bet.lay_odds.get_decimal(),
+            "commission": matched_bet.commission,
+            "lay_stake": matched_bet.lay_stake,
+            "liability": matched_bet.liability,
+            "profit": matched_bet.profit,
+        }));
+    }
+    return output;
+}
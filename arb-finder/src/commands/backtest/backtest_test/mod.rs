@@ -0,0 +1,80 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crate::commands::backtest::{backtest_report, fetch_historical_snapshots, save_recent_results};
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{backtest::ParameterSet, bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_backtest_historical_odds_from_mock() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = OddsApiClient::with_base_url("mock-key", &server.odds_host_base());
+
+    let mut config = get_replay_config();
+    config.backtest.parameter_sets = vec![ParameterSet {
+        name: String::from("never_bets"),
+        percent_ev_cutoff: Some(1000.0),
+        ..Default::default()
+    }];
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+
+    let from = Utc.with_ymd_and_hms(2024, 8, 4, 4, 40, 0).unwrap();
+    let to = from + Duration::minutes(90);
+    let num_saved =
+        fetch_historical_snapshots(&client, &config, &mut store, "aussierules_afl", from, to);
+    assert_eq!(num_saved, 2);
+    // historical odds cost 10 per market per region
+    assert_eq!(server.requests_used(), 40);
+    let snapshots = store.snapshots_for_sport("aussierules_afl").unwrap();
+    assert_eq!(snapshots[1].fetched_at, from + Duration::minutes(60));
+
+    // the mock only has a snapshot every 5 minutes, so a shorter interval
+    // doesn't fetch any of them twice
+    config.backtest.historical_interval_minutes = 1;
+    let mut minutely = SnapshotStore::open_in_memory().unwrap();
+    let num_saved = fetch_historical_snapshots(
+        &client,
+        &config,
+        &mut minutely,
+        "aussierules_afl",
+        from + Duration::minutes(2),
+        from + Duration::minutes(12),
+    );
+    assert_eq!(num_saved, 3);
+    assert_eq!(server.requests_used(), 100);
+    let fetched_at: Vec<DateTime<Utc>> = minutely
+        .snapshots_for_sport("aussierules_afl")
+        .unwrap()
+        .iter()
+        .map(|x| x.fetched_at)
+        .collect();
+    assert_eq!(fetched_at[0], from);
+    assert_eq!(fetched_at.last(), Some(&(from + Duration::minutes(10))));
+
+    assert_eq!(
+        save_recent_results(&client, &mut store, "aussierules_afl"),
+        1
+    );
+
+    let report = backtest_report(&config, &store, "aussierules_afl");
+    let rows: Vec<&str> = report.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].starts_with("configured"));
+    assert!(rows[2].starts_with("never_bets"));
+    // every opportunity was settled against the saved result
+    let configured: Vec<&str> = rows[1].split_whitespace().collect();
+    assert_ne!(configured[1], "0");
+    assert_eq!(configured[1], configured[2]);
+}
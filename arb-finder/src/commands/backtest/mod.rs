@@ -0,0 +1,110 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::Config;
+use crate::odds_interface::api_requests::{client::OddsApiClient, get_historical_odds, get_scores};
+use crate::odds_interface::logic::{
+    backtest::{format_backtest_table, run_backtest},
+    event::Event,
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod backtest_test;
+
+/// Saves odds for `sport_key` from the historical endpoint every
+/// `backtest.historical_interval_minutes` from `from` to `to`, or every
+/// snapshot if they are further apart, returning how many were saved
+pub fn fetch_historical_snapshots(
+    client: &OddsApiClient,
+    config: &Config,
+    store: &mut SnapshotStore,
+    sport_key: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> usize {
+    let interval = Duration::minutes(config.backtest.historical_interval_minutes.max(1));
+    let mut date = from;
+    let mut num_saved = 0;
+    while (date <= to) {
+        let historical =
+            match get_historical_odds(client, sport_key, &config.markets, &config.regions, date) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to get historical odds at {date}: {e}");
+                    break;
+                }
+            };
+
+        match store.save_events(&historical.data, historical.timestamp) {
+            Ok(_) => num_saved += 1,
+            Err(e) => eprintln!("Failed to save historical odds at {date}: {e}"),
+        }
+        // snapshots are only taken every few minutes, so a shorter interval
+        // would fetch the same one again
+        date = match historical.next_timestamp {
+            Some(next) => next.max(historical.timestamp + interval),
+            None => break,
+        };
+    }
+    return num_saved;
+}
+
+/// Saves the results of `sport_key` games completed in the last 3 days,
+/// returning how many were saved
+pub fn save_recent_results(
+    client: &OddsApiClient,
+    store: &mut SnapshotStore,
+    sport_key: &str,
+) -> usize {
+    let scores = match get_scores(client, sport_key, Some(3)) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to get scores for {sport_key:?}: {e}");
+            return 0;
+        }
+    };
+
+    match store.save_results(&scores) {
+        Ok(x) => return x,
+        Err(e) => {
+            eprintln!("Failed to save results: {e}");
+            return 0;
+        }
+    }
+}
+
+/// Replays every stored snapshot of `sport_key` with the configured scan
+/// settings and each backtest parameter set, settling against the stored
+/// results, and compares them
+pub fn backtest_report(config: &Config, store: &SnapshotStore, sport_key: &str) -> String {
+    let snapshots: Vec<Event> = match store.snapshots_for_sport(sport_key) {
+        Ok(x) => x.into_iter().map(|x| x.event).collect(),
+        Err(e) => return format!("Failed to read snapshots: {e}"),
+    };
+    let results = match store.results_for_sport(sport_key) {
+        Ok(x) => x,
+        Err(e) => return format!("Failed to read results: {e}"),
+    };
+
+    let backtest = &config.backtest;
+    let bankroll = config.staking.bankroll;
+    let mut reports = vec![run_backtest(
+        "configured",
+        &snapshots,
+        &results,
+        &config.scan,
+        backtest.stake,
+        bankroll,
+    )];
+    for parameters in &backtest.parameter_sets {
+        reports.push(run_backtest(
+            &parameters.name,
+            &snapshots,
+            &results,
+            &parameters.apply(&config.scan),
+            backtest.stake,
+            bankroll,
+        ));
+    }
+    return format_backtest_table(&reports);
+}
@@ -0,0 +1,57 @@
+use std::{env, fs};
+
+use chrono::{TimeZone, Utc};
+
+use crate::commands::backtest::{fetch_historical_snapshots, save_recent_results};
+use crate::commands::calibration::{calibration_predictions, calibration_report};
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_calibration_report_from_mock_history() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = OddsApiClient::with_base_url("mock-key", &server.odds_host_base());
+    let output_dir =
+        env::temp_dir().join(format!("arb_finder_calibration_{0}", std::process::id()));
+    let _ = fs::remove_dir_all(&output_dir);
+
+    let mut config = get_replay_config();
+    config.calibration.output_dir = output_dir.clone();
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let sports = [String::from("aussierules_afl")];
+
+    let from = Utc.with_ymd_and_hms(2024, 8, 4, 5, 40, 0).unwrap();
+    fetch_historical_snapshots(&client, &config, &mut store, &sports[0], from, from);
+    // nothing to score without results
+    assert!(calibration_predictions(&config, &store, &sports).is_empty());
+
+    save_recent_results(&client, &mut store, &sports[0]);
+    let predictions = calibration_predictions(&config, &store, &sports);
+    assert!(!predictions.is_empty());
+
+    let report = calibration_report(&config, &predictions);
+    assert!(report.contains("bookmaker"));
+    assert!(report.contains("devig_method"));
+    assert!(report.contains("h2h consensus reliability"));
+
+    let csv = fs::read_to_string(output_dir.join("calibration.csv")).unwrap();
+    assert!(csv.lines().any(|x| x.starts_with("sport,aussierules_afl,")));
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_dir.join("calibration.json")).unwrap())
+            .unwrap();
+    assert!(json["market"]["h2h"]["brier_score"].as_f64().unwrap() > 0.0);
+    let _ = fs::remove_dir_all(&output_dir);
+}
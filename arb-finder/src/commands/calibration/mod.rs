@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, fs};
+
+use crate::config::Config;
+use crate::odds_interface::logic::{
+    calibration::{
+        closing_snapshots, format_calibration_csv, format_calibration_table,
+        format_reliability_chart, predictions_for, summarise_calibration, summarise_calibration_by,
+        CalibrationSummary, Prediction, CONSENSUS_KEY,
+    },
+    event::Event,
+    market::MarketType,
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod calibration_test;
+
+/// Fair probabilities from the last snapshot before each finished event in
+/// `sports`, with whether each outcome happened
+pub fn calibration_predictions(
+    config: &Config,
+    store: &SnapshotStore,
+    sports: &[String],
+) -> Vec<Prediction> {
+    let mut predictions = Vec::new();
+    for sport_key in sports {
+        let snapshots: Vec<Event> = match store.snapshots_for_sport(sport_key) {
+            Ok(x) => x.into_iter().map(|x| x.event).collect(),
+            Err(e) => {
+                eprintln!("Failed to read snapshots for {sport_key:?}: {e}");
+                continue;
+            }
+        };
+        let results = match store.results_for_sport(sport_key) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Failed to read results for {sport_key:?}: {e}");
+                continue;
+            }
+        };
+
+        for event in closing_snapshots(&snapshots) {
+            if let Some(score) = results.iter().find(|x| x.id == event.id()) {
+                predictions.append(&mut predictions_for(event, score, &config.scan));
+            }
+        }
+    }
+    return predictions;
+}
+
+/// Brier score and log loss by sport, market, bookmaker and de-vig method,
+/// written to `calibration.output_dir` as CSV and JSON, with ASCII
+/// reliability charts of the consensus. Only the de-vig method configured
+/// for each market is counted outside the de-vig method table.
+pub fn calibration_report(config: &Config, predictions: &[Prediction]) -> String {
+    let num_bins = config.calibration.num_bins;
+    let all: Vec<&Prediction> = predictions.iter().collect();
+    let configured: Vec<&Prediction> = predictions
+        .iter()
+        .filter(|x| x.devig_method == config.scan.devig.method_for(&x.sport_key, &x.market_key))
+        .collect();
+
+    let mut groupings: BTreeMap<String, BTreeMap<String, CalibrationSummary>> = BTreeMap::new();
+    groupings.insert(
+        String::from("sport"),
+        summarise_calibration_by(&configured, num_bins, |x| x.sport_key.clone()),
+    );
+    groupings.insert(
+        String::from("market"),
+        summarise_calibration_by(&configured, num_bins, |x| x.market_key.to_string()),
+    );
+    groupings.insert(
+        String::from("bookmaker"),
+        summarise_calibration_by(&configured, num_bins, |x| x.bookmaker_key.clone()),
+    );
+    groupings.insert(
+        String::from("devig_method"),
+        summarise_calibration_by(&all, num_bins, |x| x.devig_method.to_string()),
+    );
+
+    let mut report: Vec<String> = groupings
+        .iter()
+        .map(|(title, summaries)| format_calibration_table(title, summaries))
+        .collect();
+    for market in [MarketType::H2h, MarketType::Totals, MarketType::Spreads] {
+        let consensus: Vec<&Prediction> = configured
+            .iter()
+            .filter(|x| x.bookmaker_key == CONSENSUS_KEY && x.market_key == market)
+            .cloned()
+            .collect();
+        if (!consensus.is_empty()) {
+            report.push(format_reliability_chart(
+                &format!("{market} consensus reliability"),
+                &summarise_calibration(&consensus, num_bins),
+            ));
+        }
+    }
+
+    let output_dir = &config.calibration.output_dir;
+    let written = fs::create_dir_all(output_dir)
+        .and_then(|_| {
+            return fs::write(
+                output_dir.join("calibration.csv"),
+                format_calibration_csv(&groupings),
+            );
+        })
+        .and_then(|_| {
+            let json = serde_json::to_string_pretty(&groupings)?;
+            return fs::write(output_dir.join("calibration.json"), json);
+        });
+    match written {
+        Ok(_) => report.push(format!("reports written to {0:?}", output_dir)),
+        Err(e) => report.push(format!("Failed to write reports to {0:?}: {e}", output_dir)),
+    }
+    return report.join("\n");
+}
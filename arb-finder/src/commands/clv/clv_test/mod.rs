@@ -0,0 +1,75 @@
+use std::{env, fs};
+
+use chrono::Duration;
+
+use crate::commands::clv::{capture_closing_lines, clv_report};
+use crate::commands::scan::scan_sports;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_closing_line_value_from_mock_scan() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let path = env::temp_dir().join(format!("arb_finder_clv_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    config.snapshot_db = Some(path.clone());
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+
+    let results = scan_sports(&client, &config, &[String::from("aussierules_afl")]);
+    let messages = results.messages(&config.odds_format);
+    let mut store = SnapshotStore::open(&path).unwrap();
+    let tracked = store.opportunities().unwrap();
+    // each opportunity is only tracked once however often it is alerted
+    assert!(!tracked.is_empty());
+    assert!(tracked.len() <= messages.len());
+
+    let first_start = tracked.iter().map(|x| x.commence_time).min().unwrap();
+    let num_closing = tracked
+        .iter()
+        .filter(|x| x.commence_time == first_start)
+        .count();
+    let requests_used = server.requests_used();
+    let num_closed = capture_closing_lines(
+        &client,
+        &config,
+        &mut store,
+        std::slice::from_ref(&results),
+        first_start - Duration::minutes(5),
+    );
+    assert_eq!(num_closed, num_closing);
+    // the scan had already fetched them
+    assert_eq!(server.requests_used(), requests_used);
+    assert_eq!(
+        store.unclosed_opportunities().unwrap().len(),
+        tracked.len() - num_closing
+    );
+
+    // the fixtures haven't moved, so every price still beats the close
+    let closed = store.opportunities().unwrap();
+    assert!(closed
+        .iter()
+        .filter_map(|x| x.clv_percent())
+        .all(|x| x > 0.0));
+
+    let report = clv_report(&store);
+    assert!(report.contains("aussierules_afl"));
+    assert!(report.contains("h2h"));
+
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+
+use super::scan::ScanResults;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{client::OddsApiClient, get_odds_for_sport};
+use crate::odds_interface::logic::{
+    clv::{format_clv_table, summarise_clv_by, TrackedOpportunity},
+    market::MarketType,
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod clv_test;
+
+/// Fetches the events starting within the capture window that have
+/// opportunities waiting on a closing line, unless they are among the
+/// events just `fetched`, and stores the consensus fair odds of each as at
+/// `now`. Returns how many opportunities were closed.
+pub fn capture_closing_lines(
+    client: &OddsApiClient,
+    config: &Config,
+    store: &mut SnapshotStore,
+    scans: &[ScanResults],
+    now: DateTime<Utc>,
+) -> usize {
+    let unclosed = match store.unclosed_opportunities() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to read tracked opportunities: {e}");
+            return 0;
+        }
+    };
+
+    let closing: Vec<&TrackedOpportunity> = unclosed
+        .iter()
+        .filter(|x| config.clv.is_closing(x.commence_time, now))
+        .collect();
+    let sport_keys: BTreeSet<&str> = closing.iter().map(|x| x.sport_key.as_str()).collect();
+
+    let mut num_closed = 0;
+    for sport_key in sport_keys {
+        let sport_opportunities: Vec<&&TrackedOpportunity> = closing
+            .iter()
+            .filter(|x| x.sport_key == sport_key)
+            .collect();
+        let mut markets: Vec<MarketType> = Vec::new();
+        for opportunity in &sport_opportunities {
+            if (!markets.contains(&opportunity.market_key)) {
+                markets.push(opportunity.market_key.clone());
+            }
+        }
+
+        // a poll may have fetched every market of these events already
+        let reusable = scans.iter().find(|scan| {
+            return scan.fetched_all(sport_key, &markets, &config.regions)
+                && sport_opportunities
+                    .iter()
+                    .all(|x| scan.events.iter().any(|event| event.id() == x.event_id));
+        });
+        let fetched_now;
+        let events = match reusable {
+            Some(scan) => &scan.events[..],
+            None => {
+                fetched_now = match get_odds_for_sport(client, sport_key, &markets, &config.regions)
+                {
+                    Ok(x) => x,
+                    Err(e) => {
+                        eprintln!("Failed to get closing odds for {sport_key:?}: {e}");
+                        continue;
+                    }
+                };
+                if let Err(e) = store.save_events(&fetched_now, now) {
+                    eprintln!("Failed to save closing odds snapshot: {e}");
+                }
+                &fetched_now[..]
+            }
+        };
+
+        for opportunity in sport_opportunities {
+            let event = match events.iter().find(|x| x.id() == opportunity.event_id) {
+                Some(x) => x,
+                None => continue,
+            };
+            let closing_odds = match event.fair_odds_for(
+                &opportunity.market_key,
+                &opportunity.outcome_key,
+                opportunity.point,
+                &config.scan,
+            ) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            match store.set_closing_odds(opportunity.id.unwrap(), &closing_odds, now) {
+                Ok(_) => num_closed += 1,
+                Err(e) => eprintln!("Failed to store closing odds: {e}"),
+            }
+        }
+    }
+    return num_closed;
+}
+
+/// How the tracked opportunities fared against the closing line, by sport,
+/// market, pricing model and bookmaker
+pub fn clv_report(store: &SnapshotStore) -> String {
+    let opportunities = match store.opportunities() {
+        Ok(x) => x,
+        Err(e) => return format!("Failed to read tracked opportunities: {e}"),
+    };
+
+    return [
+        format_clv_table(
+            "sport",
+            &summarise_clv_by(&opportunities, |x| x.sport_key.clone()),
+        ),
+        format_clv_table(
+            "market",
+            &summarise_clv_by(&opportunities, |x| x.market_key.to_string()),
+        ),
+        format_clv_table(
+            "model",
+            &summarise_clv_by(&opportunities, |x| x.model.to_string()),
+        ),
+        format_clv_table(
+            "bookmaker",
+            &summarise_clv_by(&opportunities, |x| x.bookmaker_key.clone()),
+        ),
+    ]
+    .join("\n");
+}
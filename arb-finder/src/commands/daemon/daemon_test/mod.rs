@@ -0,0 +1,176 @@
+use std::{
+    env, fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use chrono::{Duration, TimeZone, Utc};
+
+use crate::cli::ExitStatus;
+use crate::commands::daemon::{daemon_cycle, run_daemon};
+use crate::config::Config;
+use crate::daemon::{PollTier, Scheduler};
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFailure, MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_daemon_cycle_polls_on_schedule() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+    let sports = [String::from("aussierules_afl")];
+    let mut scheduler = Scheduler::new(&sports, &config.daemon, 2);
+
+    // an hour before the start, when it would be polled every 10 minutes
+    let now = Utc.with_ymd_and_hms(2024, 8, 4, 5, 40, 0).unwrap();
+    let mut out = Vec::new();
+    let num_polled = daemon_cycle(&client, &config, &mut scheduler, false, now, &mut out).unwrap();
+    assert_eq!(num_polled, 1);
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("aussierules_afl: "));
+    // the events check is free
+    assert_eq!(server.requests_used(), 2);
+
+    // 498 requests left for the rest of the month won't stretch to a poll
+    // every 10 minutes, but the 30 before the start are kept for the polls
+    // every 2 minutes once it's half an hour away
+    assert!(scheduler.stretch() > 10.0);
+    assert_eq!(scheduler.next_wake(now), now + Duration::minutes(30));
+    let mut out = Vec::new();
+    let mut later = now + Duration::minutes(10);
+    assert_eq!(
+        daemon_cycle(&client, &config, &mut scheduler, false, later, &mut out),
+        Ok(0)
+    );
+
+    let kickoff = now + Duration::minutes(60);
+    let mut polls = 1;
+    let mut last_poll = now;
+    loop {
+        later = scheduler.next_wake(later);
+        if (later >= kickoff) {
+            break;
+        }
+        let num_polled =
+            daemon_cycle(&client, &config, &mut scheduler, false, later, &mut out).unwrap();
+        if (num_polled > 0) {
+            polls += num_polled;
+            last_poll = later;
+        }
+    }
+    assert_eq!(polls, 16);
+    assert_eq!(last_poll, kickoff - Duration::minutes(2));
+    assert_eq!(server.requests_used(), 2 * polls as i32);
+
+    // a rejected key stops the daemon
+    server.set_failure(Some(MockFailure::Unauthorized));
+    let mut scheduler = Scheduler::new(&sports, &config.daemon, 2);
+    assert_eq!(
+        daemon_cycle(&client, &config, &mut scheduler, false, now, &mut out),
+        Err(ExitStatus::Unauthorized)
+    );
+}
+
+#[test]
+fn test_daemon_cycle_captures_closing_lines() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let path = env::temp_dir().join(format!("arb_finder_capture_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    config.snapshot_db = Some(path.clone());
+    // polled too rarely to be polled again before the start
+    config.daemon.poll_tiers = vec![PollTier {
+        within_minutes: 24 * 60,
+        interval_minutes: 600,
+    }];
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+    let sports = [String::from("aussierules_afl")];
+    let mut scheduler = Scheduler::new(&sports, &config.daemon, 2);
+
+    let now = Utc.with_ymd_and_hms(2024, 8, 4, 5, 40, 0).unwrap();
+    let mut out = Vec::new();
+    assert_eq!(
+        daemon_cycle(&client, &config, &mut scheduler, false, now, &mut out),
+        Ok(1)
+    );
+    let store = SnapshotStore::open(&path).unwrap();
+    let num_tracked = store.unclosed_opportunities().unwrap().len();
+    assert!(num_tracked > 0);
+
+    // a capture is scheduled as the game enters the capture window, and
+    // counted against the quota
+    let capture_at = Utc.with_ymd_and_hms(2024, 8, 4, 6, 30, 0).unwrap();
+    assert_eq!(scheduler.sports()[0].next_capture, Some(capture_at));
+    assert_eq!(scheduler.next_wake(now), capture_at);
+    assert_eq!(
+        daemon_cycle(
+            &client,
+            &config,
+            &mut scheduler,
+            false,
+            capture_at,
+            &mut out
+        ),
+        Ok(0)
+    );
+    // just the markets of the opportunities are fetched
+    assert_eq!(server.requests_used(), 3);
+    assert!(store.unclosed_opportunities().unwrap().is_empty());
+    assert_eq!(scheduler.sports()[0].next_capture, None);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_run_daemon_writes_status_until_shutdown() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+    let path = env::temp_dir().join(format!("arb_finder_daemon_{0}.json", std::process::id()));
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let stopper = shutdown.clone();
+    thread::spawn(move || {
+        thread::sleep(std::time::Duration::from_millis(200));
+        stopper.store(true, Ordering::SeqCst);
+    });
+    let status = run_daemon(
+        &client,
+        &config,
+        &[String::from("aussierules_afl")],
+        false,
+        &path,
+        &shutdown,
+        &mut Vec::new(),
+    );
+    assert_eq!(status, ExitStatus::Success);
+
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(written["running"], false);
+    // the fixture's game is long over, so nothing was polled
+    assert_eq!(written["polls"], 0);
+    assert!(written["sports"][0]["events_checked_at"].is_string());
+    assert_eq!(server.requests_used(), 0);
+}
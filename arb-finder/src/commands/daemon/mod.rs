@@ -0,0 +1,189 @@
+use std::{
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::alerts::alert_messages;
+use super::clv::capture_closing_lines;
+use super::scan::scan_sports;
+use super::{notify, open_store, write_output};
+use crate::cli::ExitStatus;
+use crate::config::Config;
+use crate::daemon::{sleep_until, write_status, DaemonStatus, Scheduler};
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient, error::OddsApiError, get_events, quota::estimate_cost,
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod daemon_test;
+
+/// Tells `scheduler` when each sport's events with opportunities waiting on
+/// a closing line enter the capture window
+fn schedule_captures(
+    config: &Config,
+    store: &SnapshotStore,
+    scheduler: &mut Scheduler,
+    now: DateTime<Utc>,
+) {
+    let unclosed = match store.unclosed_opportunities() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to read tracked opportunities: {e}");
+            return;
+        }
+    };
+
+    let window = Duration::minutes(config.clv.capture_window_minutes);
+    let sport_keys: Vec<String> = scheduler
+        .sports()
+        .iter()
+        .map(|x| x.sport_key.clone())
+        .collect();
+    for sport_key in sport_keys {
+        let capture_times: Vec<DateTime<Utc>> = unclosed
+            .iter()
+            .filter(|x| x.sport_key == sport_key && x.commence_time > now)
+            .map(|x| x.commence_time - window)
+            .collect();
+        scheduler.set_captures(&sport_key, &capture_times);
+    }
+}
+
+/// One pass of the daemon at `now`: checks the events of sports due a
+/// check, polls the sports due a poll, then captures any closing lines due.
+/// Returns how many sports were polled, or the status to stop with if the
+/// api key was rejected.
+pub fn daemon_cycle(
+    client: &OddsApiClient,
+    config: &Config,
+    scheduler: &mut Scheduler,
+    notify_results: bool,
+    now: DateTime<Utc>,
+    out: &mut dyn Write,
+) -> Result<usize, ExitStatus> {
+    for sport_key in scheduler.events_due(now) {
+        match get_events(client, &sport_key) {
+            Ok(events) => {
+                let commence_times: Vec<DateTime<Utc>> =
+                    events.iter().map(|x| x.commence_time()).collect();
+                scheduler.set_events(&sport_key, &commence_times, now);
+            }
+            Err(e) => {
+                eprintln!("Failed to get events for {sport_key:?}: {e}");
+                if let OddsApiError::Unauthorized { .. } = e {
+                    return Err(ExitStatus::Unauthorized);
+                }
+                scheduler.set_events_error(&sport_key, &e.to_string(), now);
+            }
+        }
+    }
+    let mut store = match config.snapshot_db {
+        Some(_) => open_store(config).ok(),
+        None => None,
+    };
+    if let Some(store) = &store {
+        schedule_captures(config, store, scheduler, now);
+    }
+    scheduler.reschedule(
+        client.quota().available(now),
+        client.quota().sustainable_rate(now),
+        now,
+    );
+
+    let polls_due = scheduler.polls_due(now);
+    let mut scans = Vec::new();
+    for sport_key in &polls_due {
+        let results = scan_sports(client, config, std::slice::from_ref(sport_key));
+        match results.status {
+            ExitStatus::Success => {
+                scheduler.set_polled(sport_key, results.opportunities.len(), now)
+            }
+            ExitStatus::Unauthorized => return Err(ExitStatus::Unauthorized),
+            x => scheduler.set_poll_error(sport_key, &format!("odds request failed: {x:?}"), now),
+        }
+        if (notify_results) {
+            notify(config, &alert_messages(config, &results, now));
+        }
+        let line = format!(
+            "{0} {sport_key}: {1} opportunities, {2} steam moves\n",
+            now.format("%Y-%m-%d %H:%M:%S"),
+            results.opportunities.len(),
+            results.steam_moves.len()
+        );
+        write_output(out, &line);
+        scans.push(results);
+    }
+
+    // closing lines are captured from the polls just made, or fetched if a
+    // capture is due without one
+    let captures_due = scheduler.captures_due(now);
+    if let Some(store) = &mut store {
+        if (!polls_due.is_empty() || !captures_due.is_empty()) {
+            capture_closing_lines(client, config, store, &scans, now);
+        }
+        for sport_key in &captures_due {
+            scheduler.set_captured(sport_key, now);
+        }
+        schedule_captures(config, store, scheduler, now);
+    }
+    scheduler.reschedule(
+        client.quota().available(now),
+        client.quota().sustainable_rate(now),
+        now,
+    );
+    return Ok(polls_due.len());
+}
+
+/// Runs `daemon_cycle` whenever there's something to do until `shutdown`
+/// is set, writing the schedule to `status_file` after each
+pub fn run_daemon(
+    client: &OddsApiClient,
+    config: &Config,
+    sports: &[String],
+    notify_results: bool,
+    status_file: &Path,
+    shutdown: &AtomicBool,
+    out: &mut dyn Write,
+) -> ExitStatus {
+    let poll_cost = estimate_cost(&config.markets, &config.regions);
+    let mut scheduler = Scheduler::new(sports, &config.daemon, poll_cost);
+    let started_at = Utc::now();
+    let mut polls = 0;
+    let mut status = ExitStatus::Success;
+
+    let write_daemon_status = |scheduler: &Scheduler, polls: usize, running: bool| {
+        let daemon_status = DaemonStatus {
+            pid: std::process::id(),
+            started_at,
+            updated_at: Utc::now(),
+            running,
+            polls,
+            requests_remaining: client.quota().latest_usage().map(|x| x.requests_remaining),
+            quota_stretch: scheduler.stretch(),
+            sports: scheduler.sports(),
+        };
+        if let Err(e) = write_status(status_file, &daemon_status) {
+            eprintln!("Failed to write status file {0:?}: {e}", status_file);
+        }
+    };
+
+    while (!shutdown.load(Ordering::SeqCst)) {
+        let now = Utc::now();
+        match daemon_cycle(client, config, &mut scheduler, notify_results, now, out) {
+            Ok(x) => polls += x,
+            Err(x) => {
+                status = x;
+                break;
+            }
+        }
+        write_daemon_status(&scheduler, polls, true);
+        sleep_until(scheduler.next_wake(now), shutdown);
+    }
+
+    write_daemon_status(&scheduler, polls, false);
+    return status;
+}
@@ -0,0 +1,40 @@
+use serde_json::json;
+
+use crate::cli::{HistoryArgs, Output};
+use crate::storage::{SnapshotStore, StorageError};
+
+/// Stored prices of one outcome if an event is given, otherwise the stored
+/// events of the sport
+pub fn history_output(store: &SnapshotStore, args: &HistoryArgs) -> Result<Output, StorageError> {
+    let (Some(event_id), Some(market), Some(outcome)) = (&args.event, &args.market, &args.outcome)
+    else {
+        let sport_key = args.sport.clone().unwrap_or_default();
+        let mut rows = Output::new(&["id", "commence_time", "home_team", "away_team", "snapshots"]);
+        for event in store.events_for_sport(&sport_key)? {
+            rows.push(json!({
+                "id": event.id,
+                "commence_time": event.commence_time,
+                "home_team": event.home_team,
+                "away_team": event.away_team,
+                "snapshots": event.snapshot_count,
+            }));
+        }
+        return Ok(rows);
+    };
+
+    let mut rows = Output::new(&["fetched_at", "bookmaker", "last_update", "point", "odds"]);
+    let history = store.price_history(event_id, market, outcome, args.bookmaker.as_deref())?;
+    for price in history {
+        rows.push(json!({
+            "fetched_at": price.fetched_at,
+            "bookmaker": price.bookmaker_key,
+            "bookmaker_title": price.bookmaker_title,
+            "last_update": price.last_update,
+            "market": price.market_key.to_string(),
+            "outcome": price.outcome_name,
+            "point": price.point,
+            "odds": price.odds.get_decimal(),
+        }));
+    }
+    return Ok(rows);
+}
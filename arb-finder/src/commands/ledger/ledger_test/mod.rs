@@ -0,0 +1,64 @@
+use chrono::{TimeZone, Utc};
+
+use crate::commands::ledger::{ledger_report, settle_pending_bets};
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{
+    ledger::{Bet, BetStatus},
+    market::MarketType,
+    odds::Odds,
+};
+use crate::storage::SnapshotStore;
+
+#[test]
+fn test_settle_bets_from_mock_scores() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = OddsApiClient::with_base_url("mock-key", &server.odds_host_base());
+
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let bet = Bet {
+        id: None,
+        event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+        sport_key: String::from("aussierules_afl"),
+        home_team: String::from("St Kilda Saints"),
+        away_team: String::from("Brisbane Lions"),
+        commence_time: Utc.with_ymd_and_hms(2024, 8, 4, 6, 40, 0).unwrap(),
+        market_key: MarketType::H2h,
+        outcome_key: String::from("St Kilda Saints"),
+        point: None,
+        bookmaker_key: String::from("sportsbet"),
+        odds: Odds::Decimal(2.3),
+        stake: 10.0,
+        placed_at: Utc.with_ymd_and_hms(2024, 8, 4, 5, 20, 0).unwrap(),
+        status: BetStatus::Pending,
+        settled_at: None,
+    };
+    store.record_bet(&bet).unwrap();
+    // no scores are fetched for a sport whose bets haven't started
+    store
+        .record_bet(&Bet {
+            event_id: String::from("not-started"),
+            sport_key: String::from("baseball_kbo"),
+            commence_time: Utc.with_ymd_and_hms(2024, 8, 6, 9, 30, 0).unwrap(),
+            ..bet.clone()
+        })
+        .unwrap();
+
+    let now = Utc.with_ymd_and_hms(2024, 8, 5, 0, 0, 0).unwrap();
+    assert_eq!(settle_pending_bets(&client, &mut store, now), 1);
+    assert_eq!(store.bets().unwrap()[0].status, BetStatus::Won);
+    assert_eq!(store.bets().unwrap()[1].status, BetStatus::Pending);
+    // scores for the last 3 days cost 2
+    assert_eq!(server.requests_used(), 2);
+    assert!(server
+        .requests()
+        .iter()
+        .all(|x| !x.path.contains("baseball_kbo")));
+
+    let report = ledger_report(&store, 1000.0);
+    assert!(report.contains("aussierules_afl"));
+    assert!(report.contains("sportsbet"));
+    assert!(report.contains("13.00"));
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+
+use crate::cli::BetArgs;
+use crate::odds_interface::api_requests::{client::OddsApiClient, get_scores};
+use crate::odds_interface::logic::ledger::{format_summary_table, summarise_by, Bet, BetStatus};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod ledger_test;
+
+/// A bet on an event we have a snapshot of
+pub fn bet_from_args(store: &SnapshotStore, args: &BetArgs) -> Result<Bet, String> {
+    let event = match store.stored_event(&args.event) {
+        Ok(Some(x)) => x,
+        Ok(None) => {
+            return Err(String::from(
+                "no snapshot of that event, scan its sport first",
+            ))
+        }
+        Err(e) => return Err(format!("Failed to look up event: {e}")),
+    };
+
+    return Ok(Bet {
+        id: None,
+        event_id: event.id,
+        sport_key: event.sport_key,
+        home_team: event.home_team,
+        away_team: event.away_team,
+        commence_time: event.commence_time,
+        market_key: args.market.clone(),
+        outcome_key: args.outcome.clone(),
+        point: args.point,
+        bookmaker_key: args.bookmaker.clone(),
+        odds: args.odds,
+        stake: args.stake,
+        placed_at: Utc::now(),
+        status: BetStatus::Pending,
+        settled_at: None,
+    });
+}
+
+/// Grades pending bets against the last 3 days of scores for their sports,
+/// returning how many were settled. Bets on events that finished longer
+/// ago than that can't be graded from the api. Sports whose bets haven't
+/// started by `now` aren't fetched.
+pub fn settle_pending_bets(
+    client: &OddsApiClient,
+    store: &mut SnapshotStore,
+    now: DateTime<Utc>,
+) -> usize {
+    let pending = match store.pending_bets() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to read pending bets: {e}");
+            return 0;
+        }
+    };
+
+    let sport_keys: BTreeSet<&str> = pending
+        .iter()
+        .filter(|x| x.commence_time < now)
+        .map(|x| x.sport_key.as_str())
+        .collect();
+    let mut num_settled = 0;
+    for sport_key in sport_keys {
+        let scores = match get_scores(client, sport_key, Some(3)) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Failed to get scores for {sport_key:?}: {e}");
+                continue;
+            }
+        };
+        if let Err(e) = store.save_results(&scores) {
+            eprintln!("Failed to save results: {e}");
+        }
+
+        for bet in pending.iter().filter(|x| x.sport_key == sport_key) {
+            let status = match scores.iter().find(|x| x.id == bet.event_id) {
+                Some(score) => bet.grade(score),
+                None => continue,
+            };
+            if (status == BetStatus::Pending) {
+                continue;
+            }
+
+            match store.settle_bet(bet.id.unwrap(), status, Utc::now()) {
+                Ok(_) => num_settled += 1,
+                Err(e) => eprintln!("Failed to settle bet {0:?}: {e}", bet.id),
+            }
+        }
+    }
+    return num_settled;
+}
+
+/// P&L, ROI and yield tables for every bet in the ledger, by sport, market
+/// and bookmaker
+pub fn ledger_report(store: &SnapshotStore, bankroll: f64) -> String {
+    let bets = match store.bets() {
+        Ok(x) => x,
+        Err(e) => return format!("Failed to read bets: {e}"),
+    };
+
+    return [
+        format_summary_table(
+            "sport",
+            &summarise_by(&bets, bankroll, |x| x.sport_key.clone()),
+        ),
+        format_summary_table(
+            "market",
+            &summarise_by(&bets, bankroll, |x| x.market_key.to_string()),
+        ),
+        format_summary_table(
+            "bookmaker",
+            &summarise_by(&bets, bankroll, |x| x.bookmaker_key.clone()),
+        ),
+    ]
+    .join("\n");
+}
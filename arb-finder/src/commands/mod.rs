@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use crate::cli::ExitStatus;
+use crate::config::Config;
+use crate::messaging::send_message;
+use crate::odds_interface::logic::backtest::ParameterSet;
+use crate::storage::SnapshotStore;
+
+pub mod alerts;
+pub mod api;
+pub mod arbitrage;
+pub mod backtest;
+pub mod calibration;
+pub mod clv;
+pub mod daemon;
+pub mod history;
+pub mod ledger;
+pub mod scan;
+pub mod sharpness;
+
+pub fn open_store(config: &Config) -> Result<SnapshotStore, ExitStatus> {
+    let path = match &config.snapshot_db {
+        Some(x) => x,
+        None => {
+            eprintln!("set snapshot_db in the config to keep a bet ledger and track closing lines");
+            return Err(ExitStatus::Config);
+        }
+    };
+
+    match SnapshotStore::open(path) {
+        Ok(x) => return Ok(x),
+        Err(e) => {
+            eprintln!("Failed to open snapshot database {0:?}: {e}", path);
+            return Err(ExitStatus::Failure);
+        }
+    }
+}
+
+/// `config` with `min_ev` as the EV cutoff of every sport
+pub fn with_min_ev(config: &Config, min_ev: Option<f64>) -> Config {
+    let mut config = config.clone();
+    let parameters = ParameterSet {
+        percent_ev_cutoff: min_ev,
+        ..Default::default()
+    };
+    config.scan = parameters.apply(&config.scan);
+    return config;
+}
+
+/// Posts each of `messages` to discord
+pub fn notify(config: &Config, messages: &[String]) -> ExitStatus {
+    let mut status = ExitStatus::Success;
+    for message in messages {
+        if let Err(e) = send_message(&config.discord_api_base, &config.credentials, message) {
+            eprintln!("Failed to send discord message: {e}");
+            status = ExitStatus::Failure;
+        }
+    }
+    return status;
+}
+
+pub fn write_output(out: &mut dyn Write, text: &str) -> ExitStatus {
+    match out.write_all(text.as_bytes()) {
+        Ok(_) => return ExitStatus::Success,
+        Err(e) => {
+            eprintln!("Failed to write output: {e}");
+            return ExitStatus::Failure;
+        }
+    }
+}
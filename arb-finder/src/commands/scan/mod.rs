@@ -0,0 +1,304 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+
+use crate::cli::{ExitStatus, Output};
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient, get_odds_for_sports, transport::Cassette,
+};
+use crate::odds_interface::logic::{
+    alerts::ScanCoverage,
+    bookmaker::Region,
+    clv::TrackedOpportunity,
+    event::{apply_staking, Event, Opportunity},
+    market::MarketType,
+    movement::{StaleQuote, SteamMove},
+    odds::OddsFormat,
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod scan_test;
+
+/// Saves `events` to the snapshot database, if one is configured, and
+/// returns the steam moves since each event's earliest snapshot within the
+/// movement window, or its latest before that
+pub fn save_snapshot(config: &Config, events: &[Event]) -> Vec<SteamMove> {
+    let path = match &config.snapshot_db {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+
+    let fetched_at = Utc::now();
+    let mut store = match SnapshotStore::open(path) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to open snapshot database {0:?}: {e}", path);
+            return Vec::new();
+        }
+    };
+
+    let window_start = fetched_at - Duration::minutes(config.scan.movement.window_minutes);
+    let mut steam_moves = Vec::new();
+    for event in events {
+        let previous = match store.earliest_snapshot_since(event.id(), window_start) {
+            Ok(None) => store.latest_snapshot(event.id()),
+            x => x,
+        };
+        match previous {
+            Ok(Some(previous)) => steam_moves.append(&mut event.find_steam_moves(
+                &previous.event,
+                fetched_at - previous.fetched_at,
+                &config.scan,
+            )),
+            Ok(None) => (),
+            Err(e) => eprintln!("Failed to read snapshot of {0:?}: {e}", event.id()),
+        }
+    }
+
+    if let Err(e) = store.save_events(events, fetched_at) {
+        eprintln!("Failed to save odds snapshot to {0:?}: {e}", path);
+    }
+    return steam_moves;
+}
+
+/// Starts tracking `opportunities` against the closing line, if a snapshot
+/// database is configured
+fn track_opportunities(config: &Config, opportunities: &[TrackedOpportunity]) {
+    let path = match &config.snapshot_db {
+        Some(x) => x,
+        None => return,
+    };
+
+    let mut store = match SnapshotStore::open(path) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to open snapshot database {0:?}: {e}", path);
+            return;
+        }
+    };
+    for opportunity in opportunities {
+        if let Err(e) = store.record_opportunity(opportunity) {
+            eprintln!("Failed to track opportunity: {e}");
+        }
+    }
+}
+
+/// What a scan found, and whether every sport could be fetched
+pub struct ScanResults {
+    /// What was fetched for each sport whose odds were
+    pub fetched: BTreeMap<String, ScanCoverage>,
+    /// Every event fetched, with its odds
+    pub events: Vec<Event>,
+    pub opportunities: Vec<Opportunity>,
+    pub steam_moves: Vec<SteamMove>,
+    pub found_at: DateTime<Utc>,
+    pub status: ExitStatus,
+}
+
+impl ScanResults {
+    /// Whether every one of `markets` in `regions` was fetched for
+    /// `sport_key`
+    pub fn fetched_all(&self, sport_key: &str, markets: &[MarketType], regions: &[Region]) -> bool {
+        return match self.fetched.get(sport_key) {
+            Some(x) => {
+                markets.iter().all(|market| x.markets.contains(market))
+                    && regions.iter().all(|region| x.regions.contains(region))
+            }
+            None => false,
+        };
+    }
+
+    /// Discord messages for every steam move and opportunity
+    pub fn messages(&self, odds_format: &OddsFormat) -> Vec<String> {
+        let mut messages: Vec<String> = self
+            .steam_moves
+            .iter()
+            .map(|x| x.format_message(odds_format))
+            .collect();
+        for opportunity in &self.opportunities {
+            messages.push(opportunity.format_message(odds_format));
+        }
+        return messages;
+    }
+
+    /// A row per opportunity, then per book that hasn't followed a steam
+    /// move
+    pub fn output(&self) -> Output {
+        let mut output = Output::new(&OPPORTUNITY_COLUMNS);
+        for opportunity in &self.opportunities {
+            output.push(opportunity_row(opportunity, self.found_at));
+        }
+        for steam_move in &self.steam_moves {
+            for quote in &steam_move.stale_quotes {
+                output.push(stale_quote_row(steam_move, quote));
+            }
+        }
+        return output;
+    }
+}
+
+const OPPORTUNITY_COLUMNS: [&str; 13] = [
+    "event_id",
+    "sport",
+    "matchup",
+    "commence_time",
+    "market",
+    "outcome",
+    "point",
+    "bookmaker",
+    "odds",
+    "true_odds",
+    "ev_percent",
+    "stake",
+    "model",
+];
+
+fn opportunity_row(opportunity: &Opportunity, found_at: DateTime<Utc>) -> Value {
+    let tracked = opportunity.track(found_at);
+    return json!({
+        "event_id": tracked.event_id,
+        "sport": tracked.sport_key,
+        "matchup": opportunity.matchup(),
+        "commence_time": tracked.commence_time,
+        "market": tracked.market_key.to_string(),
+        "outcome": tracked.outcome_key,
+        "point": tracked.point,
+        "bookmaker": tracked.bookmaker_key,
+        "odds": tracked.offered_odds.get_decimal(),
+        "true_odds": tracked.true_odds.get_decimal(),
+        "ev_percent": tracked.percent_ev,
+        "stake": opportunity.recommended_stake(),
+        "model": tracked.model.to_string(),
+        "devig_method": tracked.devig_method.to_string(),
+    });
+}
+
+/// A book still offering value against the price the rest of the market
+/// moved to
+fn stale_quote_row(steam_move: &SteamMove, quote: &StaleQuote) -> Value {
+    return json!({
+        "event_id": steam_move.event_id,
+        "sport": steam_move.sport_key,
+        "matchup": steam_move.matchup(),
+        "commence_time": steam_move.commence_time,
+        "market": steam_move.market_key.to_string(),
+        "outcome": steam_move.outcome_key,
+        "point": steam_move.point,
+        "bookmaker": quote.bookie_key,
+        "odds": quote.offered_odds.get_decimal(),
+        "true_odds": steam_move.post_move_fair.get_decimal(),
+        "ev_percent": quote.percent_ev,
+        "stake": Value::Null,
+        "model": "steam_move",
+        "direction": steam_move.direction.to_string(),
+    });
+}
+
+/// Opportunities in `event` with the scan settings, staked as configured
+pub fn event_opportunities(event: &Event, config: &Config) -> Vec<Opportunity> {
+    let mut opportunities = event.identify_opportunities_using(&config.scan);
+    apply_staking(&mut opportunities, &config.staking);
+    return opportunities;
+}
+
+/// Every +EV opportunity and steam move in `sports`. Sports that couldn't
+/// be fetched are reported and skipped.
+pub fn scan_sports(client: &OddsApiClient, config: &Config, sports: &[String]) -> ScanResults {
+    let mut results = ScanResults {
+        fetched: BTreeMap::new(),
+        events: Vec::new(),
+        opportunities: Vec::new(),
+        steam_moves: Vec::new(),
+        found_at: Utc::now(),
+        status: ExitStatus::Success,
+    };
+
+    let sport_odds = match get_odds_for_sports(
+        client,
+        sports,
+        &config.markets,
+        &config.regions,
+        config.max_concurrent_requests,
+    ) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to start odds requests: {e}");
+            results.status = ExitStatus::Failure;
+            return results;
+        }
+    };
+
+    let mut events_raw = Vec::new();
+    for odds in sport_odds {
+        match odds.odds {
+            Ok((mut x, plan)) => {
+                let coverage = ScanCoverage {
+                    markets: plan.markets,
+                    regions: plan.regions,
+                    bookmakers: x
+                        .iter()
+                        .flat_map(|event| event.bookmakers())
+                        .map(|bookie| bookie.key.clone())
+                        .collect(),
+                };
+                results.fetched.insert(odds.sport_key, coverage);
+                events_raw.append(&mut x);
+            }
+            Err(e) => {
+                eprintln!("Failed to get odds for {0:?}: {e}", odds.sport_key);
+                results.status = results.status.and(ExitStatus::from_api_error(&e));
+            }
+        }
+    }
+
+    results.steam_moves = save_snapshot(config, &events_raw);
+
+    let mut tracked = Vec::new();
+    for event in &events_raw {
+        for opportunity in event_opportunities(event, config) {
+            tracked.push(opportunity.track(results.found_at));
+            results.opportunities.push(opportunity);
+        }
+    }
+    track_opportunities(config, &tracked);
+    results.events = events_raw;
+    return results;
+}
+
+/// The opportunities in `events` read from a file, as if just scanned
+pub fn replay_results(config: &Config, events: &[Event]) -> ScanResults {
+    let mut results = ScanResults {
+        fetched: BTreeMap::new(),
+        events: Vec::new(),
+        opportunities: Vec::new(),
+        steam_moves: Vec::new(),
+        found_at: Utc::now(),
+        status: ExitStatus::Success,
+    };
+    for event in events {
+        results
+            .opportunities
+            .append(&mut event_opportunities(event, config));
+    }
+    return results;
+}
+
+/// Events in an odds response saved from the api, or in a cassette recorded
+/// of one, fetched when the cassette's response was sent
+pub fn read_odds_file(path: &Path) -> Result<Vec<Event>, String> {
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if let Ok(cassette) = serde_json::from_str::<Cassette>(&raw) {
+        let mut events = serde_json::from_str::<Vec<Event>>(&cassette.response.body)
+            .map_err(|e| e.to_string())?;
+        if let Some(fetched_at) = cassette.response.date() {
+            for event in events.iter_mut() {
+                event.set_fetched_at(fetched_at);
+            }
+        }
+        return Ok(events);
+    }
+    return serde_json::from_str::<Vec<Event>>(&raw).map_err(|e| e.to_string());
+}
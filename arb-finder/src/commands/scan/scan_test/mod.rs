@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use crate::commands::scan::scan_sports;
+use crate::config::Config;
+use crate::messaging::send_message;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFailure, MockFixtures, MockOddsApi},
+    transport::{TransportMode, TransportSettings},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+
+const CASSETTE_DIR: &str = "./example_data/cassettes";
+
+fn get_replay_client() -> OddsApiClient {
+    // the host doesn't matter, nothing is sent when replaying
+    return OddsApiClient::with_base_url("any-key", "http://replay.invalid/v4").with_transport(
+        TransportSettings {
+            mode: TransportMode::Replay,
+            cassette_dir: PathBuf::from(CASSETTE_DIR),
+            ..Default::default()
+        },
+    );
+}
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_replay_opportunity_scan() {
+    let config = get_replay_config();
+    let sports = [
+        String::from("aussierules_afl"),
+        // never recorded, so it fails without stopping the scan
+        String::from("baseball_kbo"),
+    ];
+
+    let messages =
+        scan_sports(&get_replay_client(), &config, &sports).messages(&config.odds_format);
+    assert!(!messages.is_empty());
+    assert!(messages.iter().all(|x| x.contains("AFL")));
+
+    // replaying is deterministic
+    let replayed =
+        scan_sports(&get_replay_client(), &config, &sports).messages(&config.odds_format);
+    assert_eq!(messages, replayed);
+}
+
+#[test]
+fn test_mock_server_scan_to_discord() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    server.set_sport_failure("baseball_kbo", MockFailure::Unauthorized);
+
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    config.discord_api_base = server.discord_api_base();
+    config.credentials.odds_api_key = String::from("mock-key");
+    config.credentials.discord_channel_id = String::from("1234");
+    let client =
+        OddsApiClient::with_base_url(&config.credentials.odds_api_key, &config.odds_host_base);
+
+    let sports = [
+        String::from("aussierules_afl"),
+        String::from("baseball_kbo"),
+    ];
+    let messages = scan_sports(&client, &config, &sports).messages(&config.odds_format);
+    assert!(!messages.is_empty());
+
+    for message in &messages {
+        send_message(&config.discord_api_base, &config.credentials, message).unwrap();
+    }
+    assert_eq!(server.discord_messages(), messages);
+    assert!(server
+        .requests()
+        .iter()
+        .any(|x| x.method == "POST" && x.path == "/api/channels/1234/messages"));
+
+    // one market per region charged for the sport that didn't fail
+    assert_eq!(server.requests_used(), 2);
+    assert_eq!(client.quota().latest_usage().unwrap().requests_used, 2);
+}
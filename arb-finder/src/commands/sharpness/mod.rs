@@ -0,0 +1,55 @@
+use crate::config::Config;
+use crate::odds_interface::logic::{
+    event::Event,
+    sharpness::{analyse_sharpness, format_sharpness_table, format_weights_toml, seed_weights},
+};
+use crate::storage::SnapshotStore;
+
+#[cfg(test)]
+mod sharpness_test;
+
+/// Bookmakers of each of `sports` ranked by how sharp their stored prices
+/// were, followed by the consensus weights that suggests as config entries
+pub fn sharpness_report(config: &Config, store: &SnapshotStore, sports: &[String]) -> String {
+    let mut weights = config.scan.bookmaker_weights.clone();
+    let mut report = Vec::new();
+    for sport_key in sports {
+        let snapshots: Vec<Event> = match store.snapshots_for_sport(sport_key) {
+            Ok(x) => x.into_iter().map(|x| x.event).collect(),
+            Err(e) => {
+                report.push(format!("Failed to read snapshots for {sport_key:?}: {e}"));
+                continue;
+            }
+        };
+        let results = match store.results_for_sport(sport_key) {
+            Ok(x) => x,
+            Err(e) => {
+                report.push(format!("Failed to read results for {sport_key:?}: {e}"));
+                continue;
+            }
+        };
+
+        let ranked = analyse_sharpness(&snapshots, &results, &config.scan);
+        if (ranked.is_empty()) {
+            report.push(format!("no stored snapshots of {sport_key:?}"));
+            continue;
+        }
+        report.push(format_sharpness_table(
+            sport_key,
+            &ranked,
+            &config.sharpness,
+        ));
+        seed_weights(&mut weights, sport_key, &ranked, &config.sharpness);
+    }
+
+    let learned: String = sports
+        .iter()
+        .map(|x| format_weights_toml(x, &weights))
+        .collect();
+    if (!learned.is_empty()) {
+        report.push(format!(
+            "learned weights, to add to your config:\n{learned}"
+        ));
+    }
+    return report.join("\n");
+}
@@ -0,0 +1,46 @@
+use chrono::{TimeZone, Utc};
+
+use crate::commands::backtest::{fetch_historical_snapshots, save_recent_results};
+use crate::commands::sharpness::sharpness_report;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    mock_server::{MockFixtures, MockOddsApi},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_sharpness_report_from_mock_history() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = OddsApiClient::with_base_url("mock-key", &server.odds_host_base());
+
+    let mut config = get_replay_config();
+    config.sharpness.min_predictions = 1;
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let sports = [
+        String::from("aussierules_afl"),
+        String::from("baseball_kbo"),
+    ];
+
+    let from = Utc.with_ymd_and_hms(2024, 8, 4, 5, 40, 0).unwrap();
+    fetch_historical_snapshots(&client, &config, &mut store, &sports[0], from, from);
+    save_recent_results(&client, &mut store, &sports[0]);
+
+    let report = sharpness_report(&config, &store, &sports);
+    let rows: Vec<&str> = report.lines().collect();
+    assert_eq!(rows[0], "aussierules_afl");
+    // a row per bookmaker, sportsbet's prices furthest from the result
+    assert!(rows[2].trim_start().starts_with("1 "));
+    assert!(rows[5].contains("sportsbet"));
+    assert!(report.contains("no stored snapshots of \"baseball_kbo\""));
+    assert!(report.contains("[scan.bookmaker_weights.sport_market_weights.aussierules_afl.h2h]"));
+}
@@ -0,0 +1,103 @@
+use std::{collections::HashMap, fs};
+
+use crate::config::{Config, ConfigError};
+use crate::odds_interface::logic::{
+    bookmaker::Region, devig::DevigMethod, market::MarketType, staking::StakingStrategy,
+};
+
+fn get_example_config() -> Config {
+    let raw = fs::read_to_string("./config.example.toml").expect("Unable to read file");
+    return Config::from_toml(&raw).expect("Example config is invalid");
+}
+
+#[test]
+fn test_example_config() {
+    let config = get_example_config();
+
+    assert_eq!(config.regions.len(), 4);
+    assert_eq!(config.credentials.odds_api_key, "My Odds API Key");
+    assert_eq!(config.scan.percent_ev_cutoff_for("baseball_kbo"), 7.5);
+    assert_eq!(config.scan.percent_ev_cutoff_for("aussierules_afl"), 5.0);
+    assert_eq!(
+        config
+            .scan
+            .devig
+            .method_for("aussierules_afl", &MarketType::Totals),
+        DevigMethod::Power
+    );
+    assert_eq!(
+        config
+            .scan
+            .bookmaker_weights
+            .weight_for("aussierules_afl", &MarketType::H2h, "sportsbet"),
+        1.5
+    );
+    assert_eq!(
+        config.staking.strategy,
+        StakingStrategy::FractionalKelly { fraction: 0.25 }
+    );
+}
+
+#[test]
+fn test_missing_sections_use_defaults() {
+    let config = Config::from_toml("sports = [\"baseball_kbo\"]").unwrap();
+
+    assert_eq!(config.sports, ["baseball_kbo"]);
+    assert_eq!(config.markets.len(), 3);
+    assert_eq!(config.scan.max_odds_cutoff, 10.0);
+    assert!(config.scan.bookmakers.allows("sportsbet"));
+}
+
+#[test]
+fn test_env_overrides() {
+    let mut config = get_example_config();
+    let env = HashMap::from([
+        ("ARB_FINDER_ODDS_API_KEY", "secret"),
+        ("ARB_FINDER_REGIONS", "au, uk"),
+        ("ARB_FINDER_BOOKMAKERS_DENY", "sportsbet"),
+        ("ARB_FINDER_PERCENT_EV_CUTOFF", "3.5"),
+    ]);
+
+    config
+        .apply_overrides(|name| env.get(name).map(|x| x.to_string()))
+        .unwrap();
+
+    assert_eq!(config.credentials.odds_api_key, "secret");
+    assert_eq!(config.regions, [Region::Au, Region::Uk]);
+    assert!(!config.scan.bookmakers.allows("sportsbet"));
+    assert_eq!(config.scan.percent_ev_cutoff_for("aussierules_afl"), 3.5);
+
+    let bad_env = HashMap::from([("ARB_FINDER_MARKETS", "h2h,moneyline")]);
+    assert!(config
+        .apply_overrides(|name| bad_env.get(name).map(|x| x.to_string()))
+        .is_err());
+}
+
+#[test]
+fn test_validate_rejects_unusable_timeouts() {
+    assert!(get_example_config().validate().is_ok());
+
+    for timeout in ["0.0", "-1.0", "nan", "inf", "1e300"] {
+        let config = Config::from_toml(&format!("[transport]\ntimeout_secs = {timeout}")).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { setting, .. }) if setting == "transport.timeout_secs"
+        ));
+    }
+}
+
+#[test]
+fn test_validate_rejects_degenerate_spread_models() {
+    for model in [
+        "{ model = \"normal\", std_dev = 0.0 }",
+        "{ model = \"normal\", std_dev = -12.0 }",
+        "{ model = \"skellam\", expected_total = 0.0 }",
+    ] {
+        let config =
+            Config::from_toml(&format!("[scan.spread_models]\nbasketball_nba = {model}")).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { setting, .. }) if setting == "scan.spread_models.basketball_nba"
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+use std::{env, fmt, fs, path::PathBuf, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+use crate::daemon::DaemonSettings;
+use crate::messaging::DISCORD_API_BASE;
+use crate::odds_interface::api_requests::{
+    client::DEFAULT_MAX_CONCURRENT_REQUESTS, quota::QuotaBudget, transport::TransportSettings,
+    ODDS_HOST_BASE,
+};
+use crate::odds_interface::logic::{
+    alerts::AlertSettings,
+    backtest::BacktestSettings,
+    bookmaker::Region,
+    calibration::CalibrationSettings,
+    clv::ClvSettings,
+    market::MarketType,
+    odds::OddsFormat,
+    settings::ScanSettings,
+    sharpness::SharpnessSettings,
+    staking::{StakingSettings, StakingStrategy},
+};
+
+#[cfg(test)]
+mod config_test;
+
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+/// Environment variable holding the path to the config file
+pub const CONFIG_PATH_VAR: &str = "ARB_FINDER_CONFIG";
+
+const ENV_PREFIX: &str = "ARB_FINDER_";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    InvalidEnv {
+        name: String,
+        value: String,
+    },
+    /// A setting that parsed but can't be used
+    Invalid {
+        setting: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config file: {e}"),
+            ConfigError::Parse(e) => write!(f, "could not parse config file: {e}"),
+            ConfigError::InvalidEnv { name, value } => {
+                write!(f, "invalid value {value:?} for environment variable {name}")
+            }
+            ConfigError::Invalid { setting, reason } => write!(f, "invalid {setting}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Credentials {
+    pub odds_api_key: String,
+    pub discord_api_key: String,
+    pub discord_channel_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub credentials: Credentials,
+    /// Base url of the odds api, e.g. to point at a local mock server
+    pub odds_host_base: String,
+    pub discord_api_base: String,
+    pub regions: Vec<Region>,
+    pub markets: Vec<MarketType>,
+    /// Sport keys to scan when none is given
+    pub sports: Vec<String>,
+    pub odds_format: OddsFormat,
+    /// Most odds api requests to have in flight at once
+    pub max_concurrent_requests: usize,
+    pub quota_budget: QuotaBudget,
+    /// Whether to call the API live, record responses or replay them
+    pub transport: TransportSettings,
+    pub scan: ScanSettings,
+    pub staking: StakingSettings,
+    /// SQLite database to save every fetched odds snapshot to, if any
+    pub snapshot_db: Option<PathBuf>,
+    pub clv: ClvSettings,
+    pub backtest: BacktestSettings,
+    pub calibration: CalibrationSettings,
+    pub sharpness: SharpnessSettings,
+    pub daemon: DaemonSettings,
+    /// When to alert on an opportunity again, and on it going
+    pub alerts: AlertSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Config {
+            credentials: Credentials::default(),
+            odds_host_base: ODDS_HOST_BASE.to_string(),
+            discord_api_base: DISCORD_API_BASE.to_string(),
+            regions: [Region::Us, Region::Uk, Region::Au, Region::Eu].to_vec(),
+            markets: [MarketType::H2h, MarketType::Spreads, MarketType::Totals].to_vec(),
+            sports: Vec::new(),
+            odds_format: OddsFormat::Decimal,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            quota_budget: QuotaBudget::default(),
+            transport: TransportSettings::default(),
+            scan: ScanSettings::default(),
+            staking: StakingSettings {
+                bankroll: 1000.0,
+                strategy: StakingStrategy::FractionalKelly { fraction: 0.25 },
+            },
+            snapshot_db: None,
+            clv: ClvSettings::default(),
+            backtest: BacktestSettings::default(),
+            calibration: CalibrationSettings::default(),
+            sharpness: SharpnessSettings::default(),
+            daemon: DaemonSettings::default(),
+            alerts: AlertSettings::default(),
+        };
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &str) -> Result<T, ConfigError> {
+    return value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| ConfigError::InvalidEnv {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+}
+
+fn parse_env_list<T: FromStr>(name: &str, value: &str) -> Result<Vec<T>, ConfigError> {
+    return value
+        .split(',')
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| parse_env(name, x))
+        .collect();
+}
+
+impl Config {
+    pub fn from_toml(raw: &str) -> Result<Config, ConfigError> {
+        return toml::from_str::<Config>(raw).map_err(ConfigError::Parse);
+    }
+
+    /// Reads the config file at `ARB_FINDER_CONFIG` (or `./config.toml`),
+    /// falling back to the defaults if there isn't one, then applies any
+    /// `ARB_FINDER_*` environment variable overrides
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = env::var(CONFIG_PATH_VAR).unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+
+        let mut config = match fs::read_to_string(&path) {
+            Ok(raw) => Self::from_toml(&raw)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(ConfigError::Io(e)),
+        };
+
+        config.apply_overrides(|name| env::var(name).ok())?;
+        config.validate()?;
+        return Ok(config);
+    }
+
+    /// Checks settings that would otherwise fail, or panic, once used
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let timeout_secs = self.transport.timeout_secs;
+        if (Duration::try_from_secs_f64(timeout_secs).is_err() || timeout_secs == 0.0) {
+            return Err(ConfigError::Invalid {
+                setting: String::from("transport.timeout_secs"),
+                reason: format!("{timeout_secs} is not a positive number of seconds"),
+            });
+        }
+        for (sport_key, model) in &self.scan.spread_models {
+            if (!model.is_valid()) {
+                return Err(ConfigError::Invalid {
+                    setting: format!("scan.spread_models.{sport_key}"),
+                    reason: format!("{model:?} needs a positive, finite parameter"),
+                });
+            }
+        }
+        return Ok(());
+    }
+
+    /// Overrides settings with values from `lookup`, which is given the full
+    /// variable name, e.g. `ARB_FINDER_ODDS_API_KEY`
+    pub fn apply_overrides<F: Fn(&str) -> Option<String>>(
+        &mut self,
+        lookup: F,
+    ) -> Result<(), ConfigError> {
+        let var = |suffix: &str| {
+            let name = format!("{ENV_PREFIX}{suffix}");
+            return lookup(&name).map(|value| (name, value));
+        };
+
+        if let Some((_, value)) = var("ODDS_API_KEY") {
+            self.credentials.odds_api_key = value;
+        }
+        if let Some((_, value)) = var("DISCORD_API_KEY") {
+            self.credentials.discord_api_key = value;
+        }
+        if let Some((_, value)) = var("DISCORD_CHANNEL_ID") {
+            self.credentials.discord_channel_id = value;
+        }
+        if let Some((_, value)) = var("ODDS_HOST_BASE") {
+            self.odds_host_base = value;
+        }
+        if let Some((_, value)) = var("DISCORD_API_BASE") {
+            self.discord_api_base = value;
+        }
+        if let Some((name, value)) = var("REGIONS") {
+            self.regions = parse_env_list(&name, &value)?;
+        }
+        if let Some((name, value)) = var("MARKETS") {
+            self.markets = parse_env_list(&name, &value)?;
+        }
+        if let Some((name, value)) = var("SPORTS") {
+            self.sports = parse_env_list(&name, &value)?;
+        }
+        if let Some((name, value)) = var("BOOKMAKERS_ALLOW") {
+            self.scan.bookmakers.allow = parse_env_list(&name, &value)?;
+        }
+        if let Some((name, value)) = var("BOOKMAKERS_DENY") {
+            self.scan.bookmakers.deny = parse_env_list(&name, &value)?;
+        }
+        if let Some((name, value)) = var("PERCENT_EV_CUTOFF") {
+            self.scan.percent_ev_cutoff = parse_env(&name, &value)?;
+        }
+        if let Some((name, value)) = var("MAX_ODDS_CUTOFF") {
+            self.scan.max_odds_cutoff = parse_env(&name, &value)?;
+        }
+        if let Some((name, value)) = var("MAX_QUOTE_AGE_MINUTES") {
+            self.scan.max_quote_age_minutes = parse_env(&name, &value)?;
+        }
+        if let Some((name, value)) = var("DAILY_REQUEST_BUDGET") {
+            self.quota_budget.daily = Some(parse_env(&name, &value)?);
+        }
+        if let Some((name, value)) = var("MONTHLY_REQUEST_BUDGET") {
+            self.quota_budget.monthly = Some(parse_env(&name, &value)?);
+        }
+        if let Some((name, value)) = var("TRANSPORT_MODE") {
+            self.transport.mode = parse_env(&name, &value)?;
+        }
+        if let Some((_, value)) = var("CASSETTE_DIR") {
+            self.transport.cassette_dir = value.into();
+        }
+        if let Some((name, value)) = var("BANKROLL") {
+            self.staking.bankroll = parse_env(&name, &value)?;
+        }
+        if let Some((_, value)) = var("SNAPSHOT_DB") {
+            self.snapshot_db = Some(value.into());
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{env, fs, sync::atomic::AtomicBool};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crate::config::Config;
+use crate::daemon::{sleep_until, write_status, DaemonSettings, DaemonStatus, Scheduler};
+
+fn get_now() -> DateTime<Utc> {
+    return Utc.with_ymd_and_hms(2024, 8, 4, 4, 0, 0).unwrap();
+}
+
+fn get_sports() -> Vec<String> {
+    return ["aussierules_afl", "rugbyleague_nrl", "baseball_kbo"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+}
+
+#[test]
+fn test_poll_interval_tightens_near_the_start() {
+    let settings = DaemonSettings::default();
+    let now = get_now();
+    let interval = |minutes_away: i64| {
+        return settings
+            .poll_interval(Some(now + Duration::minutes(minutes_away)), now)
+            .map(|x| x.num_minutes());
+    };
+
+    assert_eq!(interval(10), Some(2));
+    assert_eq!(interval(30), Some(2));
+    assert_eq!(interval(31), Some(10));
+    assert_eq!(interval(12 * 60), Some(30));
+    assert_eq!(interval(3 * 24 * 60), Some(180));
+    // too far away to be worth polling
+    assert_eq!(interval(30 * 24 * 60), None);
+    assert_eq!(settings.poll_interval(None, now), None);
+
+    // tiers can be given in any order
+    let config = Config::from_toml(
+        "[daemon]\npoll_tiers = [{ within_minutes = 600, interval_minutes = 60 }, \
+         { within_minutes = 60, interval_minutes = 5 }]",
+    )
+    .unwrap();
+    assert_eq!(
+        config
+            .daemon
+            .poll_interval(Some(now + Duration::minutes(45)), now),
+        Some(Duration::minutes(5))
+    );
+}
+
+#[test]
+fn test_scheduler_skips_sports_without_upcoming_events() {
+    let now = get_now();
+    let settings = DaemonSettings::default();
+    let mut scheduler = Scheduler::new(&get_sports(), &settings, 2);
+    assert_eq!(scheduler.events_due(now).len(), 3);
+    assert!(scheduler.polls_due(now).is_empty());
+
+    scheduler.set_events(
+        "aussierules_afl",
+        &[now - Duration::hours(1), now + Duration::minutes(20)],
+        now,
+    );
+    scheduler.set_events("rugbyleague_nrl", &[now + Duration::hours(5)], now);
+    // only games that have already started
+    scheduler.set_events("baseball_kbo", &[now - Duration::minutes(5)], now);
+    scheduler.reschedule(None, None, now);
+
+    assert!(scheduler.events_due(now).is_empty());
+    assert_eq!(
+        scheduler.polls_due(now),
+        ["aussierules_afl", "rugbyleague_nrl"]
+    );
+    assert_eq!(scheduler.sports()[2].next_poll, None);
+
+    scheduler.set_polled("aussierules_afl", 3, now);
+    scheduler.set_poll_error("rugbyleague_nrl", "odds request failed", now);
+    scheduler.reschedule(None, None, now);
+    assert!(scheduler.polls_due(now).is_empty());
+    assert_eq!(scheduler.next_wake(now), now + Duration::minutes(2));
+
+    let later = now + Duration::minutes(10);
+    assert_eq!(scheduler.polls_due(later), ["aussierules_afl"]);
+    assert_eq!(scheduler.polls_due(later + Duration::minutes(20)).len(), 2);
+
+    // once the game starts there's nothing to poll until the next check
+    let started = now + Duration::minutes(21);
+    scheduler.set_polled("aussierules_afl", 0, started);
+    scheduler.reschedule(None, None, started);
+    assert_eq!(scheduler.sports()[0].next_commence, None);
+    assert_eq!(scheduler.sports()[0].next_poll, None);
+    assert_eq!(scheduler.events_due(now + Duration::minutes(60)).len(), 3);
+}
+
+#[test]
+fn test_scheduler_stretches_intervals_to_fit_quota() {
+    let now = get_now();
+    let settings = DaemonSettings::default();
+    let mut scheduler = Scheduler::new(&get_sports()[..2], &settings, 4);
+    scheduler.set_events("aussierules_afl", &[now + Duration::minutes(20)], now);
+    scheduler.set_events("rugbyleague_nrl", &[now + Duration::hours(2)], now);
+    scheduler.set_polled("aussierules_afl", 0, now);
+    scheduler.set_polled("rugbyleague_nrl", 0, now);
+
+    // 4 every 2 minutes plus 4 every 10 minutes is 2.4 requests a minute
+    scheduler.reschedule(Some(500), Some(10.0), now);
+    assert_eq!(scheduler.stretch(), 1.0);
+    assert_eq!(scheduler.sports()[0].interval_minutes, Some(2.0));
+
+    // the last 30 minutes before each start take 4 * 10 + 4 * 15 = 100 of
+    // the 500 requests, leaving 0.16 a minute for the nrl's 0.4
+    scheduler.reschedule(Some(500), Some(0.2), now);
+    assert!((scheduler.stretch() - 2.5).abs() < 1e-9);
+    assert_eq!(
+        scheduler.sports()[0].next_poll,
+        Some(now + Duration::minutes(2))
+    );
+    assert_eq!(
+        scheduler.sports()[1].next_poll,
+        Some(now + Duration::minutes(25))
+    );
+
+    // a closing line capture is paid for up front too
+    let capture_at = now + Duration::minutes(110);
+    scheduler.set_captures("rugbyleague_nrl", &[capture_at]);
+    scheduler.reschedule(Some(500), Some(0.2), now);
+    assert!(scheduler.stretch() > 2.5);
+    assert_eq!(scheduler.sports()[1].next_capture, Some(capture_at));
+    assert!(scheduler.captures_due(now).is_empty());
+    assert_eq!(scheduler.captures_due(capture_at), ["rugbyleague_nrl"]);
+    scheduler.set_captured("rugbyleague_nrl", capture_at);
+    scheduler.reschedule(Some(500), Some(0.2), capture_at);
+    assert_eq!(scheduler.sports()[1].next_capture, None);
+    scheduler.set_captures("rugbyleague_nrl", &[]);
+
+    // however far the interval is stretched, the nrl is still polled once
+    // its game is half an hour away
+    scheduler.reschedule(Some(500), Some(0.02), now);
+    assert!(scheduler.stretch() > 10.0);
+    assert_eq!(
+        scheduler.sports()[1].next_poll,
+        Some(now + Duration::minutes(90))
+    );
+
+    // too few requests for even the final tier
+    scheduler.reschedule(Some(50), Some(0.02), now);
+    assert_eq!(scheduler.sports()[0].interval_minutes, Some(4.0));
+    assert_eq!(scheduler.sports()[1].next_poll, None);
+
+    // nothing left until the quota resets
+    scheduler.reschedule(Some(0), Some(0.0), now);
+    assert!(scheduler.sports().iter().all(|x| x.next_poll.is_none()));
+    assert_eq!(
+        scheduler.next_wake(now),
+        now + Duration::minutes(settings.events_refresh_minutes)
+    );
+}
+
+#[test]
+fn test_write_status_and_sleep() {
+    let now = get_now();
+    let mut scheduler = Scheduler::new(&get_sports(), &DaemonSettings::default(), 1);
+    scheduler.set_events("aussierules_afl", &[now + Duration::minutes(20)], now);
+    scheduler.reschedule(None, None, now);
+
+    let path = env::temp_dir().join(format!("arb_finder_status_{0}.json", std::process::id()));
+    let status = DaemonStatus {
+        pid: 1,
+        started_at: now,
+        updated_at: now,
+        running: true,
+        polls: 0,
+        requests_remaining: Some(500),
+        quota_stretch: scheduler.stretch(),
+        sports: scheduler.sports(),
+    };
+    write_status(&path, &status).unwrap();
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(written["running"], true);
+    assert_eq!(written["sports"].as_array().unwrap().len(), 3);
+    assert_eq!(written["sports"][0]["sport_key"], "aussierules_afl");
+    assert_eq!(written["sports"][0]["interval_minutes"], 2.0);
+    assert!(written["sports"][1]["next_poll"].is_null());
+
+    // a shutdown cuts the sleep short
+    let shutdown = AtomicBool::new(true);
+    let started = std::time::Instant::now();
+    sleep_until(Utc::now() + Duration::hours(1), &shutdown);
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+}
@@ -0,0 +1,461 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod daemon_test;
+
+const DEFAULT_STATUS_FILE: &str = "./daemon_status.json";
+const DEFAULT_EVENTS_REFRESH_MINUTES: i64 = 60;
+/// Longest the daemon sleeps before checking whether it should stop
+const SHUTDOWN_CHECK_SECONDS: i64 = 1;
+
+/// How often to poll a sport whose next event starts within
+/// `within_minutes`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct PollTier {
+    pub within_minutes: i64,
+    pub interval_minutes: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DaemonSettings {
+    /// Poll intervals by how soon the next event starts. Sports with nothing
+    /// starting within the widest tier aren't polled.
+    pub poll_tiers: Vec<PollTier>,
+    /// How often to check each sport's upcoming events, which costs no
+    /// quota
+    pub events_refresh_minutes: i64,
+    /// Rewritten after every poll, for supervisors to check the daemon is
+    /// alive
+    pub status_file: PathBuf,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        return DaemonSettings {
+            poll_tiers: [(30, 2), (180, 10), (24 * 60, 30), (7 * 24 * 60, 180)]
+                .iter()
+                .map(|(within_minutes, interval_minutes)| PollTier {
+                    within_minutes: *within_minutes,
+                    interval_minutes: *interval_minutes,
+                })
+                .collect(),
+            events_refresh_minutes: DEFAULT_EVENTS_REFRESH_MINUTES,
+            status_file: PathBuf::from(DEFAULT_STATUS_FILE),
+        };
+    }
+}
+
+impl DaemonSettings {
+    /// How often to poll a sport whose next event starts at
+    /// `next_commence`, before any quota stretching
+    pub fn poll_interval(
+        &self,
+        next_commence: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        let until_start = next_commence? - now;
+        return self
+            .poll_tiers
+            .iter()
+            .filter(|x| until_start <= Duration::minutes(x.within_minutes))
+            .min_by_key(|x| x.within_minutes)
+            .map(|x| Duration::minutes(x.interval_minutes.max(1)));
+    }
+}
+
+/// Where the daemon is up to with one sport
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SportSchedule {
+    pub sport_key: String,
+    /// Start times of the sport's events from the last events check
+    #[serde(skip)]
+    pub commence_times: Vec<DateTime<Utc>>,
+    pub next_commence: Option<DateTime<Utc>>,
+    pub events_checked_at: Option<DateTime<Utc>>,
+    pub last_poll: Option<DateTime<Utc>>,
+    /// None while there's nothing upcoming, or no quota to spend
+    pub next_poll: Option<DateTime<Utc>>,
+    pub interval_minutes: Option<f64>,
+    /// When each event with opportunities waiting on a closing line enters
+    /// the capture window
+    #[serde(skip)]
+    pub capture_times: Vec<DateTime<Utc>>,
+    pub last_capture: Option<DateTime<Utc>>,
+    /// The next closing line capture, unless one is already due
+    pub next_capture: Option<DateTime<Utc>>,
+    pub opportunities: usize,
+    pub last_error: Option<String>,
+}
+
+impl SportSchedule {
+    fn new(sport_key: &str) -> Self {
+        return SportSchedule {
+            sport_key: sport_key.to_string(),
+            commence_times: Vec::new(),
+            next_commence: None,
+            events_checked_at: None,
+            last_poll: None,
+            next_poll: None,
+            interval_minutes: None,
+            capture_times: Vec::new(),
+            last_capture: None,
+            next_capture: None,
+            opportunities: 0,
+            last_error: None,
+        };
+    }
+}
+
+/// Decides when to check events and poll odds for each sport, keeping the
+/// total spend inside the quota
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    settings: DaemonSettings,
+    /// Quota cost of polling one sport
+    poll_cost: u32,
+    /// How much the intervals before the final tier were lengthened to fit
+    /// the quota
+    stretch: f64,
+    sports: Vec<SportSchedule>,
+}
+
+impl Scheduler {
+    pub fn new(sports: &[String], settings: &DaemonSettings, poll_cost: u32) -> Self {
+        return Scheduler {
+            settings: settings.clone(),
+            poll_cost,
+            stretch: 1.0,
+            sports: sports.iter().map(|x| SportSchedule::new(x)).collect(),
+        };
+    }
+
+    pub fn sports(&self) -> &[SportSchedule] {
+        return &self.sports;
+    }
+
+    pub fn stretch(&self) -> f64 {
+        return self.stretch;
+    }
+
+    fn sport_mut(&mut self, sport_key: &str) -> Option<&mut SportSchedule> {
+        return self.sports.iter_mut().find(|x| x.sport_key == sport_key);
+    }
+
+    /// Sports whose upcoming events haven't been checked recently
+    pub fn events_due(&self, now: DateTime<Utc>) -> Vec<String> {
+        let refresh = Duration::minutes(self.settings.events_refresh_minutes);
+        return self
+            .sports
+            .iter()
+            .filter(|x| match x.events_checked_at {
+                Some(checked_at) => now - checked_at >= refresh,
+                None => true,
+            })
+            .map(|x| x.sport_key.clone())
+            .collect();
+    }
+
+    pub fn set_events(
+        &mut self,
+        sport_key: &str,
+        commence_times: &[DateTime<Utc>],
+        now: DateTime<Utc>,
+    ) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.commence_times = commence_times.to_vec();
+            sport.events_checked_at = Some(now);
+            sport.last_error = None;
+        }
+    }
+
+    /// Keeps the events from the last check until the next one is due
+    pub fn set_events_error(&mut self, sport_key: &str, error: &str, now: DateTime<Utc>) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.events_checked_at = Some(now);
+            sport.last_error = Some(error.to_string());
+        }
+    }
+
+    /// Sports due a poll at `now`
+    pub fn polls_due(&self, now: DateTime<Utc>) -> Vec<String> {
+        return self
+            .sports
+            .iter()
+            .filter(|x| x.next_poll.is_some_and(|next_poll| next_poll <= now))
+            .map(|x| x.sport_key.clone())
+            .collect();
+    }
+
+    pub fn set_polled(&mut self, sport_key: &str, opportunities: usize, now: DateTime<Utc>) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.last_poll = Some(now);
+            sport.opportunities = opportunities;
+            sport.last_error = None;
+        }
+    }
+
+    /// A failed poll still waits out the interval before trying again
+    pub fn set_poll_error(&mut self, sport_key: &str, error: &str, now: DateTime<Utc>) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.last_poll = Some(now);
+            sport.last_error = Some(error.to_string());
+        }
+    }
+
+    /// Sets when `sport_key`'s closing lines need capturing, once for each
+    /// time in `capture_times`
+    pub fn set_captures(&mut self, sport_key: &str, capture_times: &[DateTime<Utc>]) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.capture_times = capture_times.to_vec();
+        }
+    }
+
+    /// Sports due a closing line capture at `now`
+    pub fn captures_due(&self, now: DateTime<Utc>) -> Vec<String> {
+        return self
+            .sports
+            .iter()
+            .filter(|x| {
+                x.next_capture
+                    .is_some_and(|next_capture| next_capture <= now)
+            })
+            .map(|x| x.sport_key.clone())
+            .collect();
+    }
+
+    /// A capture is only made once, whether or not it worked
+    pub fn set_captured(&mut self, sport_key: &str, now: DateTime<Utc>) {
+        if let Some(sport) = self.sport_mut(sport_key) {
+            sport.last_capture = Some(now);
+        }
+    }
+
+    /// The tier polled most often, just before each event starts
+    fn final_tier(&self) -> Option<PollTier> {
+        return self
+            .settings
+            .poll_tiers
+            .iter()
+            .min_by_key(|x| x.within_minutes)
+            .copied();
+    }
+
+    /// Requests it takes to poll `sport` through the final tier of every
+    /// event it has starting within the widest tier
+    fn final_tier_cost(&self, sport: &SportSchedule, now: DateTime<Utc>) -> f64 {
+        let final_tier = match self.final_tier() {
+            Some(x) => x,
+            None => return 0.0,
+        };
+        let horizon = self
+            .settings
+            .poll_tiers
+            .iter()
+            .map(|x| Duration::minutes(x.within_minutes))
+            .max()
+            .unwrap_or_default();
+
+        let mut upcoming: Vec<DateTime<Utc>> = sport
+            .commence_times
+            .iter()
+            .filter(|x| **x > now && **x - now <= horizon)
+            .copied()
+            .collect();
+        upcoming.sort();
+
+        // events starting close together share their polls
+        let mut covered = Duration::zero();
+        let mut covered_until = now;
+        for commence in upcoming {
+            let start =
+                (commence - Duration::minutes(final_tier.within_minutes)).max(covered_until);
+            if (commence > start) {
+                covered += commence - start;
+            }
+            covered_until = covered_until.max(commence);
+        }
+        let minutes = covered.num_seconds() as f64 / 60.0;
+        return self.poll_cost as f64 * minutes / final_tier.interval_minutes.max(1) as f64;
+    }
+
+    /// Works out each sport's next poll from how soon its next event starts.
+    /// The final tier of every upcoming event, and each closing line capture,
+    /// is paid for first out of the `available` requests. The final tier is
+    /// only stretched if those requests can't cover it. The earlier tiers
+    /// share what's left of `sustainable_rate` requests a minute, all
+    /// lengthened by the same factor if they'd spend more, but never past
+    /// the start of the final tier.
+    pub fn reschedule(
+        &mut self,
+        available: Option<u32>,
+        sustainable_rate: Option<f64>,
+        now: DateTime<Utc>,
+    ) {
+        let final_tier = self.final_tier();
+        let in_final_tier = |sport: &SportSchedule| {
+            return match (sport.next_commence, final_tier) {
+                (Some(commence), Some(tier)) => {
+                    commence - now <= Duration::minutes(tier.within_minutes)
+                }
+                _ => false,
+            };
+        };
+
+        for sport in self.sports.iter_mut() {
+            sport.next_commence = sport
+                .commence_times
+                .iter()
+                .filter(|x| **x > now)
+                .min()
+                .copied();
+            let interval = self.settings.poll_interval(sport.next_commence, now);
+            sport.interval_minutes = interval.map(|x| x.num_seconds() as f64 / 60.0);
+            sport.next_capture = sport
+                .capture_times
+                .iter()
+                .filter(|x| sport.last_capture.is_none_or(|last| last < **x))
+                .min()
+                .copied();
+        }
+
+        let captures: usize = self
+            .sports
+            .iter()
+            .map(|x| {
+                return x
+                    .capture_times
+                    .iter()
+                    .filter(|time| x.last_capture.is_none_or(|last| last < **time))
+                    .count();
+            })
+            .sum();
+        let reserved: f64 = self
+            .sports
+            .iter()
+            .map(|x| self.final_tier_cost(x, now))
+            .sum::<f64>()
+            + (captures as u32 * self.poll_cost) as f64;
+        let planned_rate: f64 = self
+            .sports
+            .iter()
+            .filter(|x| !in_final_tier(x))
+            .filter_map(|x| x.interval_minutes)
+            .map(|x| self.poll_cost as f64 / x)
+            .sum();
+
+        let final_stretch = match available {
+            Some(x) if (reserved > x as f64) => reserved / x as f64,
+            _ => 1.0,
+        };
+        let earlier_rate = sustainable_rate.map(|rate| match available {
+            Some(x) if (x > 0) => rate * (1.0 - reserved / x as f64).max(0.0),
+            _ => rate,
+        });
+        self.stretch = match earlier_rate {
+            Some(x) if (planned_rate > x) => planned_rate / x,
+            _ => 1.0,
+        };
+
+        for sport in self.sports.iter_mut() {
+            let is_final = in_final_tier(sport);
+            let stretch = match is_final {
+                true => final_stretch,
+                false => self.stretch,
+            };
+            let minutes = match sport.interval_minutes {
+                Some(x) if (stretch.is_finite()) => x * stretch,
+                _ => {
+                    sport.interval_minutes = None;
+                    sport.next_poll = None;
+                    continue;
+                }
+            };
+            sport.interval_minutes = Some(minutes);
+
+            let mut next_poll = match sport.last_poll {
+                Some(last_poll) => last_poll + Duration::seconds((minutes * 60.0).round() as i64),
+                None => now,
+            };
+            // a stretched interval mustn't skip the polls before the start
+            if let (false, Some(commence), Some(tier)) = (is_final, sport.next_commence, final_tier)
+            {
+                next_poll = next_poll.min(commence - Duration::minutes(tier.within_minutes));
+            }
+            sport.next_poll = Some(next_poll);
+        }
+    }
+
+    /// When there's next something to do, at the latest the next events
+    /// check
+    pub fn next_wake(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let refresh = Duration::minutes(self.settings.events_refresh_minutes);
+        return self
+            .sports
+            .iter()
+            .flat_map(|x| {
+                let next_check = x.events_checked_at.map(|checked_at| checked_at + refresh);
+                return [x.next_poll, x.next_capture, next_check];
+            })
+            .flatten()
+            .min()
+            .unwrap_or(now + refresh);
+    }
+}
+
+/// What the daemon writes to its status file
+#[derive(Serialize, Debug, Clone)]
+pub struct DaemonStatus<'a> {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// Heartbeat, refreshed on every write
+    pub updated_at: DateTime<Utc>,
+    pub running: bool,
+    pub polls: usize,
+    pub requests_remaining: Option<i32>,
+    /// How much the poll intervals before the final tier were lengthened to
+    /// fit the quota
+    pub quota_stretch: f64,
+    pub sports: &'a [SportSchedule],
+}
+
+/// Writes `status` to `path` through a temporary file, so readers never see
+/// half of it
+pub fn write_status(path: &Path, status: &DaemonStatus) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(status)?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, json + "\n")?;
+    fs::rename(&temp_path, path)?;
+    return Ok(());
+}
+
+/// A flag set once SIGINT or SIGTERM is received
+pub fn shutdown_flag() -> Result<Arc<AtomicBool>, ctrlc::Error> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = flag.clone();
+    ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst))?;
+    return Ok(flag);
+}
+
+/// Sleeps until `deadline`, returning early if `shutdown` is set
+pub fn sleep_until(deadline: DateTime<Utc>, shutdown: &AtomicBool) {
+    while (!shutdown.load(Ordering::SeqCst)) {
+        let remaining = deadline - Utc::now();
+        if (remaining <= Duration::zero()) {
+            return;
+        }
+        let step = remaining.min(Duration::seconds(SHUTDOWN_CHECK_SECONDS));
+        thread::sleep(step.to_std().unwrap_or_default());
+    }
+}
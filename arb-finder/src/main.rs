@@ -1,72 +1,303 @@
-use messaging::send_message;
-use odds_interface::{
-    api_requests::{get_key_usage, get_odds_for_sport, get_sports},
-    logic::{bookmaker::Region, market::MarketType},
+use chrono::Utc;
+use clap::Parser;
+use cli::{Cli, Command, ExitStatus};
+use commands::{
+    alerts::alert_messages,
+    api::{sports_output, usage_output},
+    arbitrage::{arbitrage_output, find_arbitrages, find_matched_bets, matched_bet_output},
+    backtest::{backtest_report, fetch_historical_snapshots, save_recent_results},
+    calibration::{calibration_predictions, calibration_report},
+    clv::{capture_closing_lines, clv_report},
+    daemon::run_daemon,
+    history::history_output,
+    ledger::{bet_from_args, ledger_report, settle_pending_bets},
+    notify, open_store,
+    scan::{read_odds_file, replay_results, scan_sports},
+    sharpness::sharpness_report,
+    with_min_ev, write_output,
 };
-use std::io;
+use config::Config;
+use daemon::shutdown_flag;
+use odds_interface::api_requests::{client::OddsApiClient, get_key_usage, get_sports};
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+};
+use storage::SnapshotStore;
 
-mod local_env;
+mod cli;
+mod commands;
+mod config;
+mod daemon;
 mod messaging;
 mod odds_interface;
+mod storage;
 
-fn get_sport_key_json(sport_key: &str) -> String {
-    return format!("./src/example_responses/{sport_key}_odds.json");
-}
+#[cfg(test)]
+mod main_test;
 
-fn get_trimmed_input() -> String {
-    let mut operation_choice = String::new();
-    io::stdin()
-        .read_line(&mut operation_choice)
-        .expect("Failed to read line");
+/// Runs `command`, writing its results to `out` and anything that went
+/// wrong to stderr
+fn run(
+    command: &Command,
+    config: &Config,
+    client: &OddsApiClient,
+    out: &mut dyn Write,
+) -> ExitStatus {
+    match command {
+        Command::Sports(output) => {
+            let sports = match get_sports(client) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to get sports: {e}");
+                    return ExitStatus::from_api_error(&e);
+                }
+            };
+            return write_output(out, &sports_output(&sports).render(output.format));
+        }
+        Command::Scan(args) => {
+            let mut config = with_min_ev(config, args.min_ev);
+            args.markets.apply(&mut config);
+            let sports = args.sports.or_configured(&config);
+            if (sports.is_empty()) {
+                eprintln!("no sports to scan, pass --sport or set sports in the config");
+                return ExitStatus::Usage;
+            }
 
-    operation_choice = operation_choice.trim().to_string();
-    return operation_choice;
-}
+            let results = scan_sports(client, &config, &sports);
+            let mut status = results.status;
+            if (args.notify) {
+                let messages = alert_messages(&config, &results, Utc::now());
+                status = status.and(notify(&config, &messages));
+            }
+            return status.and(write_output(
+                out,
+                &results.output().render(args.output.format),
+            ));
+        }
+        Command::Arbs(args) => {
+            let mut config = config.clone();
+            args.markets.apply(&mut config);
+            let bankroll = args.bankroll.unwrap_or(config.staking.bankroll);
 
-fn main() {
-    let mut num_inputs = 6;
-    while num_inputs > 0 {
-        println!("Available operations:");
-        println!("==========================");
-        println!("s:   print in-season sports");
-        println!("e:   try to find +EV opportunities for sport of choosing");
-        println!("m:   send test message to discord server");
+            let arbitrages = match find_arbitrages(client, &config, &args.sport, bankroll) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to get odds for {0:?}: {e}", args.sport);
+                    return ExitStatus::from_api_error(&e);
+                }
+            };
+            let mut status = ExitStatus::Success;
+            if (args.notify) {
+                let messages: Vec<String> = arbitrages.iter().map(|x| x.to_string()).collect();
+                status = notify(&config, &messages);
+            }
+            return status.and(write_output(
+                out,
+                &arbitrage_output(&arbitrages).render(args.output.format),
+            ));
+        }
+        Command::Matched(args) => {
+            let mut config = config.clone();
+            args.markets.apply(&mut config);
 
-        let operation_choice = get_trimmed_input();
+            let matched_bets = match find_matched_bets(client, &config, &args.sport, args.stake) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to get odds for {0:?}: {e}", args.sport);
+                    return ExitStatus::from_api_error(&e);
+                }
+            };
+            let mut status = ExitStatus::Success;
+            if (args.notify) {
+                let messages: Vec<String> = matched_bets.iter().map(|x| x.to_string()).collect();
+                status = notify(&config, &messages);
+            }
+            return status.and(write_output(
+                out,
+                &matched_bet_output(&matched_bets).render(args.output.format),
+            ));
+        }
+        Command::Usage(output) => {
+            let usage = match get_key_usage(client) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("could not check requests remaining: {e}");
+                    return ExitStatus::from_api_error(&e);
+                }
+            };
 
-        if operation_choice == "s" {
-            let sports = get_sports().expect("Failed to get sports");
-            println!("{sports:#?}")
-        } else if operation_choice == "e" {
-            println!("write your sport key of choice");
+            let rows = usage_output(&usage, client.quota(), Utc::now());
+            return write_output(out, &rows.render(output.format));
+        }
+        Command::NotifyTest => {
+            let status = notify(config, &[String::from("Hello World!!")]);
+            if (status == ExitStatus::Success) {
+                return write_output(out, "sent test message to discord server\n");
+            }
+            return status;
+        }
+        Command::Replay(args) => {
+            let config = with_min_ev(config, args.min_ev);
+            let events = match read_odds_file(&args.file) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to read odds from {0:?}: {e}", args.file);
+                    return ExitStatus::Failure;
+                }
+            };
 
-            let sport_key = get_trimmed_input();
-            let markets = [MarketType::H2h, MarketType::Spreads, MarketType::Totals].to_vec();
-            let regions = [Region::Us, Region::Uk, Region::Au, Region::Eu].to_vec();
+            let results = replay_results(&config, &events);
+            return write_output(out, &results.output().render(args.output.format));
+        }
+        Command::Bet(args) => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let bet = match bet_from_args(&store, args) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return ExitStatus::Failure;
+                }
+            };
+            match store.record_bet(&bet) {
+                Ok(id) => return write_output(out, &format!("recorded bet {id}\n")),
+                Err(e) => {
+                    eprintln!("Failed to record bet: {e}");
+                    return ExitStatus::Failure;
+                }
+            }
+        }
+        Command::Settle => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let num_settled = settle_pending_bets(client, &mut store, Utc::now());
+            let report = ledger_report(&store, config.staking.bankroll);
+            return write_output(out, &format!("settled {num_settled} bets\n{report}"));
+        }
+        Command::Clv => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let num_closed = capture_closing_lines(client, config, &mut store, &[], Utc::now());
+            let report = clv_report(&store);
+            return write_output(
+                out,
+                &format!("captured {num_closed} closing lines\n{report}"),
+            );
+        }
+        Command::Backtest(args) => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let mut report = String::new();
+            if let Some(from) = args.from {
+                let to = args.to.unwrap_or(Utc::now());
+                let num_saved =
+                    fetch_historical_snapshots(client, config, &mut store, &args.sport, from, to);
+                report += &format!("saved {num_saved} historical snapshots\n");
+            }
 
-            let events_raw = get_odds_for_sport(&sport_key, &markets, &regions)
-                .expect("Failed to get odds for {sport_key:?}");
-            for event in events_raw {
-                for market in &markets {
-                    let opportunities = event.identify_opportunities();
-                    for opportunity in opportunities {
-                        send_message(&opportunity.to_string());
-                    }
+            let num_results = save_recent_results(client, &mut store, &args.sport);
+            report += &format!("saved {num_results} results\n");
+            report += &backtest_report(config, &store, &args.sport);
+            return write_output(out, &report);
+        }
+        Command::Calibration(args) => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let sports = args.or_configured(config);
+            for sport_key in &sports {
+                save_recent_results(client, &mut store, sport_key);
+            }
+
+            let predictions = calibration_predictions(config, &store, &sports);
+            return write_output(out, &(calibration_report(config, &predictions) + "\n"));
+        }
+        Command::Sharpness(args) => {
+            let mut store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            let sports = args.or_configured(config);
+            for sport_key in &sports {
+                save_recent_results(client, &mut store, sport_key);
+            }
+            return write_output(out, &(sharpness_report(config, &store, &sports) + "\n"));
+        }
+        Command::History(args) => {
+            let store = match open_store(config) {
+                Ok(x) => x,
+                Err(status) => return status,
+            };
+            match history_output(&store, args) {
+                Ok(rows) => return write_output(out, &rows.render(args.output.format)),
+                Err(e) => {
+                    eprintln!("Failed to read the price history: {e}");
+                    return ExitStatus::Failure;
                 }
             }
-        } else if operation_choice == "m" {
-            println!("sending test message to discord server");
-            send_message("Hello World!!")
-        } else {
-            println!("{operation_choice:#?} is not a valid choice!")
         }
+        Command::Daemon(args) => {
+            let mut config = with_min_ev(config, args.min_ev);
+            args.markets.apply(&mut config);
+            let sports = args.sports.or_configured(&config);
+            if (sports.is_empty()) {
+                eprintln!("no sports to poll, pass --sport or set sports in the config");
+                return ExitStatus::Usage;
+            }
 
-        let key_usage = get_key_usage();
-        match key_usage {
-            Some(x) => println!("requests remaining: {0}", x.requests_remaining),
-            None => println!(""),
-        };
+            let shutdown = match shutdown_flag() {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to handle shutdown signals: {e}");
+                    return ExitStatus::Failure;
+                }
+            };
+            let status_file = args
+                .status_file
+                .clone()
+                .unwrap_or(config.daemon.status_file.clone());
+            return run_daemon(
+                client,
+                &config,
+                &sports,
+                args.notify,
+                &status_file,
+                &shutdown,
+                out,
+            );
+        }
+    }
+}
 
-        num_inputs -= 1;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = match Config::load() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed to load config: {e}");
+            return ExitStatus::Config.into();
+        }
+    };
+    let mut client =
+        OddsApiClient::with_base_url(&config.credentials.odds_api_key, &config.odds_host_base)
+            .with_budget(config.quota_budget)
+            .with_transport(config.transport.clone());
+    if let Some(path) = &config.snapshot_db {
+        match SnapshotStore::open(path) {
+            Ok(store) => client = client.with_spend_store(store),
+            Err(e) => eprintln!("Failed to open snapshot database {0:?}: {e}", path),
+        }
     }
+
+    return run(&cli.command, &config, &client, &mut io::stdout()).into();
 }
@@ -0,0 +1,320 @@
+use std::{collections::BTreeSet, env, fs, path::PathBuf};
+
+use chrono::{Duration, Utc};
+use clap::Parser;
+
+use crate::cli::{Cli, ExitStatus};
+use crate::commands::scan::scan_sports;
+use crate::config::Config;
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    get_key_usage, get_sports,
+    mock_server::{MockFailure, MockFixtures, MockOddsApi},
+    transport::{TransportMode, TransportSettings},
+};
+use crate::odds_interface::logic::{bookmaker::Region, event::Event, market::MarketType};
+use crate::run;
+use crate::storage::SnapshotStore;
+
+const CASSETTE_DIR: &str = "./example_data/cassettes";
+
+fn get_replay_client() -> OddsApiClient {
+    // the host doesn't matter, nothing is sent when replaying
+    return OddsApiClient::with_base_url("any-key", "http://replay.invalid/v4").with_transport(
+        TransportSettings {
+            mode: TransportMode::Replay,
+            cassette_dir: PathBuf::from(CASSETTE_DIR),
+            ..Default::default()
+        },
+    );
+}
+
+fn get_replay_config() -> Config {
+    return Config {
+        markets: [MarketType::H2h, MarketType::Totals].to_vec(),
+        regions: [Region::Au].to_vec(),
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_replay_sports_and_usage() {
+    let client = get_replay_client();
+
+    assert_eq!(get_sports(&client).unwrap().len(), 3);
+
+    let usage = get_key_usage(&client).unwrap();
+    assert_eq!(usage.requests_used, 12);
+    assert_eq!(usage.requests_remaining, 488);
+}
+
+/// Runs the command line `args` against `config`, returning what it wrote
+fn run_command(args: &[&str], config: &Config, client: &OddsApiClient) -> (ExitStatus, String) {
+    let cli = Cli::try_parse_from([&["arb-finder"], args].concat()).unwrap();
+    let mut out = Vec::new();
+    let status = run(&cli.command, config, client, &mut out);
+    return (status, String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_run_scan_writes_each_format() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+
+    let args = ["scan", "--sport", "aussierules_afl", "--markets", "h2h"];
+    let (status, json) = run_command(
+        &[&args[..], &["--format", "json"]].concat(),
+        &config,
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Success);
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    assert!(!rows.is_empty());
+    assert!(rows
+        .iter()
+        .all(|x| x["sport"] == "aussierules_afl" && x["market"] == "h2h"));
+
+    let (status, csv) = run_command(
+        &[&args[..], &["--format", "csv"]].concat(),
+        &config,
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Success);
+    assert!(csv.starts_with("event_id,sport,matchup,"));
+    assert_eq!(csv.lines().count(), rows.len() + 1);
+
+    let (status, table) = run_command(&args, &config, &client);
+    assert_eq!(status, ExitStatus::Success);
+    assert!(table.starts_with("event_id"));
+    assert_eq!(table.lines().count(), rows.len() + 1);
+
+    // none configured and none given
+    let (status, _) = run_command(&["scan"], &Config::default(), &client);
+    assert_eq!(status, ExitStatus::Usage);
+}
+
+#[test]
+fn test_run_exit_codes_from_api_errors() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    server.set_sport_failure("baseball_kbo", MockFailure::Unauthorized);
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+
+    let (status, out) = run_command(&["arbs", "--sport", "baseball_kbo"], &config, &client);
+    assert_eq!(status, ExitStatus::Unauthorized);
+    assert!(out.is_empty());
+
+    // the sport that could be fetched is still written out
+    let (status, out) = run_command(
+        &[
+            "scan",
+            "--sport",
+            "aussierules_afl,baseball_kbo",
+            "--format",
+            "json",
+        ],
+        &config,
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Unauthorized);
+    assert!(out.contains("aussierules_afl"));
+
+    // commands that need the database say so
+    let (status, _) = run_command(&["settle"], &config, &client);
+    assert_eq!(status, ExitStatus::Config);
+}
+
+#[test]
+fn test_run_replay_of_cassette() {
+    let file = format!(
+        "{CASSETTE_DIR}/v4_sports_aussierules_afl_odds__markets=h2h_totals__regions=au.json"
+    );
+    let client = get_replay_client();
+
+    let (status, json) = run_command(
+        &["replay", &file, "--format", "json"],
+        &Config::default(),
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Success);
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    assert!(!rows.is_empty());
+
+    // a higher cutoff only drops opportunities
+    let (_, strict) = run_command(
+        &["replay", &file, "--min-ev", "50", "--format", "json"],
+        &Config::default(),
+        &client,
+    );
+    let strict: Vec<serde_json::Value> = serde_json::from_str(&strict).unwrap();
+    assert!(strict.len() < rows.len());
+
+    let (status, _) = run_command(&["replay", "missing.json"], &Config::default(), &client);
+    assert_eq!(status, ExitStatus::Failure);
+}
+
+#[test]
+fn test_run_history_of_stored_scan() {
+    let path = env::temp_dir().join(format!("arb_finder_history_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let config = Config {
+        snapshot_db: Some(path.clone()),
+        ..get_replay_config()
+    };
+    let client = get_replay_client();
+    let (status, _) = run_command(&["scan", "--sport", "aussierules_afl"], &config, &client);
+    assert_eq!(status, ExitStatus::Success);
+
+    let (status, written) = run_command(
+        &["history", "--sport", "aussierules_afl", "--format", "json"],
+        &config,
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Success);
+    let events: serde_json::Value = serde_json::from_str(&written).unwrap();
+    let event = &events.as_array().unwrap()[0];
+    assert_eq!(event["snapshots"], 1);
+
+    let event_id = event["id"].as_str().unwrap();
+    let home_team = event["home_team"].as_str().unwrap();
+    let (status, written) = run_command(
+        &[
+            "history",
+            "--event",
+            event_id,
+            "--market",
+            "h2h",
+            "--outcome",
+            home_team,
+            "--format",
+            "json",
+        ],
+        &config,
+        &client,
+    );
+    assert_eq!(status, ExitStatus::Success);
+    let prices: serde_json::Value = serde_json::from_str(&written).unwrap();
+    assert!(!prices.as_array().unwrap().is_empty());
+    assert!(prices[0]["odds"].as_f64().unwrap() > 1.0);
+
+    // an event needs a market and outcome to show prices for
+    assert!(Cli::try_parse_from(["arb-finder", "history", "--event", event_id]).is_err());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_notify_alerts_each_steam_move_once() {
+    let fixtures = MockFixtures::from_example_data(&["aussierules_afl"]);
+    let path = env::temp_dir().join(format!("arb_finder_steam_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    // five minutes ago every book had the home side at a longer price
+    let mut earlier: serde_json::Value =
+        serde_json::from_str(&fixtures.odds["aussierules_afl"]).unwrap();
+    let home_team = earlier[0]["home_team"].clone();
+    for bookmaker in earlier[0]["bookmakers"].as_array_mut().unwrap() {
+        for market in bookmaker["markets"].as_array_mut().unwrap() {
+            if (market["key"] != "h2h") {
+                continue;
+            }
+            for outcome in market["outcomes"].as_array_mut().unwrap() {
+                if (outcome["name"] == home_team) {
+                    outcome["price"] = (outcome["price"].as_f64().unwrap() * 1.3).into();
+                }
+            }
+        }
+    }
+    let earlier: Vec<Event> = serde_json::from_value(earlier).unwrap();
+    SnapshotStore::open(&path)
+        .unwrap()
+        .save_events(&earlier, Utc::now() - Duration::minutes(5))
+        .unwrap();
+
+    let server = MockOddsApi::start(fixtures);
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    config.discord_api_base = server.discord_api_base();
+    config.snapshot_db = Some(path.clone());
+    config.credentials.discord_channel_id = String::from("1234");
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+
+    // both scans see the move against the same snapshot, but it's only
+    // posted once
+    let args = ["scan", "--sport", "aussierules_afl", "--notify"];
+    let steam_messages = || {
+        return server
+            .discord_messages()
+            .iter()
+            .filter(|x| x.contains("Steam move"))
+            .count();
+    };
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    assert_eq!(steam_messages(), 1);
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    assert_eq!(steam_messages(), 1);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_notify_alerts_each_opportunity_once() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let path = env::temp_dir().join(format!("arb_finder_alerts_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut config = get_replay_config();
+    config.odds_host_base = server.odds_host_base();
+    config.discord_api_base = server.discord_api_base();
+    config.snapshot_db = Some(path.clone());
+    config.credentials.discord_channel_id = String::from("1234");
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+
+    let results = scan_sports(&client, &config, &[String::from("aussierules_afl")]);
+    assert!(!results.opportunities.is_empty());
+    // each bookmaker's price on an outcome is only found once
+    let keys: Vec<String> = results
+        .opportunities
+        .iter()
+        .map(|x| x.key().to_string())
+        .collect();
+    let unique: BTreeSet<&String> = keys.iter().collect();
+    assert_eq!(unique.len(), keys.len());
+
+    let args = ["scan", "--sport", "aussierules_afl", "--notify"];
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    assert_eq!(server.discord_messages().len(), keys.len());
+    // the same prices again aren't worth another alert
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    assert_eq!(server.discord_messages().len(), keys.len());
+    // nor is a scan that didn't fetch the other prices a sign they're gone
+    assert!(results
+        .opportunities
+        .iter()
+        .any(|x| x.key().market_key == MarketType::H2h));
+    for narrower in [["--markets", "totals"], ["--regions", "uk"]] {
+        let args = [&args[..], &narrower[..]].concat();
+        assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+        assert_eq!(server.discord_messages().len(), keys.len());
+    }
+
+    // once the prices are gone, each alert gets a follow up
+    let mut fixtures = MockFixtures::from_example_data(&["aussierules_afl"]);
+    fixtures
+        .odds
+        .insert(String::from("aussierules_afl"), String::from("[]"));
+    let emptied = MockOddsApi::start(fixtures);
+    config.odds_host_base = emptied.odds_host_base();
+    config.discord_api_base = emptied.discord_api_base();
+    let client = OddsApiClient::with_base_url("mock-key", &config.odds_host_base);
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    let gone = emptied.discord_messages();
+    assert_eq!(gone.len(), keys.len());
+    assert!(gone.iter().all(|x| x.contains("Opportunity gone")));
+
+    // and only one
+    assert_eq!(run_command(&args, &config, &client).0, ExitStatus::Success);
+    assert_eq!(emptied.discord_messages().len(), keys.len());
+    let _ = fs::remove_file(&path);
+}
@@ -1,19 +1,23 @@
 use serde::Serialize;
 
-use crate::local_env::MY_ENV;
+use crate::config::Credentials;
 
-const BASE_ENDPOINT: &str = "https://discord.com/api/";
-const API_KEY: &str = MY_ENV.discord_api_key;
-const CHANNEL_ID: &str = MY_ENV.discord_channel_id;
+pub const DISCORD_API_BASE: &str = "https://discord.com/api/";
 
 #[derive(Serialize)]
 struct DiscordMessage {
     content: String,
 }
 
-pub fn send_message(msg: &str) {
-    let send_message_endpoint = format!("channels/{CHANNEL_ID}/messages");
-    let full_url = BASE_ENDPOINT.to_owned() + &send_message_endpoint;
+/// Posts `msg` to the configured channel through the discord api at `api_base`
+pub fn send_message(
+    api_base: &str,
+    credentials: &Credentials,
+    msg: &str,
+) -> Result<(), reqwest::Error> {
+    let channel_id = &credentials.discord_channel_id;
+    let send_message_endpoint = format!("channels/{channel_id}/messages");
+    let full_url = api_base.to_owned() + &send_message_endpoint;
 
     let message_body = DiscordMessage {
         content: msg.to_string(),
@@ -22,7 +26,12 @@ pub fn send_message(msg: &str) {
     let client = reqwest::blocking::Client::new();
     client
         .post(full_url)
-        .header("Authorization", format!("Bot {}", API_KEY))
+        .header(
+            "Authorization",
+            format!("Bot {}", credentials.discord_api_key),
+        )
         .form(&message_body)
-        .send();
+        .send()?
+        .error_for_status()?;
+    return Ok(());
 }
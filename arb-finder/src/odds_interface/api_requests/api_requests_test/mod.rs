@@ -0,0 +1,465 @@
+use std::{path::PathBuf, time::Duration};
+
+use chrono::{TimeZone, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    StatusCode,
+};
+
+use crate::odds_interface::api_requests::{
+    client::OddsApiClient,
+    error::OddsApiError,
+    get_example_odds_file,
+    mock_server::{MockFailure, MockFixtures, MockOddsApi},
+    quota::{BudgetPolicy, QuotaBudget, QuotaTracker},
+    transport::{cassette_name, TransportMode, TransportSettings},
+    util::{get_key_usage_from_headers, ApiKeyUsage},
+};
+use crate::odds_interface::logic::{bookmaker::Region, market::MarketType};
+use crate::storage::SnapshotStore;
+
+#[test]
+fn test_error_from_status() {
+    let body = r#"{"message": "API key is not valid", "error_code": "INVALID_KEY"}"#;
+    match OddsApiError::from_status(StatusCode::UNAUTHORIZED, body) {
+        OddsApiError::Unauthorized { message } => assert_eq!(message, "API key is not valid"),
+        e => panic!("unexpected error {e:?}"),
+    }
+
+    assert!(matches!(
+        OddsApiError::from_status(StatusCode::UNPROCESSABLE_ENTITY, "{}"),
+        OddsApiError::InvalidRequest { .. }
+    ));
+    assert!(matches!(
+        OddsApiError::from_status(StatusCode::TOO_MANY_REQUESTS, "slow down"),
+        OddsApiError::QuotaExceeded { .. }
+    ));
+    assert!(matches!(
+        OddsApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, ""),
+        OddsApiError::Http { .. }
+    ));
+}
+
+#[test]
+fn test_key_usage_from_headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-requests-used", HeaderValue::from_static("12"));
+    headers.insert("x-requests-remaining", HeaderValue::from_static("488"));
+
+    let usage = get_key_usage_from_headers(&headers).unwrap();
+    assert_eq!(usage.requests_used, 12);
+    assert_eq!(usage.requests_remaining, 488);
+
+    headers.insert("x-requests-remaining", HeaderValue::from_static("lots"));
+    assert!(matches!(
+        get_key_usage_from_headers(&headers),
+        Err(OddsApiError::Header { value: Some(_), .. })
+    ));
+    for value in ["nan", "inf", "-infinity"] {
+        headers.insert("x-requests-remaining", HeaderValue::from_static(value));
+        assert!(get_key_usage_from_headers(&headers).is_err());
+    }
+
+    headers.remove("x-requests-used");
+    assert!(matches!(
+        get_key_usage_from_headers(&headers),
+        Err(OddsApiError::Header { value: None, .. })
+    ));
+}
+
+#[test]
+fn test_example_odds_file() {
+    let events = get_example_odds_file("./example_data/oddsapi/rugbyleague_nrl_odds.json").unwrap();
+    assert!(!events.is_empty());
+
+    assert!(matches!(
+        get_example_odds_file("./example_data/oddsapi/missing.json"),
+        Err(OddsApiError::Io(_))
+    ));
+}
+
+#[test]
+fn test_client_build_url() {
+    let client = OddsApiClient::with_base_url("my-key", "http://localhost:8080/v4/");
+    let url = client
+        .build_url("/sports/nrl/odds/", &[("markets", "h2h,totals")])
+        .unwrap();
+
+    assert_eq!(
+        url.as_str(),
+        "http://localhost:8080/v4/sports/nrl/odds/?apiKey=my-key&markets=h2h%2Ctotals"
+    );
+}
+
+#[tokio::test]
+async fn test_get_odds_for_sports_keeps_per_sport_results() {
+    // nothing listens on the discard port, so every request fails to connect
+    let client = OddsApiClient::with_base_url("my-key", "http://127.0.0.1:9/v4");
+    let sports = [
+        String::from("aussierules_afl"),
+        String::from("baseball_kbo"),
+        String::from("rugbyleague_nrl"),
+    ];
+
+    let results = client
+        .get_odds_for_sports(&sports, &[MarketType::H2h], &[Region::Au], 2)
+        .await;
+
+    assert_eq!(results.len(), 3);
+    for (result, sport_key) in results.iter().zip(&sports) {
+        assert_eq!(&result.sport_key, sport_key);
+        assert!(matches!(result.odds, Err(OddsApiError::Transport(_))));
+    }
+}
+
+fn usage(used: i32, remaining: i32, last: i32) -> ApiKeyUsage {
+    return ApiKeyUsage {
+        requests_used: used,
+        requests_remaining: remaining,
+        requests_last: Some(last),
+    };
+}
+
+#[test]
+fn test_quota_available_uses_tightest_limit() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+    let tracker = QuotaTracker::new(QuotaBudget {
+        daily: Some(20),
+        monthly: Some(100),
+        on_exceed: BudgetPolicy::Refuse,
+        reset_day: None,
+    });
+    assert_eq!(tracker.available(now), Some(20));
+
+    // yesterday's spend doesn't count towards today's budget
+    tracker.record(usage(80, 420, 6), 0, now - chrono::Duration::days(1));
+    tracker.record(usage(92, 408, 12), 0, now);
+    assert_eq!(tracker.available(now), Some(8));
+
+    tracker.record(usage(97, 403, 5), 0, now);
+    assert_eq!(tracker.available(now), Some(3));
+}
+
+#[test]
+fn test_quota_sustainable_rate() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap();
+    assert_eq!(QuotaTracker::default().sustainable_rate(now), None);
+
+    let tracker = QuotaTracker::new(QuotaBudget {
+        daily: Some(72),
+        monthly: Some(1000),
+        on_exceed: BudgetPolicy::Refuse,
+        reset_day: None,
+    });
+    // 72 requests over the 12 hours left today
+    assert_eq!(tracker.sustainable_rate(now), Some(0.1));
+
+    // the month also ends tonight, with 36 requests left on the key
+    tracker.record(usage(464, 36, 0), 0, now);
+    assert_eq!(tracker.sustainable_rate(now), Some(0.05));
+
+    tracker.record(usage(500, 0, 36), 0, now);
+    assert_eq!(tracker.sustainable_rate(now), Some(0.0));
+}
+
+#[test]
+fn test_quota_plan_refuses_over_budget() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+    let tracker = QuotaTracker::new(QuotaBudget {
+        daily: Some(5),
+        monthly: None,
+        on_exceed: BudgetPolicy::Refuse,
+        reset_day: None,
+    });
+    let markets = [MarketType::H2h, MarketType::Totals];
+    let regions = [Region::Au, Region::Uk, Region::Us];
+
+    match tracker.plan(&markets, &regions, now) {
+        Err(OddsApiError::BudgetExceeded { cost, available }) => {
+            assert_eq!(cost, 6);
+            assert_eq!(available, 5);
+        }
+        x => panic!("unexpected plan {x:?}"),
+    }
+
+    let plan = tracker.plan(&markets, &regions[..2], now).unwrap();
+    assert_eq!(plan.cost, 4);
+    assert!(!plan.downscaled);
+}
+
+#[test]
+fn test_quota_plan_downscales() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+    let tracker = QuotaTracker::new(QuotaBudget {
+        daily: Some(3),
+        monthly: None,
+        on_exceed: BudgetPolicy::Downscale,
+        reset_day: None,
+    });
+    let markets = [MarketType::H2h, MarketType::Spreads, MarketType::Totals];
+    let regions = [Region::Au, Region::Uk];
+
+    let plan = tracker.plan(&markets, &regions, now).unwrap();
+    assert!(plan.downscaled);
+    assert_eq!(plan.regions, [Region::Au]);
+    assert_eq!(plan.markets.len(), 3);
+    assert_eq!(plan.cost, 3);
+
+    tracker.record(usage(3, 497, 3), plan.cost, now);
+    assert!(matches!(
+        tracker.plan(&markets, &regions, now),
+        Err(OddsApiError::BudgetExceeded { available: 0, .. })
+    ));
+}
+
+#[test]
+fn test_quota_plan_reserves_until_charged() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+    let tracker = QuotaTracker::new(QuotaBudget {
+        daily: Some(5),
+        monthly: None,
+        on_exceed: BudgetPolicy::Refuse,
+        reset_day: None,
+    });
+    let markets = [MarketType::H2h, MarketType::Totals];
+
+    // two requests planned before either is charged can't share the budget
+    let first = tracker.plan(&markets, &[Region::Au], now).unwrap();
+    assert_eq!(tracker.available(now), Some(3));
+    let second = tracker.plan(&markets, &[Region::Au], now).unwrap();
+    assert!(matches!(
+        tracker.plan(&markets, &[Region::Au], now),
+        Err(OddsApiError::BudgetExceeded { available: 1, .. })
+    ));
+
+    // charged less than planned, and a request that failed
+    tracker.record(usage(1, 499, 1), first.cost, now);
+    assert_eq!(tracker.available(now), Some(2));
+    tracker.release(second.cost);
+    assert_eq!(tracker.available(now), Some(4));
+}
+
+#[tokio::test]
+async fn test_scores_are_reserved_against_the_budget() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = get_mock_client(&server).with_budget(QuotaBudget {
+        daily: Some(3),
+        monthly: None,
+        on_exceed: BudgetPolicy::Downscale,
+        reset_day: None,
+    });
+
+    assert!(client.get_scores("aussierules_afl", Some(3)).await.is_ok());
+    assert_eq!(client.quota().available(Utc::now()), Some(1));
+    // daysFrom doubles the cost, and it can't be downscaled
+    assert!(matches!(
+        client.get_scores("aussierules_afl", Some(3)).await,
+        Err(OddsApiError::BudgetExceeded {
+            cost: 2,
+            available: 1
+        })
+    ));
+    assert!(client.get_scores("aussierules_afl", None).await.is_ok());
+    assert!(client.get_events("aussierules_afl").await.is_ok());
+    assert_eq!(server.requests_used(), 3);
+}
+
+#[test]
+fn test_quota_spend_outlives_the_tracker() {
+    let path = std::env::temp_dir().join(format!("arb_finder_quota_{0}.db", std::process::id()));
+    let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+    let budget = QuotaBudget {
+        daily: Some(20),
+        monthly: Some(100),
+        on_exceed: BudgetPolicy::Refuse,
+        reset_day: Some(20),
+    };
+
+    let tracker = QuotaTracker::new(budget).with_store(SnapshotStore::open(&path).unwrap());
+    tracker.record(usage(40, 460, 6), 6, now - chrono::Duration::hours(2));
+    tracker.record(usage(48, 452, 8), 8, now);
+    drop(tracker);
+
+    // a later run starts with what's left of today's budget
+    let tracker = QuotaTracker::new(budget).with_store(SnapshotStore::open(&path).unwrap());
+    assert_eq!(tracker.available(now), Some(6));
+    std::fs::remove_file(&path).unwrap();
+
+    // the key resets on the 20th, leaving 52 of the 100 for 4.5 days
+    tracker.record(usage(48, 452, 0), 0, now);
+    assert_eq!(
+        tracker.sustainable_rate(now),
+        Some(52.0 / (4.5 * 24.0 * 60.0))
+    );
+}
+
+#[test]
+fn test_quota_projected_exhaustion() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 11, 0, 0, 0).unwrap();
+    let tracker = QuotaTracker::default();
+    assert_eq!(tracker.projected_exhaustion(now), None);
+
+    // 100 requests in 10 days leaves 20 days for the other 200
+    tracker.record(usage(100, 200, 1), 0, now);
+    assert_eq!(
+        tracker.projected_exhaustion(now),
+        Some(Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_replay_missing_cassette() {
+    let settings = TransportSettings {
+        mode: TransportMode::Replay,
+        cassette_dir: PathBuf::from("./example_data/cassettes"),
+        ..Default::default()
+    };
+    let client =
+        OddsApiClient::with_base_url("my-key", "http://127.0.0.1:9/v4").with_transport(settings);
+
+    let events = client
+        .get_odds_for_sport(
+            "aussierules_afl",
+            &[MarketType::H2h, MarketType::Totals],
+            &[Region::Au],
+        )
+        .await
+        .unwrap();
+    assert!(!events.is_empty());
+
+    match client
+        .get_odds_for_sport("aussierules_afl", &[MarketType::H2h], &[Region::Au])
+        .await
+    {
+        Err(OddsApiError::MissingCassette { path }) => {
+            assert!(path.ends_with("v4_sports_aussierules_afl_odds__markets=h2h__regions=au.json"))
+        }
+        x => panic!("unexpected result {x:?}"),
+    }
+}
+
+#[test]
+fn test_cassette_name_ignores_host_and_key() {
+    let url = OddsApiClient::with_base_url("secret", "http://localhost:8080/v4")
+        .build_url(
+            "/sports/nrl/odds/",
+            &[("regions", "au"), ("markets", "h2h,totals")],
+        )
+        .unwrap();
+
+    let name = cassette_name(&url);
+    assert_eq!(
+        name,
+        "v4_sports_nrl_odds__markets=h2h_totals__regions=au.json"
+    );
+    assert!(!name.contains("secret"));
+}
+
+fn get_mock_client(server: &MockOddsApi) -> OddsApiClient {
+    return OddsApiClient::with_base_url("mock-key", &server.odds_host_base());
+}
+
+#[tokio::test]
+async fn test_mock_server_endpoints() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = get_mock_client(&server);
+
+    assert_eq!(client.get_sports().await.unwrap().len(), 1);
+
+    let events = client.get_events("aussierules_afl").await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].get_all_bookies().is_empty());
+
+    let scores = client.get_scores("aussierules_afl", Some(3)).await.unwrap();
+    assert!(scores[0].completed);
+    assert_eq!(scores[0].score_for("St Kilda Saints"), Some(88.0));
+
+    let odds = client
+        .get_odds_for_sport(
+            "aussierules_afl",
+            &[MarketType::H2h],
+            &[Region::Au, Region::Uk],
+        )
+        .await
+        .unwrap();
+    assert!(!odds[0].get_all_bookies().is_empty());
+
+    // scores with daysFrom cost 2, odds one per market per region
+    assert_eq!(server.requests_used(), 4);
+    assert_eq!(client.get_key_usage().await.unwrap().requests_last, Some(2));
+
+    assert!(matches!(
+        client
+            .get_odds_for_sport("cricket_ipl", &[MarketType::H2h], &[Region::Au])
+            .await,
+        Err(OddsApiError::Http { .. })
+    ));
+    assert!(matches!(
+        OddsApiClient::with_base_url("", &server.odds_host_base())
+            .get_sports()
+            .await,
+        Err(OddsApiError::Unauthorized { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_odds_are_aged_against_response_date() {
+    let mut fixtures = MockFixtures::from_example_data(&["aussierules_afl"]);
+    fixtures.date = Some(String::from("Sun, 04 Aug 2024 06:00:00 GMT"));
+    let server = MockOddsApi::start(fixtures);
+    let client = get_mock_client(&server);
+
+    let odds = client
+        .get_odds_for_sport("aussierules_afl", &[MarketType::H2h], &[Region::Au])
+        .await
+        .unwrap();
+    assert_eq!(
+        odds[0].fetched_at(),
+        Some(Utc.with_ymd_and_hms(2024, 8, 4, 6, 0, 0).unwrap())
+    );
+    // sportsbet last updated at 05:15:36
+    assert_eq!(
+        odds[0].quote_age("sportsbet").unwrap().num_seconds(),
+        44 * 60 + 24
+    );
+}
+
+#[tokio::test]
+async fn test_mock_server_failures() {
+    let server = MockOddsApi::start(MockFixtures::from_example_data(&["aussierules_afl"]));
+    let client = get_mock_client(&server);
+    let get_odds =
+        || client.get_odds_for_sport("aussierules_afl", &[MarketType::H2h], &[Region::Au]);
+
+    server.set_failure(Some(MockFailure::Unauthorized));
+    assert!(matches!(
+        get_odds().await,
+        Err(OddsApiError::Unauthorized { .. })
+    ));
+
+    server.set_failure(Some(MockFailure::TooManyRequests));
+    assert!(matches!(
+        get_odds().await,
+        Err(OddsApiError::QuotaExceeded { .. })
+    ));
+
+    server.set_failure(Some(MockFailure::MalformedJson));
+    assert!(matches!(
+        get_odds().await,
+        Err(OddsApiError::Deserialize(_))
+    ));
+
+    server.set_failure(Some(MockFailure::Slow(Duration::from_millis(500))));
+    let impatient_client = get_mock_client(&server).with_transport(TransportSettings {
+        timeout_secs: 0.1,
+        ..Default::default()
+    });
+    match impatient_client
+        .get_odds_for_sport("aussierules_afl", &[MarketType::H2h], &[Region::Au])
+        .await
+    {
+        Err(OddsApiError::Transport(e)) => assert!(e.is_timeout()),
+        x => panic!("unexpected result {x:?}"),
+    }
+    assert!(get_odds().await.is_ok());
+}
@@ -0,0 +1,292 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::{stream, StreamExt};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::error::OddsApiError;
+use super::quota::{
+    scores_cost, FetchPlan, QuotaBudget, QuotaTracker, EVENTS_COST, HISTORICAL_COST_MULTIPLIER,
+};
+use super::transport::{HttpResponse, Transport, TransportSettings};
+use super::util::{get_key_usage_from_headers, ApiKeyUsage};
+use crate::odds_interface::logic::bookmaker::Region;
+use crate::odds_interface::logic::market::MarketType;
+use crate::odds_interface::logic::{event::Event, score::EventScore, sport::Sport};
+use crate::storage::SnapshotStore;
+
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Odds fetched for one sport as part of a multi-sport request
+#[derive(Debug)]
+pub struct SportOdds {
+    pub sport_key: String,
+    /// The events, and the markets and regions requested once the budget
+    /// was applied
+    pub odds: Result<(Vec<Event>, FetchPlan), OddsApiError>,
+}
+
+/// Odds as they were at `timestamp`, the snapshot closest to and no later
+/// than the requested date
+#[derive(Deserialize, Debug)]
+pub struct HistoricalOdds {
+    pub timestamp: DateTime<Utc>,
+    /// The snapshot after this one, if there is one yet
+    pub next_timestamp: Option<DateTime<Utc>>,
+    pub data: Vec<Event>,
+}
+
+/// Async Odds API client. Cloning is cheap and shares the connection pool.
+#[derive(Debug, Clone)]
+pub struct OddsApiClient {
+    api_key: String,
+    base_url: String,
+    transport: Transport,
+    quota: QuotaTracker,
+}
+
+fn join_keys<T: ToString>(keys: &[T]) -> String {
+    return keys
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+}
+
+impl OddsApiClient {
+    pub fn with_base_url(api_key: &str, base_url: &str) -> Self {
+        return OddsApiClient {
+            api_key: api_key.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            transport: Transport::new(TransportSettings::default()),
+            quota: QuotaTracker::default(),
+        };
+    }
+
+    /// Limits requests made with this client to `budget`
+    pub fn with_budget(self, budget: QuotaBudget) -> Self {
+        return OddsApiClient {
+            quota: QuotaTracker::new(budget),
+            ..self
+        };
+    }
+
+    /// Keeps the requests spent in `store`, shared with other runs
+    pub fn with_spend_store(self, store: SnapshotStore) -> Self {
+        return OddsApiClient {
+            quota: self.quota.with_store(store),
+            ..self
+        };
+    }
+
+    /// Sends requests live, recording them, or replaying recordings
+    pub fn with_transport(self, settings: TransportSettings) -> Self {
+        return OddsApiClient {
+            transport: Transport::new(settings),
+            ..self
+        };
+    }
+
+    pub fn quota(&self) -> &QuotaTracker {
+        return &self.quota;
+    }
+
+    /// Full url for `endpoint`, with the api key added to `params`
+    pub fn build_url(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<Url, OddsApiError> {
+        let full_url = self.base_url.clone() + endpoint;
+        let mut all_params = vec![("apiKey", self.api_key.as_str())];
+        all_params.extend_from_slice(params);
+
+        return Url::parse_with_params(&full_url, &all_params)
+            .map_err(|e| OddsApiError::InvalidUrl(e.to_string()));
+    }
+
+    /// Sends a GET request, turning non-success statuses into errors and
+    /// recording the quota headers of every response. `reserved` is what
+    /// the request's plan reserved from the quota.
+    async fn send_request(&self, url: Url, reserved: u32) -> Result<HttpResponse, OddsApiError> {
+        let res = match self.transport.get(url).await {
+            Ok(x) => x,
+            Err(e) => {
+                self.quota.release(reserved);
+                return Err(e);
+            }
+        };
+
+        match get_key_usage_from_headers(&res.header_map()) {
+            Ok(usage) => self.quota.record(usage, reserved, Utc::now()),
+            Err(_) => self.quota.release(reserved),
+        }
+
+        let status = res.status();
+        if (!status.is_success()) {
+            return Err(OddsApiError::from_status(status, &res.body));
+        }
+
+        return Ok(res);
+    }
+
+    fn parse_body<T: DeserializeOwned>(res: HttpResponse) -> Result<T, OddsApiError> {
+        return Ok(serde_json::from_str::<T>(&res.body)?);
+    }
+
+    pub async fn get_sports(&self) -> Result<Vec<Sport>, OddsApiError> {
+        let url = self.build_url("/sports/", &[])?;
+        let res = self.send_request(url, 0).await?;
+        return Self::parse_body::<Vec<Sport>>(res);
+    }
+
+    /// Latest quota usage. Only makes a request, to the free `/sports`
+    /// endpoint, if no response has told us the usage yet.
+    pub async fn get_key_usage(&self) -> Result<ApiKeyUsage, OddsApiError> {
+        if let Some(usage) = self.quota.latest_usage() {
+            return Ok(usage);
+        }
+
+        let url = self.build_url("/sports/", &[])?;
+        let res = self.send_request(url, 0).await?;
+        return get_key_usage_from_headers(&res.header_map());
+    }
+
+    pub async fn get_odds_for_sport(
+        &self,
+        sport: &str,
+        markets: &[MarketType],
+        regions: &[Region],
+    ) -> Result<Vec<Event>, OddsApiError> {
+        return Ok(self.get_planned_odds(sport, markets, regions).await?.0);
+    }
+
+    /// Same as `get_odds_for_sport`, along with the plan saying which of
+    /// `markets` and `regions` were actually requested
+    pub async fn get_planned_odds(
+        &self,
+        sport: &str,
+        markets: &[MarketType],
+        regions: &[Region],
+    ) -> Result<(Vec<Event>, FetchPlan), OddsApiError> {
+        let plan = self.quota.plan(markets, regions, Utc::now())?;
+
+        let odds_endpoint = format!("/sports/{sport}/odds/");
+        let regions_param = join_keys(&plan.regions);
+        let markets_param = join_keys(&plan.markets);
+
+        let url = self
+            .build_url(
+                &odds_endpoint,
+                &[("regions", &regions_param), ("markets", &markets_param)],
+            )
+            .inspect_err(|_| self.quota.release(plan.cost))?;
+        let res = self.send_request(url, plan.cost).await?;
+        let fetched_at = res.date();
+        let mut events = Self::parse_body::<Vec<Event>>(res)?;
+
+        // quotes are aged against the server's clock, so replayed
+        // recordings age the same as they did live
+        if let Some(fetched_at) = fetched_at {
+            for event in events.iter_mut() {
+                event.set_fetched_at(fetched_at);
+            }
+        }
+        return Ok((events, plan));
+    }
+
+    /// Odds for `sport` as they were at `date`, from the historical
+    /// endpoint. Only available on paid plans, and charged at ten times the
+    /// cost of current odds.
+    pub async fn get_historical_odds(
+        &self,
+        sport: &str,
+        markets: &[MarketType],
+        regions: &[Region],
+        date: DateTime<Utc>,
+    ) -> Result<HistoricalOdds, OddsApiError> {
+        let plan = self.quota.plan_with_multiplier(
+            markets,
+            regions,
+            HISTORICAL_COST_MULTIPLIER,
+            Utc::now(),
+        )?;
+
+        let odds_endpoint = format!("/historical/sports/{sport}/odds/");
+        let regions_param = join_keys(&plan.regions);
+        let markets_param = join_keys(&plan.markets);
+        let date_param = date.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let url = self
+            .build_url(
+                &odds_endpoint,
+                &[
+                    ("regions", &regions_param),
+                    ("markets", &markets_param),
+                    ("date", &date_param),
+                ],
+            )
+            .inspect_err(|_| self.quota.release(plan.cost))?;
+        let res = self.send_request(url, plan.cost).await?;
+        let mut historical = Self::parse_body::<HistoricalOdds>(res)?;
+
+        for event in historical.data.iter_mut() {
+            event.set_fetched_at(historical.timestamp);
+        }
+        return Ok(historical);
+    }
+
+    /// Upcoming and live events for `sport`, without odds. Doesn't count
+    /// towards the quota.
+    pub async fn get_events(&self, sport: &str) -> Result<Vec<Event>, OddsApiError> {
+        let reserved = self.quota.reserve(EVENTS_COST, Utc::now())?;
+        let url = self
+            .build_url(&format!("/sports/{sport}/events/"), &[])
+            .inspect_err(|_| self.quota.release(reserved))?;
+        let res = self.send_request(url, reserved).await?;
+        return Self::parse_body::<Vec<Event>>(res);
+    }
+
+    /// Live and upcoming scores for `sport`, plus those completed in the
+    /// last `days_from` days (1 to 3), which doubles the request's cost.
+    /// Refused if the cost would go over budget.
+    pub async fn get_scores(
+        &self,
+        sport: &str,
+        days_from: Option<u32>,
+    ) -> Result<Vec<EventScore>, OddsApiError> {
+        let scores_endpoint = format!("/sports/{sport}/scores/");
+        let days_from_param = days_from.map(|x| x.to_string());
+        let reserved = self.quota.reserve(scores_cost(days_from), Utc::now())?;
+
+        let url = match &days_from_param {
+            Some(x) => self.build_url(&scores_endpoint, &[("daysFrom", x)]),
+            None => self.build_url(&scores_endpoint, &[]),
+        }
+        .inspect_err(|_| self.quota.release(reserved))?;
+        let res = self.send_request(url, reserved).await?;
+        return Self::parse_body::<Vec<EventScore>>(res);
+    }
+
+    /// Fetches odds for every sport in `sports`, with at most
+    /// `max_concurrent` requests in flight. Results come back in the same
+    /// order as `sports`, and one sport failing doesn't stop the others.
+    pub async fn get_odds_for_sports(
+        &self,
+        sports: &[String],
+        markets: &[MarketType],
+        regions: &[Region],
+        max_concurrent: usize,
+    ) -> Vec<SportOdds> {
+        let mut results: Vec<(usize, SportOdds)> = stream::iter(sports.iter().enumerate())
+            .map(|(i, sport_key)| async move {
+                let odds = self.get_planned_odds(sport_key, markets, regions).await;
+                let sport_odds = SportOdds {
+                    sport_key: sport_key.clone(),
+                    odds,
+                };
+                return (i, sport_odds);
+            })
+            .buffer_unordered(max_concurrent.max(1))
+            .collect()
+            .await;
+
+        results.sort_by_key(|(i, _)| *i);
+        return results.into_iter().map(|(_, x)| x).collect();
+    }
+}
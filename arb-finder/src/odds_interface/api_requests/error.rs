@@ -0,0 +1,114 @@
+use std::{fmt, path::PathBuf};
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+/// Everything that can go wrong talking to the Odds API
+#[derive(Debug)]
+pub enum OddsApiError {
+    /// The request never got a response, e.g. DNS or connection failures
+    Transport(reqwest::Error),
+    /// 401: the API key is missing or invalid
+    Unauthorized {
+        message: String,
+    },
+    /// 422: the sport, market or region parameters were rejected
+    InvalidRequest {
+        message: String,
+    },
+    /// 429: the key has run out of requests, or we are sending them too fast
+    QuotaExceeded {
+        message: String,
+    },
+    /// Any other non-success status
+    Http {
+        status: StatusCode,
+        message: String,
+    },
+    /// The response body wasn't the JSON we expected
+    Deserialize(serde_json::Error),
+    /// A quota header was missing or not a number
+    Header {
+        name: String,
+        value: Option<String>,
+    },
+    /// The request would cost more than the quota budget has left
+    BudgetExceeded {
+        cost: u32,
+        available: u32,
+    },
+    /// Replaying, and no cassette was recorded for the request
+    MissingCassette {
+        path: PathBuf,
+    },
+    InvalidUrl(String),
+    Io(std::io::Error),
+}
+
+/// Body the Odds API sends back with error statuses
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+impl OddsApiError {
+    pub fn from_status(status: StatusCode, body: &str) -> OddsApiError {
+        let message = match serde_json::from_str::<ErrorBody>(body) {
+            Ok(x) => x.message,
+            Err(_) => body.to_string(),
+        };
+
+        return match status {
+            StatusCode::UNAUTHORIZED => OddsApiError::Unauthorized { message },
+            StatusCode::UNPROCESSABLE_ENTITY => OddsApiError::InvalidRequest { message },
+            StatusCode::TOO_MANY_REQUESTS => OddsApiError::QuotaExceeded { message },
+            _ => OddsApiError::Http { status, message },
+        };
+    }
+}
+
+impl fmt::Display for OddsApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OddsApiError::Transport(e) => write!(f, "request to the odds api failed: {e}"),
+            OddsApiError::Unauthorized { message } => write!(f, "api key rejected: {message}"),
+            OddsApiError::InvalidRequest { message } => write!(f, "invalid request: {message}"),
+            OddsApiError::QuotaExceeded { message } => write!(f, "quota exceeded: {message}"),
+            OddsApiError::Http { status, message } => write!(f, "http {status}: {message}"),
+            OddsApiError::Deserialize(e) => write!(f, "unexpected response body: {e}"),
+            OddsApiError::Header { name, value } => match value {
+                Some(x) => write!(f, "header {name} has invalid value {x:?}"),
+                None => write!(f, "header {name} is missing"),
+            },
+            OddsApiError::BudgetExceeded { cost, available } => write!(
+                f,
+                "request would cost {cost} but only {available} left in the quota budget"
+            ),
+            OddsApiError::MissingCassette { path } => {
+                write!(f, "no cassette recorded at {0}", path.display())
+            }
+            OddsApiError::InvalidUrl(e) => write!(f, "could not build url: {e}"),
+            OddsApiError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for OddsApiError {}
+
+impl From<reqwest::Error> for OddsApiError {
+    fn from(e: reqwest::Error) -> Self {
+        return OddsApiError::Transport(e);
+    }
+}
+
+impl From<serde_json::Error> for OddsApiError {
+    fn from(e: serde_json::Error) -> Self {
+        return OddsApiError::Deserialize(e);
+    }
+}
+
+impl From<std::io::Error> for OddsApiError {
+    fn from(e: std::io::Error) -> Self {
+        return OddsApiError::Io(e);
+    }
+}
@@ -0,0 +1,476 @@
+//! A local stand-in for the Odds API (and the discord message endpoint) for
+//! end-to-end tests. It speaks just enough HTTP/1.1 for reqwest, serves
+//! fixtures, charges quota like the real api and can be told to fail.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use reqwest::Url;
+use serde_json::Value;
+
+use super::EXAMPLE_RESPONSES_DIR;
+use crate::odds_interface::logic::bookmaker::Region;
+
+const DEFAULT_QUOTA: i32 = 500;
+/// How often the historical endpoint has a snapshot, as the real one does
+const HISTORICAL_INTERVAL_MINUTES: i64 = 5;
+
+/// Ways to make a request fail
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MockFailure {
+    /// 401 with an invalid key message
+    Unauthorized,
+    /// 429 with an out of requests message
+    TooManyRequests,
+    /// 200 with a body cut off halfway
+    MalformedJson,
+    /// The normal response, after a delay
+    Slow(Duration),
+}
+
+/// Response bodies to serve, keyed by sport key
+#[derive(Debug, Clone, Default)]
+pub struct MockFixtures {
+    pub sports: Vec<Value>,
+    pub odds: HashMap<String, String>,
+    /// Sports without events fall back to their odds, minus the bookmakers
+    pub events: HashMap<String, String>,
+    pub scores: HashMap<String, String>,
+    /// Failure for every odds api request
+    pub failure: Option<MockFailure>,
+    /// Failures for requests about one sport, overriding `failure`
+    pub sport_failures: HashMap<String, MockFailure>,
+    /// `date` header to send, which quotes are aged against
+    pub date: Option<String>,
+}
+
+impl MockFixtures {
+    /// Fixtures from `{sport_key}_odds.json` and `{sport_key}_scores.json` in
+    /// `EXAMPLE_RESPONSES_DIR`, for whichever of those exist
+    pub fn from_example_data(sport_keys: &[&str]) -> Self {
+        let mut fixtures = MockFixtures::default();
+        for sport_key in sport_keys {
+            let read = |kind: &str| {
+                fs::read_to_string(format!("{EXAMPLE_RESPONSES_DIR}/{sport_key}_{kind}.json")).ok()
+            };
+
+            let odds = match read("odds") {
+                Some(x) => x,
+                None => continue,
+            };
+            let title = serde_json::from_str::<Vec<Value>>(&odds)
+                .ok()
+                .and_then(|x| x.first()?.get("sport_title").cloned())
+                .unwrap_or(Value::from(*sport_key));
+
+            fixtures.sports.push(serde_json::json!({
+                "key": sport_key,
+                "group": title,
+                "title": title,
+                "description": title,
+                "active": true,
+                "has_outrights": false,
+            }));
+            fixtures.odds.insert(sport_key.to_string(), odds);
+            if let Some(scores) = read("scores") {
+                fixtures.scores.insert(sport_key.to_string(), scores);
+            }
+        }
+        return fixtures;
+    }
+
+    fn failure_for(&self, sport_key: Option<&str>) -> Option<MockFailure> {
+        if let Some(failure) = sport_key.and_then(|x| self.sport_failures.get(x)) {
+            return Some(*failure);
+        }
+        return self.failure;
+    }
+
+    fn events_for(&self, sport_key: &str) -> Option<String> {
+        if let Some(events) = self.events.get(sport_key) {
+            return Some(events.clone());
+        }
+
+        let mut events = serde_json::from_str::<Vec<Value>>(self.odds.get(sport_key)?).ok()?;
+        for event in events.iter_mut() {
+            if let Some(x) = event.as_object_mut() {
+                x.remove("bookmakers");
+            }
+        }
+        return serde_json::to_string(&events).ok();
+    }
+}
+
+/// A request the server has seen
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    fixtures: MockFixtures,
+    requests_used: i32,
+    requests_remaining: i32,
+    requests: Vec<MockRequest>,
+    discord_messages: Vec<String>,
+}
+
+struct MockResponse {
+    status: u16,
+    body: String,
+    cost: Option<i32>,
+}
+
+/// Handle to a running mock server. The server keeps running on background
+/// threads until the test process exits.
+#[derive(Debug, Clone)]
+pub struct MockOddsApi {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockOddsApi {
+    pub fn start(fixtures: MockFixtures) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock server");
+        let port = listener.local_addr().unwrap().port();
+
+        let state = Arc::new(Mutex::new(MockState {
+            fixtures,
+            requests_used: 0,
+            requests_remaining: DEFAULT_QUOTA,
+            requests: Vec::new(),
+            discord_messages: Vec::new(),
+        }));
+
+        let server_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let connection_state = server_state.clone();
+                thread::spawn(move || handle_connection(stream, &connection_state));
+            }
+        });
+
+        return MockOddsApi {
+            base_url: format!("http://127.0.0.1:{port}"),
+            state,
+        };
+    }
+
+    /// Base url to use in place of `ODDS_HOST_BASE`
+    pub fn odds_host_base(&self) -> String {
+        return format!("{0}/v4", self.base_url);
+    }
+
+    /// Base url to use in place of `DISCORD_API_BASE`
+    pub fn discord_api_base(&self) -> String {
+        return format!("{0}/api/", self.base_url);
+    }
+
+    pub fn set_failure(&self, failure: Option<MockFailure>) {
+        self.state.lock().unwrap().fixtures.failure = failure;
+    }
+
+    pub fn set_sport_failure(&self, sport_key: &str, failure: MockFailure) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .fixtures
+            .sport_failures
+            .insert(sport_key.to_string(), failure);
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        return self.state.lock().unwrap().requests.clone();
+    }
+
+    pub fn requests_used(&self) -> i32 {
+        return self.state.lock().unwrap().requests_used;
+    }
+
+    pub fn discord_messages(&self) -> Vec<String> {
+        return self.state.lock().unwrap().discord_messages.clone();
+    }
+}
+
+/// `odds` with only the `markets` asked for, from the bookmakers of the
+/// `regions` asked for, like the real api. Books of no known region are
+/// kept.
+fn filter_odds(odds: &str, query: &HashMap<String, String>) -> String {
+    let requested = |name: &str| -> Vec<String> {
+        return query
+            .get(name)
+            .map(|x| x.split(',').map(|x| x.to_string()).collect())
+            .unwrap_or_default();
+    };
+    let markets = requested("markets");
+    let regions: Vec<Region> = requested("regions")
+        .iter()
+        .filter_map(|x| x.parse().ok())
+        .collect();
+    let in_regions = |bookmaker_key: &str| {
+        let bookmaker_regions = Region::of_bookmaker(bookmaker_key);
+        return bookmaker_regions.is_empty()
+            || bookmaker_regions.iter().any(|x| regions.contains(x));
+    };
+
+    let mut events: Vec<Value> = match serde_json::from_str(odds) {
+        Ok(x) => x,
+        Err(_) => return odds.to_string(),
+    };
+    for event in events.iter_mut() {
+        let bookmakers = match event["bookmakers"].as_array_mut() {
+            Some(x) => x,
+            None => continue,
+        };
+        bookmakers.retain(|x| in_regions(x["key"].as_str().unwrap_or_default()));
+        for bookmaker in bookmakers.iter_mut() {
+            if let Some(x) = bookmaker["markets"].as_array_mut() {
+                x.retain(|market| markets.iter().any(|key| market["key"] == key.as_str()));
+            }
+        }
+        bookmakers.retain(|x| x["markets"].as_array().is_some_and(|x| !x.is_empty()));
+    }
+    return Value::from(events).to_string();
+}
+
+fn error_body(message: &str) -> String {
+    return serde_json::json!({ "message": message }).to_string();
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => return "OK",
+        401 => return "Unauthorized",
+        404 => return "Not Found",
+        429 => return "Too Many Requests",
+        _ => return "Error",
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<MockState>) {
+    let mut reader = BufReader::new(match stream.try_clone() {
+        Ok(x) => x,
+        Err(_) => return,
+    });
+
+    let mut request_line = String::new();
+    if (reader.read_line(&mut request_line).is_err()) {
+        return;
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if (reader.read_line(&mut header).is_err() || header.trim().is_empty()) {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if (name.trim().eq_ignore_ascii_case("content-length")) {
+                content_length = value.trim().parse::<usize>().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    if (reader.read_exact(&mut body).is_err()) {
+        return;
+    }
+
+    let url = match Url::parse(&format!("http://mock{target}")) {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+    let request = MockRequest {
+        method,
+        path: url.path().to_string(),
+        query: url.query_pairs().into_owned().collect(),
+    };
+
+    let (response, delay) = respond(&request, &String::from_utf8_lossy(&body), state);
+    if let Some(delay) = delay {
+        thread::sleep(delay);
+    }
+
+    let quota_headers = {
+        let mut state = state.lock().unwrap();
+        match response.cost {
+            Some(cost) => {
+                state.requests_used += cost;
+                state.requests_remaining -= cost;
+                format!(
+                    "x-requests-used: {0}\r\nx-requests-remaining: {1}\r\nx-requests-last: {cost}\r\n",
+                    state.requests_used, state.requests_remaining
+                )
+            }
+            None => String::new(),
+        }
+    };
+
+    let date_header = match &state.lock().unwrap().fixtures.date {
+        Some(x) => format!("date: {x}\r\n"),
+        None => String::new(),
+    };
+
+    let raw_response = format!(
+        "HTTP/1.1 {0} {1}\r\ncontent-type: application/json\r\ncontent-length: {2}\r\nconnection: close\r\n{3}\r\n{4}",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len(),
+        date_header + &quota_headers,
+        response.body
+    );
+    let _ = stream.write_all(raw_response.as_bytes());
+}
+
+/// Picks the response for `request`, and how long to wait before sending it
+fn respond(
+    request: &MockRequest,
+    body: &str,
+    state: &Mutex<MockState>,
+) -> (MockResponse, Option<Duration>) {
+    let mut state = state.lock().unwrap();
+    state.requests.push(request.clone());
+
+    let segments: Vec<&str> = request.path.split('/').filter(|x| !x.is_empty()).collect();
+
+    // discord: POST /api/channels/{id}/messages
+    if let ["api", "channels", _, "messages"] = segments.as_slice() {
+        let form = Url::parse(&format!("http://mock/?{body}")).ok();
+        let content = form
+            .and_then(|x| {
+                x.query_pairs()
+                    .find(|(k, _)| k == "content")
+                    .map(|(_, v)| v.to_string())
+            })
+            .unwrap_or_default();
+        state.discord_messages.push(content);
+        let response = MockResponse {
+            status: 200,
+            body: String::from("{}"),
+            cost: None,
+        };
+        return (response, None);
+    }
+
+    let not_found = MockResponse {
+        status: 404,
+        body: error_body("Unknown sport"),
+        cost: None,
+    };
+    let (sport_key, endpoint) = match segments.as_slice() {
+        ["v4", "sports"] => (None, "sports"),
+        ["v4", "sports", sport_key, endpoint] => (Some(*sport_key), *endpoint),
+        ["v4", "historical", "sports", sport_key, "odds"] => (Some(*sport_key), "historical"),
+        _ => return (not_found, None),
+    };
+
+    if (request.query.get("apiKey").map_or(true, |x| x.is_empty())) {
+        let response = MockResponse {
+            status: 401,
+            body: error_body("API key is missing"),
+            cost: Some(0),
+        };
+        return (response, None);
+    }
+
+    let mut delay = None;
+    match state.fixtures.failure_for(sport_key) {
+        Some(MockFailure::Unauthorized) => {
+            let response = MockResponse {
+                status: 401,
+                body: error_body("API key is not valid"),
+                cost: Some(0),
+            };
+            return (response, None);
+        }
+        Some(MockFailure::TooManyRequests) => {
+            let response = MockResponse {
+                status: 429,
+                body: error_body("Usage quota has been reached"),
+                cost: Some(0),
+            };
+            return (response, None);
+        }
+        Some(MockFailure::Slow(x)) => delay = Some(x),
+        Some(MockFailure::MalformedJson) | None => (),
+    }
+
+    let count_param = |name: &str| {
+        return request
+            .query
+            .get(name)
+            .map_or(0, |x| x.split(',').filter(|x| !x.is_empty()).count()) as i32;
+    };
+    let fixture = match (sport_key, endpoint) {
+        (None, _) => serde_json::to_string(&state.fixtures.sports)
+            .ok()
+            .map(|x| (x, 0)),
+        (Some(x), "odds") => state.fixtures.odds.get(x).map(|odds| {
+            (
+                filter_odds(odds, &request.query),
+                count_param("markets") * count_param("regions"),
+            )
+        }),
+        // the odds fixture, as if it were the last snapshot at or before the
+        // requested date
+        (Some(x), "historical") => state.fixtures.odds.get(x).map(|odds| {
+            let interval = TimeDelta::minutes(HISTORICAL_INTERVAL_MINUTES);
+            let timestamp = request
+                .query
+                .get("date")
+                .and_then(|x| x.parse::<DateTime<Utc>>().ok())
+                .and_then(|x| x.duration_trunc(interval).ok());
+            let historical = serde_json::json!({
+                "timestamp": timestamp,
+                "previous_timestamp": timestamp.map(|x| x - interval),
+                "next_timestamp": timestamp.map(|x| x + interval),
+                "data": serde_json::from_str::<Value>(&filter_odds(odds, &request.query))
+                    .unwrap_or_default(),
+            });
+            (
+                historical.to_string(),
+                10 * count_param("markets") * count_param("regions"),
+            )
+        }),
+        (Some(x), "events") => state.fixtures.events_for(x).map(|events| (events, 0)),
+        (Some(x), "scores") => {
+            let cost = match request.query.contains_key("daysFrom") {
+                true => 2,
+                false => 1,
+            };
+            state
+                .fixtures
+                .scores
+                .get(x)
+                .map(|scores| (scores.clone(), cost))
+        }
+        _ => None,
+    };
+
+    let (mut body, cost) = match fixture {
+        Some(x) => x,
+        None => return (not_found, None),
+    };
+    if (state.fixtures.failure_for(sport_key) == Some(MockFailure::MalformedJson)) {
+        body.truncate(body.len() / 2);
+    }
+
+    let response = MockResponse {
+        status: 200,
+        body,
+        cost: Some(cost),
+    };
+    return (response, delay);
+}
@@ -1,95 +1,103 @@
-use std::fs;
+use std::{fs, future::Future, sync::OnceLock};
 
-use reqwest::{blocking::Response, Error};
-use util::{get_key_usage_from_headers, ApiKeyUsage};
-
-use crate::local_env::MY_ENV;
+use chrono::{DateTime, Utc};
+use client::{HistoricalOdds, OddsApiClient, SportOdds};
+use error::OddsApiError;
+use util::ApiKeyUsage;
 
 use super::logic::bookmaker::Region;
 use super::logic::market::MarketType;
-use super::logic::{event::Event, sport::Sport};
+use super::logic::{event::Event, score::EventScore, sport::Sport};
+
+pub const ODDS_HOST_BASE: &str = "https://api.the-odds-api.com/v4";
+/// Saved odds responses, named `{sport_key}_odds.json`
+pub const EXAMPLE_RESPONSES_DIR: &str = "./example_data/oddsapi";
+
+pub mod client;
+pub mod error;
+#[cfg(test)]
+pub mod mock_server;
+pub mod quota;
+pub mod transport;
+pub mod util;
 
-const ODDS_HOST_BASE: &str = "https://api.the-odds-api.com/v4";
-const API_KEY: &str = MY_ENV.odds_api_key;
+#[cfg(test)]
+mod api_requests_test;
 
-pub mod util;
+// The functions below are blocking wrappers around `OddsApiClient`, for
+// callers that aren't running inside an async runtime. They share one
+// runtime so the client's connection pool stays usable between calls.
+
+static BLOCKING_RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+fn block_on<F: Future>(future: F) -> Result<F::Output, OddsApiError> {
+    let runtime = match BLOCKING_RUNTIME.get() {
+        Some(x) => x,
+        None => {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()?;
+            BLOCKING_RUNTIME.get_or_init(|| runtime)
+        }
+    };
+    return Ok(runtime.block_on(future));
+}
 
-// todo: these should return the actual type
 pub fn get_odds_for_sport(
+    client: &OddsApiClient,
     sport: &str,
     markets: &Vec<MarketType>,
     regions: &Vec<Region>,
-) -> reqwest::Result<Vec<Event>> {
-    let odds_endpoint = format!("/sports/{sport}/odds/");
-    let full_url = ODDS_HOST_BASE.to_owned() + &odds_endpoint;
-
-    let params = [
-        ("apiKey", API_KEY),
-        (
-            "regions",
-            &regions
-                .iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>()
-                .join(","),
-        ),
-        (
-            "markets",
-            &markets
-                .iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>()
-                .join(","),
-        ),
-    ];
-
-    let url = reqwest::Url::parse_with_params(&full_url, &params).unwrap();
-    let res = match reqwest::blocking::get(url) {
-        Ok(x) => x,
-        Err(e) => return Err(e),
-    };
-
-    let events = res.json::<Vec<Event>>().unwrap_or(Vec::new());
-
-    return Ok(events);
+) -> Result<Vec<Event>, OddsApiError> {
+    return block_on(client.get_odds_for_sport(sport, markets, regions))?;
 }
 
-pub fn get_example_odds_file(filepath: &str) -> Vec<Event> {
-    let file_str = fs::read_to_string(filepath).expect("Unable to read file");
-    return serde_json::from_str::<Vec<Event>>(&file_str).expect("JSON was not well-formatted");
+pub fn get_historical_odds(
+    client: &OddsApiClient,
+    sport: &str,
+    markets: &Vec<MarketType>,
+    regions: &Vec<Region>,
+    date: DateTime<Utc>,
+) -> Result<HistoricalOdds, OddsApiError> {
+    return block_on(client.get_historical_odds(sport, markets, regions, date))?;
 }
 
-pub fn get_key_usage() -> Option<ApiKeyUsage> {
-    let response = match get_sports_raw() {
-        Ok(x) => x,
-        Err(_) => return None,
-    };
-
-    let headers = response.headers();
-    return get_key_usage_from_headers(&headers);
+pub fn get_odds_for_sports(
+    client: &OddsApiClient,
+    sports: &[String],
+    markets: &Vec<MarketType>,
+    regions: &Vec<Region>,
+    max_concurrent: usize,
+) -> Result<Vec<SportOdds>, OddsApiError> {
+    return block_on(client.get_odds_for_sports(sports, markets, regions, max_concurrent));
 }
 
-pub fn get_sports_raw() -> Result<Response, Error> {
-    let sports_endpoint = "/sports/";
-    let full_url = ODDS_HOST_BASE.to_owned() + sports_endpoint;
-    let params = [("apiKey", API_KEY)];
+pub fn get_example_odds_file(filepath: &str) -> Result<Vec<Event>, OddsApiError> {
+    let file_str = fs::read_to_string(filepath)?;
+    return Ok(serde_json::from_str::<Vec<Event>>(&file_str)?);
+}
 
-    let url = reqwest::Url::parse_with_params(&full_url, &params).unwrap();
-    return reqwest::blocking::get(url);
+/// Saved odds for `sport_key` from `EXAMPLE_RESPONSES_DIR`
+pub fn get_example_odds_for_sport(sport_key: &str) -> Result<Vec<Event>, OddsApiError> {
+    return get_example_odds_file(&format!("{EXAMPLE_RESPONSES_DIR}/{sport_key}_odds.json"));
 }
 
-pub fn get_sports() -> Result<Vec<Sport>, Error> {
-    let sports_endpoint = "/sports/";
-    let full_url = ODDS_HOST_BASE.to_owned() + sports_endpoint;
-    let params = [("apiKey", API_KEY)];
+pub fn get_events(client: &OddsApiClient, sport: &str) -> Result<Vec<Event>, OddsApiError> {
+    return block_on(client.get_events(sport))?;
+}
 
-    let url = reqwest::Url::parse_with_params(&full_url, &params).unwrap();
-    let res = match reqwest::blocking::get(url) {
-        Ok(x) => x,
-        Err(e) => return Err(e),
-    };
+pub fn get_scores(
+    client: &OddsApiClient,
+    sport: &str,
+    days_from: Option<u32>,
+) -> Result<Vec<EventScore>, OddsApiError> {
+    return block_on(client.get_scores(sport, days_from))?;
+}
 
-    let body = res.json::<Vec<Sport>>().unwrap_or(Vec::new());
+pub fn get_key_usage(client: &OddsApiClient) -> Result<ApiKeyUsage, OddsApiError> {
+    return block_on(client.get_key_usage())?;
+}
 
-    return Ok(body);
+pub fn get_sports(client: &OddsApiClient) -> Result<Vec<Sport>, OddsApiError> {
+    return block_on(client.get_sports())?;
 }
@@ -0,0 +1,353 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+
+use super::error::OddsApiError;
+use super::util::ApiKeyUsage;
+use crate::odds_interface::logic::bookmaker::Region;
+use crate::odds_interface::logic::market::MarketType;
+use crate::storage::SnapshotStore;
+
+/// What to do with a request that would go over budget
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPolicy {
+    /// Fail the request with `OddsApiError::BudgetExceeded`
+    #[default]
+    Refuse,
+    /// Drop regions, then markets, until the request fits
+    Downscale,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct QuotaBudget {
+    /// Most requests to spend per UTC day
+    pub daily: Option<u32>,
+    /// Most requests to spend per month, as counted by `x-requests-used`
+    pub monthly: Option<u32>,
+    pub on_exceed: BudgetPolicy,
+    /// Day of the month the key's quota resets on, if not the 1st. Later
+    /// days than a month has mean its last day.
+    pub reset_day: Option<u32>,
+}
+
+/// Markets and regions to actually request once the budget is applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchPlan {
+    pub markets: Vec<MarketType>,
+    pub regions: Vec<Region>,
+    pub cost: u32,
+    pub downscaled: bool,
+}
+
+#[derive(Default)]
+struct QuotaState {
+    latest_usage: Option<ApiKeyUsage>,
+    /// Estimated cost of the planned requests that haven't been charged yet
+    reserved: u32,
+    /// When each request we've made was charged and what it cost, unless
+    /// it was saved to `store`
+    spend_log: Vec<(DateTime<Utc>, u32)>,
+    /// Shares the spend with every run using the same database
+    store: Option<SnapshotStore>,
+}
+
+impl fmt::Debug for QuotaState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("QuotaState")
+            .field("latest_usage", &self.latest_usage)
+            .field("reserved", &self.reserved)
+            .field("spend_log", &self.spend_log)
+            .field("store", &self.store.is_some())
+            .finish();
+    }
+}
+
+impl QuotaState {
+    /// Requests spent since `since`, from the responses we've seen
+    fn spent_since(&self, since: DateTime<Utc>) -> u32 {
+        let logged: u32 = self
+            .spend_log
+            .iter()
+            .filter(|(at, _)| *at >= since)
+            .map(|(_, cost)| cost)
+            .sum();
+        let stored = match self.store.as_ref().map(|x| x.spent_since(since)) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                eprintln!("Failed to read the quota spend: {e}");
+                0
+            }
+            None => 0,
+        };
+        return logged + stored;
+    }
+
+    fn add_spend(&mut self, at: DateTime<Utc>, cost: u32) {
+        if let Some(store) = self.store.as_mut() {
+            match store.save_spend(at, cost) {
+                Ok(()) => return,
+                Err(e) => eprintln!("Failed to save the quota spend: {e}"),
+            }
+        }
+        self.spend_log.push((at, cost));
+    }
+}
+
+/// Tracks quota usage across every request made with a client. Cloning
+/// shares the underlying state.
+#[derive(Debug, Clone, Default)]
+pub struct QuotaTracker {
+    budget: QuotaBudget,
+    state: Arc<Mutex<QuotaState>>,
+}
+
+/// Historical odds cost ten times as much as current odds
+pub const HISTORICAL_COST_MULTIPLIER: u32 = 10;
+
+/// The events endpoint is free
+pub const EVENTS_COST: u32 = 0;
+
+/// The scores endpoint costs one request, or two with `daysFrom`
+pub fn scores_cost(days_from: Option<u32>) -> u32 {
+    return match days_from {
+        Some(_) => 2,
+        None => 1,
+    };
+}
+
+/// The odds endpoint costs one request per market per region
+pub fn estimate_cost(markets: &[MarketType], regions: &[Region]) -> u32 {
+    return (markets.len() * regions.len()) as u32;
+}
+
+fn start_of_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    return Utc
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0)
+        .unwrap();
+}
+
+/// When a quota resetting on `reset_day` resets in the month of `month`
+fn reset_in_month(month: NaiveDate, reset_day: u32) -> DateTime<Utc> {
+    let first = month.with_day(1).unwrap();
+    let last_day = (first + Months::new(1) - Duration::days(1)).day();
+    let day = first.with_day(reset_day.clamp(1, last_day)).unwrap();
+    return Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap());
+}
+
+impl QuotaTracker {
+    pub fn new(budget: QuotaBudget) -> Self {
+        return QuotaTracker {
+            budget,
+            state: Arc::new(Mutex::new(QuotaState::default())),
+        };
+    }
+
+    /// Keeps the spend in `store`, so the daily budget holds across runs
+    pub fn with_store(self, store: SnapshotStore) -> Self {
+        self.state.lock().unwrap().store = Some(store);
+        return self;
+    }
+
+    /// When the key's quota last reset, at or before `now`
+    fn cycle_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let reset_day = self.budget.reset_day.unwrap_or(1);
+        let this_month = reset_in_month(now.date_naive(), reset_day);
+        if (this_month <= now) {
+            return this_month;
+        }
+        return reset_in_month(now.date_naive() - Months::new(1), reset_day);
+    }
+
+    /// When the key's quota next resets after `now`
+    fn next_reset(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let reset_day = self.budget.reset_day.unwrap_or(1);
+        return reset_in_month(
+            self.cycle_start(now).date_naive() + Months::new(1),
+            reset_day,
+        );
+    }
+
+    /// Updates usage from the quota headers of a response to a request
+    /// that was planned to cost `reserved`
+    pub fn record(&self, usage: ApiKeyUsage, reserved: u32, at: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        state.reserved = state.reserved.saturating_sub(reserved);
+        let cost = match usage.requests_last {
+            Some(x) => x.max(0) as u32,
+            None => reserved,
+        };
+        if (cost > 0) {
+            state.add_spend(at, cost);
+        }
+        state.latest_usage = Some(usage);
+    }
+
+    /// Gives back the reservation of a planned request that never got a
+    /// response with quota headers
+    pub fn release(&self, reserved: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.reserved = state.reserved.saturating_sub(reserved);
+    }
+
+    pub fn latest_usage(&self) -> Option<ApiKeyUsage> {
+        return self.state.lock().unwrap().latest_usage.clone();
+    }
+
+    /// Requests left under each budget, and the key's quota, with when
+    /// they reset
+    fn limits(&self, state: &QuotaState, now: DateTime<Utc>) -> Vec<(u32, DateTime<Utc>)> {
+        let mut limits: Vec<(u32, DateTime<Utc>)> = Vec::new();
+
+        if let Some(daily) = self.budget.daily {
+            let left = daily.saturating_sub(state.spent_since(start_of_day(now)));
+            limits.push((left, start_of_day(now) + Duration::days(1)));
+        }
+
+        if let Some(monthly) = self.budget.monthly {
+            let used_this_cycle = match &state.latest_usage {
+                Some(usage) => usage.requests_used.max(0) as u32,
+                None => state.spent_since(self.cycle_start(now)),
+            };
+            limits.push((
+                monthly.saturating_sub(used_this_cycle),
+                self.next_reset(now),
+            ));
+        }
+
+        if let Some(usage) = &state.latest_usage {
+            limits.push((usage.requests_remaining.max(0) as u32, self.next_reset(now)));
+        }
+
+        return limits
+            .into_iter()
+            .map(|(left, resets_at)| (left.saturating_sub(state.reserved), resets_at))
+            .collect();
+    }
+
+    /// How many more requests the budget and the key's quota allow right
+    /// now, less those reserved by requests in flight
+    pub fn available(&self, now: DateTime<Utc>) -> Option<u32> {
+        let state = self.state.lock().unwrap();
+        return self.limits(&state, now).into_iter().map(|x| x.0).min();
+    }
+
+    /// Most requests per minute that can be spent from `now` on without
+    /// running out of any budget, or the key's quota, before it resets
+    pub fn sustainable_rate(&self, now: DateTime<Utc>) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        return self
+            .limits(&state, now)
+            .into_iter()
+            .map(|(left, resets_at)| {
+                let minutes = (resets_at - now).num_seconds().max(60) as f64 / 60.0;
+                return left as f64 / minutes;
+            })
+            .reduce(f64::min);
+    }
+
+    /// Works out what to request for `markets` in `regions` without going
+    /// over budget, downscaling or refusing according to the budget policy
+    pub fn plan(
+        &self,
+        markets: &[MarketType],
+        regions: &[Region],
+        now: DateTime<Utc>,
+    ) -> Result<FetchPlan, OddsApiError> {
+        return self.plan_with_multiplier(markets, regions, 1, now);
+    }
+
+    /// Same as `plan`, for endpoints charging `multiplier` times the odds
+    /// endpoint, such as historical odds
+    pub fn plan_with_multiplier(
+        &self,
+        markets: &[MarketType],
+        regions: &[Region],
+        multiplier: u32,
+        now: DateTime<Utc>,
+    ) -> Result<FetchPlan, OddsApiError> {
+        let cost = multiplier * estimate_cost(markets, regions);
+        // the reservation is made under the same lock as the check, so
+        // concurrent requests can't all spend the same requests
+        let mut state = self.state.lock().unwrap();
+        let available = self
+            .limits(&state, now)
+            .into_iter()
+            .map(|x| x.0)
+            .min()
+            .unwrap_or(u32::MAX);
+
+        let mut plan = FetchPlan {
+            markets: markets.to_vec(),
+            regions: regions.to_vec(),
+            cost,
+            downscaled: false,
+        };
+
+        if (cost <= available) {
+            state.reserved += plan.cost;
+            return Ok(plan);
+        }
+
+        let exceeded = OddsApiError::BudgetExceeded { cost, available };
+        if (self.budget.on_exceed == BudgetPolicy::Refuse) {
+            return Err(exceeded);
+        }
+
+        // regions are usually cheaper to lose than markets, as several
+        // regions tend to quote the same books
+        while (plan.cost > available && plan.regions.len() > 1) {
+            plan.regions.pop();
+            plan.cost = multiplier * estimate_cost(&plan.markets, &plan.regions);
+        }
+        while (plan.cost > available && plan.markets.len() > 1) {
+            plan.markets.pop();
+            plan.cost = multiplier * estimate_cost(&plan.markets, &plan.regions);
+        }
+
+        if (plan.cost > available) {
+            return Err(exceeded);
+        }
+
+        plan.downscaled = true;
+        state.reserved += plan.cost;
+        return Ok(plan);
+    }
+
+    /// Reserves `cost` requests for an endpoint with a fixed price, such as
+    /// scores. There's nothing to downscale, so it's refused if it would go
+    /// over budget whatever the policy.
+    pub fn reserve(&self, cost: u32, now: DateTime<Utc>) -> Result<u32, OddsApiError> {
+        let mut state = self.state.lock().unwrap();
+        let available = self
+            .limits(&state, now)
+            .into_iter()
+            .map(|x| x.0)
+            .min()
+            .unwrap_or(u32::MAX);
+        if (cost > available) {
+            return Err(OddsApiError::BudgetExceeded { cost, available });
+        }
+        state.reserved += cost;
+        return Ok(cost);
+    }
+
+    /// When the key's remaining requests run out at this cycle's spend rate
+    pub fn projected_exhaustion(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let usage = self.latest_usage()?;
+        let elapsed = now - self.cycle_start(now);
+        if (usage.requests_used <= 0 || elapsed <= Duration::zero()) {
+            return None;
+        }
+
+        let seconds_per_request = elapsed.num_seconds() as f64 / usage.requests_used as f64;
+        let seconds_left = seconds_per_request * usage.requests_remaining.max(0) as f64;
+        return Some(now + Duration::seconds(seconds_left as i64));
+    }
+}
@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumString;
+
+use super::error::OddsApiError;
+
+pub const DEFAULT_CASSETTE_DIR: &str = "./cassettes";
+pub const DEFAULT_TIMEOUT_SECS: f64 = 30.0;
+
+/// Query parameters left out of cassettes, so recordings don't leak the key
+/// and replay works with any key
+const UNRECORDED_PARAMS: [&str; 1] = ["apiKey"];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TransportMode {
+    /// Call the API
+    #[default]
+    Live,
+    /// Call the API and save every response to the cassette directory
+    Record,
+    /// Serve responses from the cassette directory without calling the API
+    Replay,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TransportSettings {
+    pub mode: TransportMode,
+    pub cassette_dir: PathBuf,
+    /// Live requests taking longer than this fail with a transport error
+    pub timeout_secs: f64,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        return TransportSettings {
+            mode: TransportMode::default(),
+            cassette_dir: PathBuf::from(DEFAULT_CASSETTE_DIR),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+        };
+    }
+}
+
+/// Everything we keep of a response, whether it came off the wire or out of
+/// a cassette
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        return StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    /// When the server sent the response, from its `date` header
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        let raw = self
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("date"))?
+            .1;
+        return DateTime::parse_from_rfc2822(raw)
+            .ok()
+            .map(|x| x.with_timezone(&Utc));
+    }
+
+    pub fn header_map(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        return headers;
+    }
+}
+
+/// A recorded request and the response it got
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cassette {
+    pub path: String,
+    pub query: BTreeMap<String, String>,
+    pub response: HttpResponse,
+}
+
+/// Sends requests for `OddsApiClient`, live or from cassettes
+#[derive(Debug, Clone)]
+pub struct Transport {
+    settings: TransportSettings,
+    http: reqwest::Client,
+}
+
+fn recorded_query(url: &Url) -> BTreeMap<String, String> {
+    return url
+        .query_pairs()
+        .filter(|(key, _)| !UNRECORDED_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+}
+
+fn sanitise(raw: &str) -> String {
+    return raw
+        .chars()
+        .map(
+            |x| match x.is_ascii_alphanumeric() || x == '-' || x == '=' {
+                true => x,
+                false => '_',
+            },
+        )
+        .collect();
+}
+
+/// File name of the cassette for `url`: its path and sorted query, without
+/// the host or api key, so recordings replay against any base url
+pub fn cassette_name(url: &Url) -> String {
+    let mut name = sanitise(url.path().trim_matches('/'));
+    for (key, value) in recorded_query(url) {
+        name += &format!("__{0}", sanitise(&format!("{key}={value}")));
+    }
+    return name + ".json";
+}
+
+impl Transport {
+    pub fn new(settings: TransportSettings) -> Self {
+        let mut builder = reqwest::Client::builder();
+        // `Config::validate` rejects timeouts that aren't a duration
+        if let Ok(timeout) = Duration::try_from_secs_f64(settings.timeout_secs) {
+            builder = builder.timeout(timeout);
+        }
+        let http = builder.build().unwrap_or_default();
+        return Transport { settings, http };
+    }
+
+    pub fn cassette_path(&self, url: &Url) -> PathBuf {
+        return self.settings.cassette_dir.join(cassette_name(url));
+    }
+
+    pub async fn get(&self, url: Url) -> Result<HttpResponse, OddsApiError> {
+        match self.settings.mode {
+            TransportMode::Live => return self.get_live(url).await,
+            TransportMode::Record => {
+                let path = self.cassette_path(&url);
+                let cassette = Cassette {
+                    path: url.path().to_string(),
+                    query: recorded_query(&url),
+                    response: self.get_live(url).await?,
+                };
+                fs::create_dir_all(&self.settings.cassette_dir)?;
+                fs::write(path, serde_json::to_string_pretty(&cassette)?)?;
+                return Ok(cassette.response);
+            }
+            TransportMode::Replay => {
+                let path = self.cassette_path(&url);
+                let raw = match fs::read_to_string(&path) {
+                    Ok(x) => x,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return Err(OddsApiError::MissingCassette { path })
+                    }
+                    Err(e) => return Err(OddsApiError::Io(e)),
+                };
+                return Ok(serde_json::from_str::<Cassette>(&raw)?.response);
+            }
+        }
+    }
+
+    async fn get_live(&self, url: Url) -> Result<HttpResponse, OddsApiError> {
+        let res = self.http.get(url).send().await?;
+
+        let status = res.status().as_u16();
+        let headers = res
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                return Some((name.to_string(), value.to_string()));
+            })
+            .collect();
+        let body = res.text().await?;
+
+        return Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        });
+    }
+}
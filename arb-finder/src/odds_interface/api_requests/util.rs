@@ -1,40 +1,55 @@
-use serde::Deserialize;
 use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+use super::error::OddsApiError;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct ApiKeyUsage {
     pub requests_used: i32,
-    pub requests_remaining: i32
+    pub requests_remaining: i32,
+    /// Cost of the request these headers came back with
+    pub requests_last: Option<i32>,
 }
 
-fn get_typed_header(headers : &HeaderMap, header_name: &str) -> Option<i32> {
-
+fn get_typed_header(headers: &HeaderMap, header_name: &str) -> Result<i32, OddsApiError> {
     let raw_val = match headers.get(header_name) {
         Some(x) => x,
-        None => return None
+        None => {
+            return Err(OddsApiError::Header {
+                name: header_name.to_string(),
+                value: None,
+            })
+        }
     };
 
-    let string_val = raw_val.to_str().unwrap();
-    return Some(string_val.parse::<i32>().unwrap());
-}
+    let invalid_header = || OddsApiError::Header {
+        name: header_name.to_string(),
+        value: Some(String::from_utf8_lossy(raw_val.as_bytes()).to_string()),
+    };
 
-pub fn get_key_usage_from_headers(headers: &HeaderMap) -> Option<ApiKeyUsage> {
+    let string_val = raw_val.to_str().map_err(|_| invalid_header())?;
+    // the api sometimes sends usage as a float, e.g. "12.0"
+    return match string_val.trim().parse::<f64>() {
+        Ok(x) if (x.is_finite()) => Ok(x as i32),
+        _ => Err(invalid_header()),
+    };
+}
 
+pub fn get_key_usage_from_headers(headers: &HeaderMap) -> Result<ApiKeyUsage, OddsApiError> {
     let requests_used_header_name = "x-requests-used";
     let requests_remaining_header_name = "x-requests-remaining";
+    let requests_last_header_name = "x-requests-last";
 
-    let reqs_used_val =  match get_typed_header(headers, &requests_used_header_name) {
-        Some(val) => val,
-        None => return None
+    let reqs_used_val = get_typed_header(headers, requests_used_header_name)?;
+    let reqs_remaining_val = get_typed_header(headers, requests_remaining_header_name)?;
+    let reqs_last_val = match headers.contains_key(requests_last_header_name) {
+        true => Some(get_typed_header(headers, requests_last_header_name)?),
+        false => None,
     };
 
-    let reqs_remaining_val =  match get_typed_header(headers, &requests_remaining_header_name) {
-        Some(val) => val,
-        None => return None
-    };
-    
-    return Some(ApiKeyUsage {
+    return Ok(ApiKeyUsage {
         requests_used: reqs_used_val,
-        requests_remaining: reqs_remaining_val
+        requests_remaining: reqs_remaining_val,
+        requests_last: reqs_last_val,
     });
 }
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crate::odds_interface::logic::{
+    alerts::{
+        format_gone_message, format_realert_note, gone_alerts, AlertReason, AlertRecord,
+        AlertSettings, OpportunityKey, ScanCoverage,
+    },
+    bookmaker::Region,
+    market::MarketType,
+    odds::{Odds, OddsFormat},
+};
+
+fn get_alerted_at() -> DateTime<Utc> {
+    return Utc.with_ymd_and_hms(2024, 8, 4, 5, 0, 0).unwrap();
+}
+
+fn get_alert(outcome_key: &str, point: Option<f64>) -> AlertRecord {
+    return AlertRecord {
+        key: OpportunityKey {
+            event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+            market_key: MarketType::Totals,
+            outcome_key: outcome_key.to_string(),
+            point,
+            bookmaker_key: String::from("tab"),
+            direction: None,
+        },
+        sport_key: String::from("aussierules_afl"),
+        commence_time: Utc.with_ymd_and_hms(2024, 8, 4, 6, 40, 0).unwrap(),
+        matchup: String::from("St Kilda Saints vs Brisbane Lions"),
+        offered_odds: Odds::Decimal(2.05),
+        percent_ev: 5.0,
+        alerted_at: get_alerted_at(),
+        gone_at: None,
+    };
+}
+
+#[test]
+fn test_alert_reason() {
+    let settings = AlertSettings {
+        ev_delta: 1.0,
+        cooldown_minutes: 60,
+        notify_gone: true,
+    };
+    let previous = get_alert("Over", Some(166.5));
+    let soon = get_alerted_at() + Duration::minutes(10);
+
+    assert_eq!(
+        settings.alert_reason(None, 5.0, soon),
+        Some(AlertReason::New)
+    );
+    // the same price again, or a little better, isn't worth another alert
+    assert_eq!(settings.alert_reason(Some(&previous), 5.0, soon), None);
+    assert_eq!(settings.alert_reason(Some(&previous), 5.9, soon), None);
+    assert_eq!(settings.alert_reason(Some(&previous), 3.0, soon), None);
+    assert_eq!(
+        settings.alert_reason(Some(&previous), 6.0, soon),
+        Some(AlertReason::Improved)
+    );
+    assert_eq!(
+        settings.alert_reason(Some(&previous), 5.0, get_alerted_at() + Duration::hours(1)),
+        Some(AlertReason::Reminder)
+    );
+
+    let gone = AlertRecord {
+        gone_at: Some(soon),
+        ..previous.clone()
+    };
+    assert_eq!(
+        settings.alert_reason(Some(&gone), 4.0, soon),
+        Some(AlertReason::Returned)
+    );
+}
+
+#[test]
+fn test_alert_messages() {
+    let previous = get_alert("Over", Some(166.5));
+    let format = OddsFormat::Decimal;
+
+    assert_eq!(
+        format_realert_note(AlertReason::New, &previous, &format),
+        None
+    );
+    let note = format_realert_note(AlertReason::Improved, &previous, &format).unwrap();
+    assert!(note.contains("Improved from: 5.00% at 2.05, 2024-08-04 05:00 UTC"));
+
+    let gone = format_gone_message(&previous, &format);
+    assert!(gone.contains("Opportunity gone on aussierules_afl"));
+    assert!(gone.contains("St Kilda Saints vs Brisbane Lions at tab"));
+    assert!(gone.contains("Outcome: Over 166.5"));
+
+    assert_eq!(
+        previous.key.to_string(),
+        "132905fc8e35fe3551638c40c80fe9e6/totals/Over@166.5/tab"
+    );
+}
+
+#[test]
+fn test_gone_alerts() {
+    let over = get_alert("Over", Some(166.5));
+    let under = get_alert("Under", Some(166.5));
+    let moved = get_alert("Over", Some(170.5));
+    let active = [over.clone(), under.clone(), moved.clone()];
+    let found = [over.key, under.key.clone()];
+
+    let mut coverage = ScanCoverage {
+        markets: vec![MarketType::H2h, MarketType::Totals],
+        regions: vec![Region::Au],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        gone_alerts(&active, &found[..1], &coverage),
+        [&under, &moved]
+    );
+    assert_eq!(gone_alerts(&active, &found, &coverage), [&moved]);
+
+    // a scan without totals, or without the book's region, can't tell
+    coverage.markets = vec![MarketType::H2h];
+    assert!(gone_alerts(&active, &found, &coverage).is_empty());
+    coverage.markets.push(MarketType::Totals);
+    coverage.regions = vec![Region::Uk, Region::Us];
+    assert!(gone_alerts(&active, &found, &coverage).is_empty());
+
+    // a book of no known region has to have quoted something
+    let mut unknown = moved.clone();
+    unknown.key.bookmaker_key = String::from("newbook");
+    let active = [unknown.clone()];
+    assert!(gone_alerts(&active, &found, &coverage).is_empty());
+    coverage.bookmakers.insert(String::from("newbook"));
+    assert_eq!(gone_alerts(&active, &found, &coverage), [&unknown]);
+}
@@ -0,0 +1,210 @@
+use std::{collections::BTreeSet, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use super::bookmaker::Region;
+use super::market::MarketType;
+use super::movement::MoveDirection;
+use super::odds::{Odds, OddsFormat};
+
+#[cfg(test)]
+mod alerts_test;
+
+const DEFAULT_EV_DELTA: f64 = 1.0;
+const DEFAULT_COOLDOWN_MINUTES: i64 = 360;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// Percentage points the EV has to rise by since the last alert to
+    /// alert again
+    pub ev_delta: f64,
+    /// An opportunity that is still there is alerted again after this long
+    pub cooldown_minutes: i64,
+    /// Whether to send a message when an alerted price is no longer an
+    /// opportunity
+    pub notify_gone: bool,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        return AlertSettings {
+            ev_delta: DEFAULT_EV_DELTA,
+            cooldown_minutes: DEFAULT_COOLDOWN_MINUTES,
+            notify_gone: true,
+        };
+    }
+}
+
+/// What makes two opportunities the same one: a bookmaker's price on an
+/// outcome of an event's market. A steam move on the outcome is keyed by
+/// its direction instead of a bookmaker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpportunityKey {
+    pub event_id: String,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    /// Line for totals and spreads
+    pub point: Option<f64>,
+    /// Empty for steam moves
+    pub bookmaker_key: String,
+    pub direction: Option<MoveDirection>,
+}
+
+impl fmt::Display for OpportunityKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{0}/{1}/{2}",
+            self.event_id, self.market_key, self.outcome_key
+        )?;
+        if let Some(point) = self.point {
+            write!(f, "@{point}")?;
+        }
+        return match self.direction {
+            Some(x) => write!(f, "/steam_{x}"),
+            None => write!(f, "/{0}", self.bookmaker_key),
+        };
+    }
+}
+
+/// The last alert sent for an opportunity or steam move
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRecord {
+    pub key: OpportunityKey,
+    pub sport_key: String,
+    pub commence_time: DateTime<Utc>,
+    /// "home vs away", for the message when it's gone
+    pub matchup: String,
+    /// The fair odds moved to, for a steam move
+    pub offered_odds: Odds,
+    /// How far the fair probability moved, for a steam move
+    pub percent_ev: f64,
+    pub alerted_at: DateTime<Utc>,
+    /// When a scan of its sport last found it missing
+    pub gone_at: Option<DateTime<Utc>>,
+}
+
+/// Why an opportunity is being alerted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertReason {
+    New,
+    /// It had gone, and now it's back
+    Returned,
+    /// The EV rose by at least `ev_delta` since the last alert
+    Improved,
+    /// Still there after the cooldown
+    Reminder,
+}
+
+impl AlertSettings {
+    /// Whether an opportunity at `percent_ev` should be alerted at `now`,
+    /// given its last alert
+    pub fn alert_reason(
+        &self,
+        previous: Option<&AlertRecord>,
+        percent_ev: f64,
+        now: DateTime<Utc>,
+    ) -> Option<AlertReason> {
+        let previous = match previous {
+            Some(x) => x,
+            None => return Some(AlertReason::New),
+        };
+
+        if (previous.gone_at.is_some()) {
+            return Some(AlertReason::Returned);
+        }
+        if (percent_ev - previous.percent_ev >= self.ev_delta) {
+            return Some(AlertReason::Improved);
+        }
+        if (now - previous.alerted_at >= Duration::minutes(self.cooldown_minutes)) {
+            return Some(AlertReason::Reminder);
+        }
+        return None;
+    }
+}
+
+/// A line added to the message of an opportunity alerted before
+pub fn format_realert_note(
+    reason: AlertReason,
+    previous: &AlertRecord,
+    odds_format: &OddsFormat,
+) -> Option<String> {
+    let last_alert = format!(
+        "{0:.2}% at {1}, {2}",
+        previous.percent_ev,
+        previous.offered_odds.display_as(odds_format),
+        previous.alerted_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    match reason {
+        AlertReason::New => return None,
+        AlertReason::Returned => return Some(format!("    - Back since: {last_alert}\n")),
+        AlertReason::Improved => return Some(format!("    - Improved from: {last_alert}\n")),
+        AlertReason::Reminder => {
+            return Some(format!("    - Still available since: {last_alert}\n"))
+        }
+    }
+}
+
+/// Markdown for discord when an alerted price is no longer an opportunity
+pub fn format_gone_message(record: &AlertRecord, odds_format: &OddsFormat) -> String {
+    let mut outcome = record.key.outcome_key.clone();
+    if let Some(point) = record.key.point {
+        outcome += &format!(" {point}");
+    }
+    return format!(
+        " ### Opportunity gone on {0}
+    - {1} at {2}
+    - Outcome: {3}
+    - Market: {4}
+    - Last alerted: {5:.2}% at {6}, {7}
+",
+        record.sport_key,
+        record.matchup,
+        record.key.bookmaker_key,
+        outcome,
+        record.key.market_key,
+        record.percent_ev,
+        record.offered_odds.display_as(odds_format),
+        record.alerted_at.format("%Y-%m-%d %H:%M UTC")
+    );
+}
+
+/// What a scan of one sport fetched, after the budget had its say
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanCoverage {
+    pub markets: Vec<MarketType>,
+    pub regions: Vec<Region>,
+    /// Bookmakers that quoted any of the events
+    pub bookmakers: BTreeSet<String>,
+}
+
+impl ScanCoverage {
+    /// Whether the scan could have found `key`: its market was fetched, and
+    /// so was a region of its bookmaker. A bookmaker of no known region
+    /// only counts if it quoted something.
+    pub fn covers(&self, key: &OpportunityKey) -> bool {
+        if (!self.markets.contains(&key.market_key)) {
+            return false;
+        }
+        let regions = Region::of_bookmaker(&key.bookmaker_key);
+        if (regions.is_empty()) {
+            return self.bookmakers.contains(&key.bookmaker_key);
+        }
+        return regions.iter().any(|x| self.regions.contains(x));
+    }
+}
+
+/// Active alerts of a scanned sport that weren't found again, out of those
+/// the scan covered
+pub fn gone_alerts<'a>(
+    active: &'a [AlertRecord],
+    found: &[OpportunityKey],
+    coverage: &ScanCoverage,
+) -> Vec<&'a AlertRecord> {
+    return active
+        .iter()
+        .filter(|x| x.gone_at.is_none() && !found.contains(&x.key) && coverage.covers(&x.key))
+        .collect();
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use super::event::matchup;
+use super::market::MarketType;
+use super::odds::Odds;
+
+/// Best price available for one outcome of a market, across every bookie
+#[derive(Debug, Clone)]
+pub struct BestPrice {
+    pub outcome_key: String,
+    pub point: Option<f64>,
+    pub bookie_name: String,
+    pub odds: Odds,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArbitrageLeg {
+    pub outcome_key: String,
+    pub point: Option<f64>,
+    pub bookie_name: String,
+    pub odds: Odds,
+    pub stake: f64,
+}
+
+/// A set of bets covering every outcome of a market which returns the same
+/// amount whichever outcome wins
+#[derive(Debug, Clone)]
+pub struct Arbitrage {
+    pub sport_title: String,
+    pub home_team: String,
+    pub away_team: String,
+    pub market_key: MarketType,
+    pub legs: Vec<ArbitrageLeg>,
+    pub bankroll: f64,
+    /// Guaranteed return on the bankroll, as a percentage
+    pub profit_margin: f64,
+    pub guaranteed_profit: f64,
+}
+
+pub fn total_implied_probability(all_odds: &[Odds]) -> f64 {
+    return all_odds
+        .iter()
+        .fold(0.0, |acc, x| acc + x.implied_probability());
+}
+
+impl Arbitrage {
+    pub fn matchup(&self) -> String {
+        return matchup(&self.home_team, &self.away_team);
+    }
+
+    /// Splits `bankroll` across `best_prices` so every outcome pays out the
+    /// same, or returns `None` if the prices don't add up to a sure bet.
+    /// `best_prices` must cover every outcome of the market.
+    pub fn from_best_prices(
+        sport_title: &str,
+        home_team: &str,
+        away_team: &str,
+        market_key: &MarketType,
+        best_prices: &[BestPrice],
+        bankroll: f64,
+    ) -> Option<Arbitrage> {
+        if (best_prices.len() < 2) {
+            return None;
+        }
+
+        let all_odds: Vec<Odds> = best_prices.iter().map(|x| x.odds).collect();
+        let total_probability = total_implied_probability(&all_odds);
+        if (total_probability >= 1.0) {
+            return None;
+        }
+
+        let payout = bankroll / total_probability;
+        let legs = best_prices
+            .iter()
+            .map(|x| ArbitrageLeg {
+                outcome_key: x.outcome_key.clone(),
+                point: x.point,
+                bookie_name: x.bookie_name.clone(),
+                odds: x.odds,
+                stake: payout / x.odds.get_decimal(),
+            })
+            .collect();
+
+        return Some(Arbitrage {
+            sport_title: sport_title.to_string(),
+            home_team: home_team.to_string(),
+            away_team: away_team.to_string(),
+            market_key: market_key.clone(),
+            legs,
+            bankroll,
+            profit_margin: 100.0 * (1.0 / total_probability - 1.0),
+            guaranteed_profit: payout - bankroll,
+        });
+    }
+}
+
+impl fmt::Display for Arbitrage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut markdown_str = format!(
+            " ### Arbitrage found on {0}
+    - {1:.2}%: {2}
+    - Market: {3}
+    - Guaranteed profit: {4:.2} on {5:.2}
+",
+            self.sport_title,
+            self.profit_margin,
+            self.matchup(),
+            self.market_key,
+            self.guaranteed_profit,
+            self.bankroll,
+        );
+
+        for leg in &self.legs {
+            let outcome = match leg.point {
+                Some(point) => format!("{0} {1}", leg.outcome_key, point),
+                None => leg.outcome_key.clone(),
+            };
+            markdown_str += &format!(
+                "    - Stake {0:.2} on {1} at {2} with {3}\n",
+                leg.stake, outcome, leg.odds, leg.bookie_name
+            );
+        }
+
+        return write!(f, "{}", markdown_str);
+    }
+}
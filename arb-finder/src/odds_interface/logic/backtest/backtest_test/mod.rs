@@ -0,0 +1,137 @@
+use std::fs;
+
+use chrono::Duration;
+
+use crate::odds_interface::logic::{
+    backtest::{format_backtest_table, run_backtest, ParameterSet},
+    devig::DevigMethod,
+    event::Event,
+    market::MarketType,
+    score::EventScore,
+    settings::ScanSettings,
+};
+
+fn get_afl_events() -> Vec<Event> {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_odds.json")
+        .expect("Unable to read file");
+    return serde_json::from_str::<Vec<Event>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+}
+
+/// St Kilda Saints 88, Brisbane Lions 81
+fn get_afl_results() -> Vec<EventScore> {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_scores.json")
+        .expect("Unable to read file");
+    return serde_json::from_str::<Vec<EventScore>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+}
+
+/// The same odds fetched an hour and half an hour before the start, and
+/// again once the game is under way
+fn get_afl_snapshots() -> Vec<Event> {
+    let mut snapshots = Vec::new();
+    for minutes_before in [60, 30, -10] {
+        for mut event in get_afl_events() {
+            event.set_fetched_at(event.commence_time() - Duration::minutes(minutes_before));
+            snapshots.push(event);
+        }
+    }
+    return snapshots;
+}
+
+#[test]
+fn test_each_opportunity_is_bet_once() {
+    let settings = ScanSettings::default();
+    let num_found: usize = get_afl_events()
+        .iter()
+        .map(|x| x.identify_opportunities_using(&settings).len())
+        .sum();
+    assert!(num_found > 0);
+
+    let report = run_backtest(
+        "configured",
+        &get_afl_snapshots(),
+        &get_afl_results(),
+        &settings,
+        10.0,
+        1000.0,
+    );
+    assert_eq!(report.opportunities, num_found);
+    assert_eq!(report.summary.settled + report.summary.pending, num_found);
+    assert!((report.summary.turnover - 10.0 * report.summary.settled as f64).abs() < 1e-9);
+
+    let decided = report.summary.won + report.summary.lost;
+    assert!(decided > 0);
+    assert!((report.hit_rate - 100.0 * report.summary.won as f64 / decided as f64).abs() < 1e-9);
+    assert!(report.max_drawdown >= 0.0);
+    assert!(report.max_drawdown <= 10.0 * report.summary.lost as f64);
+}
+
+#[test]
+fn test_unsettled_bets_are_pending() {
+    let report = run_backtest(
+        "configured",
+        &get_afl_snapshots(),
+        &[],
+        &ScanSettings::default(),
+        10.0,
+        1000.0,
+    );
+    assert_eq!(report.summary.settled, 0);
+    assert_eq!(report.summary.pending, report.opportunities);
+    assert_eq!(report.hit_rate, 0.0);
+    assert_eq!(report.max_drawdown, 0.0);
+}
+
+#[test]
+fn test_parameter_sets() {
+    let base = ScanSettings::default();
+    let parameters = ParameterSet {
+        name: String::from("shin_equal_weights"),
+        devig_method: Some(DevigMethod::Shin),
+        equal_weights: true,
+        percent_ev_cutoff: Some(1000.0),
+    };
+
+    let settings = parameters.apply(&base);
+    assert_eq!(
+        settings
+            .devig
+            .method_for("aussierules_afl", &MarketType::Outrights),
+        DevigMethod::Shin
+    );
+    assert_eq!(
+        settings
+            .bookmaker_weights
+            .weight_for("aussierules_afl", &MarketType::H2h, "pinnacle"),
+        1.0
+    );
+    assert_eq!(settings.percent_ev_cutoff_for("aussierules_afl"), 1000.0);
+
+    // unset parameters keep the configured values
+    let unchanged = ParameterSet::default().apply(&base);
+    assert_eq!(unchanged.percent_ev_cutoff, base.percent_ev_cutoff);
+
+    let snapshots = get_afl_snapshots();
+    let results = get_afl_results();
+    let reports = [
+        run_backtest("configured", &snapshots, &results, &base, 10.0, 1000.0),
+        run_backtest(
+            &parameters.name,
+            &snapshots,
+            &results,
+            &settings,
+            10.0,
+            1000.0,
+        ),
+    ];
+    assert_eq!(reports[1].opportunities, 0);
+
+    let table = format_backtest_table(&reports);
+    assert_eq!(table.lines().count(), 3);
+    assert!(table
+        .lines()
+        .nth(2)
+        .unwrap()
+        .starts_with("shin_equal_weights"));
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use super::devig::{DevigMethod, DevigSettings};
+use super::event::Event;
+use super::ledger::{summarise, Bet, BetStatus, PerformanceSummary};
+use super::score::EventScore;
+use super::settings::ScanSettings;
+use super::weights::BookmakerWeights;
+
+#[cfg(test)]
+mod backtest_test;
+
+const DEFAULT_STAKE: f64 = 10.0;
+const DEFAULT_HISTORICAL_INTERVAL_MINUTES: i64 = 60;
+
+/// Changes to the scan settings to replay history with. Anything left unset
+/// keeps the configured value.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ParameterSet {
+    pub name: String,
+    /// De-vig method for every sport and market
+    pub devig_method: Option<DevigMethod>,
+    /// Count every bookmaker the same in the consensus, rather than by
+    /// sharpness
+    pub equal_weights: bool,
+    /// EV cutoff for every sport
+    pub percent_ev_cutoff: Option<f64>,
+}
+
+impl ParameterSet {
+    pub fn apply(&self, base: &ScanSettings) -> ScanSettings {
+        let mut settings = base.clone();
+        if let Some(method) = self.devig_method {
+            settings.devig = DevigSettings::with_method(method);
+        }
+        if (self.equal_weights) {
+            settings.bookmaker_weights = BookmakerWeights::equal();
+        }
+        if let Some(cutoff) = self.percent_ev_cutoff {
+            settings.percent_ev_cutoff = cutoff;
+            settings.sport_percent_ev_cutoffs.clear();
+        }
+        return settings;
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BacktestSettings {
+    /// Flat stake of every simulated bet
+    pub stake: f64,
+    /// Time between snapshots fetched from the historical odds endpoint
+    pub historical_interval_minutes: i64,
+    /// Compared against each other, along with the configured settings
+    pub parameter_sets: Vec<ParameterSet>,
+}
+
+impl Default for BacktestSettings {
+    fn default() -> Self {
+        return BacktestSettings {
+            stake: DEFAULT_STAKE,
+            historical_interval_minutes: DEFAULT_HISTORICAL_INTERVAL_MINUTES,
+            parameter_sets: Vec::new(),
+        };
+    }
+}
+
+/// How one parameter set would have done
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    pub name: String,
+    /// Distinct opportunities found, each bet once at the first price seen
+    pub opportunities: usize,
+    pub summary: PerformanceSummary,
+    /// Percentage of won and lost bets that won
+    pub hit_rate: f64,
+    /// Largest fall in cumulative profit from its previous peak, betting in
+    /// order of kick off
+    pub max_drawdown: f64,
+}
+
+/// Replays `snapshots` through the opportunity finders with `settings`,
+/// betting `stake` on each opportunity the first time it is seen before
+/// its event starts, and settles the bets against `results`
+pub fn run_backtest(
+    name: &str,
+    snapshots: &[Event],
+    results: &[EventScore],
+    settings: &ScanSettings,
+    stake: f64,
+    bankroll: f64,
+) -> BacktestReport {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut bets: Vec<Bet> = Vec::new();
+
+    for event in snapshots {
+        let placed_at = match event.fetched_at() {
+            Some(x) => x,
+            None => event.commence_time(),
+        };
+        if (placed_at > event.commence_time()) {
+            continue;
+        }
+
+        for opportunity in event.identify_opportunities_using(settings) {
+            let mut bet = opportunity.as_bet(stake, placed_at);
+            let identity = format!(
+                "{0}|{1}|{2}|{3:?}|{4}",
+                bet.event_id, bet.market_key, bet.outcome_key, bet.point, bet.bookmaker_key
+            );
+            if (!seen.insert(identity)) {
+                continue;
+            }
+
+            if let Some(score) = results.iter().find(|x| x.id == bet.event_id) {
+                bet.status = bet.grade(score);
+                if (bet.status != BetStatus::Pending) {
+                    bet.settled_at = score.last_update;
+                }
+            }
+            bets.push(bet);
+        }
+    }
+
+    bets.sort_by_key(|x| x.commence_time);
+    let mut cumulative_profit = 0.0;
+    let mut peak = 0.0;
+    let mut max_drawdown = 0.0;
+    for profit in bets.iter().filter_map(|x| x.profit()) {
+        cumulative_profit += profit;
+        peak = f64::max(peak, cumulative_profit);
+        max_drawdown = f64::max(max_drawdown, peak - cumulative_profit);
+    }
+
+    let summary = summarise(&bets.iter().collect::<Vec<&Bet>>(), bankroll);
+    let mut hit_rate = 0.0;
+    if (summary.won + summary.lost > 0) {
+        hit_rate = 100.0 * summary.won as f64 / (summary.won + summary.lost) as f64;
+    }
+
+    return BacktestReport {
+        name: name.to_string(),
+        opportunities: bets.len(),
+        summary,
+        hit_rate,
+        max_drawdown,
+    };
+}
+
+/// Plain text table comparing parameter sets, one row each
+pub fn format_backtest_table(reports: &[BacktestReport]) -> String {
+    let mut table = format!(
+        "{0:<24} {1:>7} {2:>7} {3:>7} {4:>10} {5:>8} {6:>8} {7:>10}\n",
+        "parameters", "opps", "settled", "hit %", "profit", "roi %", "yield %", "drawdown"
+    );
+    for report in reports {
+        table += &format!(
+            "{0:<24} {1:>7} {2:>7} {3:>7.2} {4:>10.2} {5:>8.2} {6:>8.2} {7:>10.2}\n",
+            report.name,
+            report.opportunities,
+            report.summary.settled,
+            report.hit_rate,
+            report.summary.profit,
+            report.summary.roi,
+            report.summary.yield_percent,
+            report.max_drawdown
+        );
+    }
+    return table;
+}
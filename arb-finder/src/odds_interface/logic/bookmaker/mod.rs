@@ -2,8 +2,10 @@ use std::{collections::HashSet, fmt, marker};
 
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use strum_macros::EnumString;
 
 use super::{
+    exchange::lay_market_for,
     market::{Market, MarketType, Outcome},
     odds::Odds,
 };
@@ -16,8 +18,9 @@ pub struct Bookmaker {
     pub markets: Vec<Market>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Debug, Clone, PartialEq, EnumString)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum Region {
     Us,
     Us2,
@@ -37,7 +40,127 @@ impl fmt::Display for Region {
         }
     }
 }
+
+impl Region {
+    const ALL: [Region; 5] = [Region::Us, Region::Us2, Region::Uk, Region::Au, Region::Eu];
+
+    /// Keys of the bookmakers the Odds API returns for this region, past
+    /// and present. Some books are in more than one region.
+    pub fn bookmaker_keys(&self) -> &'static [&'static str] {
+        match *self {
+            Region::Us => &[
+                "barstool",
+                "betmgm",
+                "betonlineag",
+                "betrivers",
+                "betus",
+                "bovada",
+                "caesars",
+                "draftkings",
+                "fanatics",
+                "fanduel",
+                "lowvig",
+                "mybookieag",
+                "pointsbetus",
+                "sugarhouse",
+                "superbook",
+                "twinspires",
+                "unibet_us",
+                "williamhill_us",
+                "wynnbet",
+            ],
+            Region::Us2 => &[
+                "ballybet",
+                "betanysports",
+                "betparx",
+                "espnbet",
+                "fliff",
+                "hardrockbet",
+                "windcreek",
+            ],
+            Region::Uk => &[
+                "betfair_ex_uk",
+                "betfair_sb_uk",
+                "betvictor",
+                "betway",
+                "boylesports",
+                "casumo",
+                "coral",
+                "grosvenor",
+                "ladbrokes_uk",
+                "leovegas",
+                "livescorebet",
+                "matchbook",
+                "mrgreen",
+                "paddypower",
+                "skybet",
+                "smarkets",
+                "sport888",
+                "unibet_uk",
+                "virginbet",
+                "williamhill",
+            ],
+            Region::Au => &[
+                "bet365_au",
+                "betdeluxe",
+                "betfair_ex_au",
+                "betr_au",
+                "betright",
+                "bluebet",
+                "boombet",
+                "dabble_au",
+                "ladbrokes_au",
+                "neds",
+                "playup",
+                "pointsbetau",
+                "sportsbet",
+                "tab",
+                "tabtouch",
+                "topsport",
+                "unibet",
+            ],
+            Region::Eu => &[
+                "betclic",
+                "betfair_ex_eu",
+                "betonlineag",
+                "betsson",
+                "betvictor",
+                "coolbet",
+                "everygame",
+                "gtbets",
+                "livescorebet_eu",
+                "marathonbet",
+                "matchbook",
+                "mybookieag",
+                "nordicbet",
+                "onexbet",
+                "pinnacle",
+                "sport888",
+                "suprabets",
+                "tipico_de",
+                "unibet_eu",
+                "williamhill",
+                "winamax_de",
+                "winamax_fr",
+            ],
+        }
+    }
+
+    /// Regions that include `bookmaker_key`, empty for a book we don't know
+    pub fn of_bookmaker(bookmaker_key: &str) -> Vec<Region> {
+        return Region::ALL
+            .into_iter()
+            .filter(|x| x.bookmaker_keys().contains(&bookmaker_key))
+            .collect();
+    }
+}
 impl Bookmaker {
+    /// When the bookmaker's prices last changed
+    pub fn last_update(&self) -> DateTime<Utc> {
+        return self.last_update;
+    }
+
+    /// Back markets only, without exchange lay markets
     pub fn get_enabled_markets(&self) -> Vec<Market> {
         let to_exclude = [MarketType::OutrightsLay, MarketType::H2hLay];
         return self
@@ -57,6 +180,12 @@ impl Bookmaker {
         return None;
     }
 
+    /// Exchange price to lay `outcome_key` in `market_key`
+    pub fn get_lay_odds(&self, market_key: &MarketType, outcome_key: &str) -> Option<Odds> {
+        let lay_market = lay_market_for(market_key)?;
+        return self.get_odds(&lay_market, outcome_key);
+    }
+
     pub fn get_over_under_line(&self) -> Option<f64> {
         for market in &self.markets {
             if market.key == MarketType::Totals {
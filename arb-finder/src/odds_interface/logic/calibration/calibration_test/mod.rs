@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use chrono::Duration;
+use strum::IntoEnumIterator;
+
+use crate::odds_interface::logic::{
+    calibration::{
+        closing_snapshots, format_calibration_csv, format_reliability_chart, predictions_for,
+        summarise_calibration, summarise_calibration_by, Prediction, CONSENSUS_KEY,
+    },
+    devig::DevigMethod,
+    event::Event,
+    market::MarketType,
+    score::EventScore,
+    settings::ScanSettings,
+};
+
+fn get_afl_events() -> Vec<Event> {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_odds.json")
+        .expect("Unable to read file");
+    return serde_json::from_str::<Vec<Event>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+}
+
+/// St Kilda Saints 88, Brisbane Lions 81
+fn get_afl_results() -> Vec<EventScore> {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_scores.json")
+        .expect("Unable to read file");
+    return serde_json::from_str::<Vec<EventScore>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+}
+
+fn prediction(probability: f64, happened: bool) -> Prediction {
+    return Prediction {
+        event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+        sport_key: String::from("aussierules_afl"),
+        market_key: MarketType::H2h,
+        outcome_key: String::from("St Kilda Saints"),
+        point: None,
+        devig_method: DevigMethod::Multiplicative,
+        bookmaker_key: String::from("sportsbet"),
+        probability,
+        happened,
+    };
+}
+
+#[test]
+fn test_brier_score_and_log_loss() {
+    let predictions = [
+        prediction(0.8, true),
+        prediction(0.2, false),
+        prediction(0.6, false),
+        prediction(0.5, true),
+    ];
+    let summary = summarise_calibration(&predictions.iter().collect::<Vec<_>>(), 10);
+
+    assert_eq!(summary.count, 4);
+    let brier = (0.04 + 0.04 + 0.36 + 0.25) / 4.0;
+    assert!((summary.brier_score - brier).abs() < 1e-9);
+    let log_loss = -(0.8_f64.ln() + 0.8_f64.ln() + 0.4_f64.ln() + 0.5_f64.ln()) / 4.0;
+    assert!((summary.log_loss - log_loss).abs() < 1e-9);
+
+    assert_eq!(summary.bins.len(), 10);
+    assert_eq!(summary.bins[8].count, 1);
+    assert_eq!(summary.bins[8].observed_rate, 1.0);
+    assert_eq!(summary.bins[5].count, 1);
+    assert_eq!(summary.bins[6].count, 1);
+    assert_eq!(summary.bins[6].observed_rate, 0.0);
+    assert_eq!(summary.bins[2].count, 1);
+    assert_eq!(summary.bins.iter().map(|x| x.count).sum::<usize>(), 4);
+}
+
+#[test]
+fn test_certain_misses_have_finite_log_loss() {
+    let predictions = [prediction(1.0, false), prediction(0.0, true)];
+    let summary = summarise_calibration(&predictions.iter().collect::<Vec<_>>(), 4);
+
+    assert_eq!(summary.brier_score, 1.0);
+    assert!(summary.log_loss.is_finite());
+    assert!(summary.log_loss > 30.0);
+    assert_eq!(summary.bins[0].count, 1);
+    assert_eq!(summary.bins[3].count, 1);
+
+    let empty = summarise_calibration(&[], 4);
+    assert_eq!(empty.count, 0);
+    assert_eq!(empty.brier_score, 0.0);
+}
+
+#[test]
+fn test_closing_snapshots_ignore_in_play_odds() {
+    let mut snapshots = Vec::new();
+    for minutes_before in [60, 5, -10] {
+        for mut event in get_afl_events() {
+            event.set_fetched_at(event.commence_time() - Duration::minutes(minutes_before));
+            snapshots.push(event);
+        }
+    }
+
+    let closing = closing_snapshots(&snapshots);
+    assert_eq!(closing.len(), get_afl_events().len());
+    for event in closing {
+        assert_eq!(
+            event.fetched_at(),
+            Some(event.commence_time() - Duration::minutes(5))
+        );
+    }
+
+    assert!(closing_snapshots(&get_afl_events()).is_empty());
+}
+
+#[test]
+fn test_predictions_against_the_result() {
+    let event = &get_afl_events()[0];
+    let score = &get_afl_results()[0];
+    let predictions = predictions_for(event, score, &ScanSettings::default());
+    assert!(!predictions.is_empty());
+
+    for method in DevigMethod::iter() {
+        assert!(predictions
+            .iter()
+            .any(|x| x.devig_method == method && x.bookmaker_key == CONSENSUS_KEY));
+    }
+    for prediction in &predictions {
+        assert!(prediction.probability > 0.0 && prediction.probability < 1.0);
+    }
+
+    let h2h: Vec<&Prediction> = predictions
+        .iter()
+        .filter(|x| x.market_key == MarketType::H2h)
+        .collect();
+    assert!(!h2h.is_empty());
+    // Both teams are priced in every h2h market and only St Kilda won
+    let num_happened = h2h.iter().filter(|x| x.happened).count();
+    assert_eq!(2 * num_happened, h2h.len());
+
+    // A consensus probability per outcome and method, not per bookmaker
+    let consensus_h2h = h2h
+        .iter()
+        .filter(|x| x.bookmaker_key == CONSENSUS_KEY)
+        .count();
+    assert_eq!(consensus_h2h, 2 * DevigMethod::iter().count());
+
+    let by_method = summarise_calibration_by(&h2h, 10, |x| x.devig_method.to_string());
+    assert_eq!(by_method.len(), DevigMethod::iter().count());
+
+    let mut unfinished = score.clone();
+    unfinished.completed = false;
+    assert!(predictions_for(event, &unfinished, &ScanSettings::default()).is_empty());
+}
+
+#[test]
+fn test_csv_and_chart() {
+    let predictions = [
+        prediction(0.70, true),
+        prediction(0.72, false),
+        prediction(0.25, false),
+    ];
+    let summary = summarise_calibration(&predictions.iter().collect::<Vec<_>>(), 4);
+
+    let mut groupings = BTreeMap::new();
+    groupings.insert(
+        String::from("bookmaker"),
+        BTreeMap::from([(String::from("sportsbet"), summary.clone())]),
+    );
+    let csv = format_calibration_csv(&groupings);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 1 + 4);
+    assert!(lines[0].starts_with("grouping,group,count,brier_score"));
+    assert!(lines[1].starts_with("bookmaker,sportsbet,3,"));
+
+    let chart = format_reliability_chart("h2h", &summary);
+    let rows: Vec<&str> = chart.lines().collect();
+    // Title and the two bins with predictions
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].starts_with("h2h (3 predictions)"));
+    assert!(rows[1].starts_with("0.25-0.50"));
+    assert!(!rows[1].contains('#'));
+    assert!(rows[2].starts_with("0.50-0.75"));
+    assert!(rows[2].contains('#') && rows[2].contains('|'));
+}
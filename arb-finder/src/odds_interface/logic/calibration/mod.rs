@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::devig::{DevigMethod, DevigSettings};
+use super::event::Event;
+use super::ledger::{grade_outcome, BetStatus};
+use super::market::{MarketType, Outcome};
+use super::score::EventScore;
+use super::settings::ScanSettings;
+
+#[cfg(test)]
+mod calibration_test;
+
+/// Predictions made from the consensus rather than a single bookmaker
+pub const CONSENSUS_KEY: &str = "consensus";
+
+const DEFAULT_NUM_BINS: usize = 10;
+const CHART_WIDTH: usize = 40;
+/// Probabilities are clamped this far from 0 and 1 for log loss
+const LOG_LOSS_EPSILON: f64 = 1e-15;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CalibrationSettings {
+    /// Number of equal width probability bins in the reliability curves
+    pub num_bins: usize,
+    /// Where the CSV and JSON reports are written
+    pub output_dir: PathBuf,
+}
+
+impl Default for CalibrationSettings {
+    fn default() -> Self {
+        return CalibrationSettings {
+            num_bins: DEFAULT_NUM_BINS,
+            output_dir: PathBuf::from("./reports"),
+        };
+    }
+}
+
+/// A fair probability we had for an outcome, and whether it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub event_id: String,
+    pub sport_key: String,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    /// Line for totals and spreads
+    pub point: Option<f64>,
+    pub devig_method: DevigMethod,
+    /// The bookmaker whose prices were de-vigged, or `CONSENSUS_KEY`
+    pub bookmaker_key: String,
+    pub probability: f64,
+    pub happened: bool,
+}
+
+/// The last snapshot of each event fetched before it started, oldest event
+/// first. `snapshots` must be oldest first.
+pub fn closing_snapshots(snapshots: &[Event]) -> Vec<&Event> {
+    let mut latest: HashMap<&str, &Event> = HashMap::new();
+    for event in snapshots {
+        let fetched_at = match event.fetched_at() {
+            Some(x) => x,
+            None => continue,
+        };
+        if (fetched_at <= event.commence_time()) {
+            latest.insert(event.id(), event);
+        }
+    }
+
+    let mut closing: Vec<&Event> = latest.into_values().collect();
+    closing.sort_by(|a, b| {
+        return a
+            .commence_time()
+            .cmp(&b.commence_time())
+            .then(a.id().cmp(b.id()));
+    });
+    return closing;
+}
+
+/// Whether an outcome won against its final `score`, or None for pushes
+/// and markets that can't be graded
+fn outcome_happened(
+    score: &EventScore,
+    market: &MarketType,
+    outcome_key: &str,
+    point: Option<f64>,
+) -> Option<bool> {
+    match grade_outcome(market, outcome_key, point, score) {
+        BetStatus::Won => return Some(true),
+        BetStatus::Lost => return Some(false),
+        _ => return None,
+    }
+}
+
+/// Every bookmaker's de-vigged probability and the consensus fair
+/// probability of each h2h, totals and spreads outcome of `event`, with
+/// every de-vig method, against its final `score`
+pub fn predictions_for(
+    event: &Event,
+    score: &EventScore,
+    settings: &ScanSettings,
+) -> Vec<Prediction> {
+    let mut predictions = Vec::new();
+    if (!score.completed) {
+        return predictions;
+    }
+
+    for market_key in [MarketType::H2h, MarketType::Totals, MarketType::Spreads] {
+        for method in DevigMethod::iter() {
+            let method_settings = ScanSettings {
+                devig: DevigSettings::with_method(method),
+                ..settings.clone()
+            };
+            let mut prediction =
+                |outcome: &Outcome, bookmaker_key: &str, probability: f64, happened: bool| {
+                    predictions.push(Prediction {
+                        event_id: event.id().to_string(),
+                        sport_key: event.sport_key().to_string(),
+                        market_key: market_key.clone(),
+                        outcome_key: outcome.name.clone(),
+                        point: outcome.point,
+                        devig_method: method,
+                        bookmaker_key: bookmaker_key.to_string(),
+                        probability,
+                        happened,
+                    });
+                };
+
+            // (outcome, point) pairs already given a consensus probability
+            let mut priced: Vec<(String, Option<f64>)> = Vec::new();
+            for bookie in event.bookmakers() {
+                let market = match bookie.markets.iter().find(|x| x.key == market_key) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                for outcome in &market.outcomes {
+                    let happened =
+                        match outcome_happened(score, &market_key, &outcome.name, outcome.point) {
+                            Some(x) => x,
+                            None => continue,
+                        };
+
+                    if let Some(probability) =
+                        market.true_probability_for_outcome(&outcome.name, &method)
+                    {
+                        prediction(outcome, &bookie.key, probability, happened);
+                    }
+
+                    let line = (outcome.name.clone(), outcome.point);
+                    if (priced.contains(&line)) {
+                        continue;
+                    }
+                    if let Some(fair_odds) = event.fair_odds_for(
+                        &market_key,
+                        &outcome.name,
+                        outcome.point,
+                        &method_settings,
+                    ) {
+                        prediction(
+                            outcome,
+                            CONSENSUS_KEY,
+                            fair_odds.implied_probability(),
+                            happened,
+                        );
+                    }
+                    priced.push(line);
+                }
+            }
+        }
+    }
+    return predictions;
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReliabilityBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_probability: f64,
+    /// How often outcomes in the bin happened
+    pub observed_rate: f64,
+}
+
+/// How well a set of predictions matched what happened
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CalibrationSummary {
+    pub count: usize,
+    /// Mean squared error of the probabilities, 0 being perfect
+    pub brier_score: f64,
+    pub log_loss: f64,
+    pub bins: Vec<ReliabilityBin>,
+}
+
+/// Negative log likelihood of what happened, given `probability` that it
+/// would
+pub fn log_loss(probability: f64, happened: bool) -> f64 {
+    let clamped = probability.clamp(LOG_LOSS_EPSILON, 1.0 - LOG_LOSS_EPSILON);
+    if (happened) {
+        return -clamped.ln();
+    }
+    return -(1.0 - clamped).ln();
+}
+
+pub fn summarise_calibration(predictions: &[&Prediction], num_bins: usize) -> CalibrationSummary {
+    let num_bins = num_bins.max(1);
+    let mut brier_total = 0.0;
+    let mut log_loss_total = 0.0;
+    let mut bin_counts = vec![0; num_bins];
+    let mut bin_probabilities = vec![0.0; num_bins];
+    let mut bin_happened = vec![0; num_bins];
+
+    for prediction in predictions {
+        let probability = prediction.probability.clamp(0.0, 1.0);
+        let actual = match prediction.happened {
+            true => 1.0,
+            false => 0.0,
+        };
+        brier_total += (probability - actual).powi(2);
+
+        log_loss_total += log_loss(probability, prediction.happened);
+
+        let bin = ((probability * num_bins as f64) as usize).min(num_bins - 1);
+        bin_counts[bin] += 1;
+        bin_probabilities[bin] += probability;
+        if (prediction.happened) {
+            bin_happened[bin] += 1;
+        }
+    }
+
+    let bins = (0..num_bins)
+        .map(|i| {
+            let count = bin_counts[i];
+            let (mean_probability, observed_rate) = match count {
+                0 => (0.0, 0.0),
+                _ => (
+                    bin_probabilities[i] / count as f64,
+                    bin_happened[i] as f64 / count as f64,
+                ),
+            };
+            return ReliabilityBin {
+                lower: i as f64 / num_bins as f64,
+                upper: (i + 1) as f64 / num_bins as f64,
+                count,
+                mean_probability,
+                observed_rate,
+            };
+        })
+        .collect();
+
+    let count = predictions.len();
+    let (brier_score, log_loss) = match count {
+        0 => (0.0, 0.0),
+        _ => (brier_total / count as f64, log_loss_total / count as f64),
+    };
+    return CalibrationSummary {
+        count,
+        brier_score,
+        log_loss,
+        bins,
+    };
+}
+
+/// Summaries of `predictions` grouped by `group_key`, e.g. bookmaker
+pub fn summarise_calibration_by<F: Fn(&Prediction) -> String>(
+    predictions: &[&Prediction],
+    num_bins: usize,
+    group_key: F,
+) -> BTreeMap<String, CalibrationSummary> {
+    let mut groups: BTreeMap<String, Vec<&Prediction>> = BTreeMap::new();
+    for prediction in predictions {
+        groups
+            .entry(group_key(prediction))
+            .or_default()
+            .push(prediction);
+    }
+
+    return groups
+        .into_iter()
+        .map(|(key, group)| (key, summarise_calibration(&group, num_bins)))
+        .collect();
+}
+
+/// Plain text table of `summaries`, one row per group
+pub fn format_calibration_table(
+    title: &str,
+    summaries: &BTreeMap<String, CalibrationSummary>,
+) -> String {
+    let mut table = format!(
+        "{title:<24} {0:>7} {1:>8} {2:>8}\n",
+        "count", "brier", "log loss"
+    );
+    for (key, summary) in summaries {
+        table += &format!(
+            "{key:<24} {0:>7} {1:>8.4} {2:>8.4}\n",
+            summary.count, summary.brier_score, summary.log_loss
+        );
+    }
+    return table;
+}
+
+/// ASCII reliability curve: a bar of the observed rate in each bin, with
+/// `|` where the bar would end if the predictions were perfectly calibrated
+pub fn format_reliability_chart(title: &str, summary: &CalibrationSummary) -> String {
+    let mut chart = format!("{title} ({0} predictions)\n", summary.count);
+    for bin in &summary.bins {
+        if (bin.count == 0) {
+            continue;
+        }
+
+        let observed = (bin.observed_rate * CHART_WIDTH as f64).round() as usize;
+        let expected =
+            ((bin.mean_probability * CHART_WIDTH as f64).round() as usize).min(CHART_WIDTH);
+        let bar: String = (0..=CHART_WIDTH)
+            .map(|i| {
+                if (i == expected) {
+                    return '|';
+                } else if (i < observed) {
+                    return '#';
+                }
+                return ' ';
+            })
+            .collect();
+        chart += &format!(
+            "{0:.2}-{1:.2} {2:>6} {bar} {3:.2} vs {4:.2}\n",
+            bin.lower, bin.upper, bin.count, bin.observed_rate, bin.mean_probability
+        );
+    }
+    return chart;
+}
+
+/// One row per reliability bin of every group of every grouping, e.g.
+/// `bookmaker,pinnacle,...`
+pub fn format_calibration_csv(
+    groupings: &BTreeMap<String, BTreeMap<String, CalibrationSummary>>,
+) -> String {
+    let mut csv = String::from(
+        "grouping,group,count,brier_score,log_loss,bin_lower,bin_upper,bin_count,mean_probability,observed_rate\n",
+    );
+    for (grouping, summaries) in groupings {
+        for (key, summary) in summaries {
+            for bin in &summary.bins {
+                csv += &format!(
+                    "{grouping},{key},{0},{1},{2},{3},{4},{5},{6},{7}\n",
+                    summary.count,
+                    summary.brier_score,
+                    summary.log_loss,
+                    bin.lower,
+                    bin.upper,
+                    bin.count,
+                    bin.mean_probability,
+                    bin.observed_rate
+                );
+            }
+        }
+    }
+    return csv;
+}
@@ -0,0 +1,88 @@
+use chrono::{Duration, TimeZone, Utc};
+
+use crate::odds_interface::logic::{
+    clv::{format_clv_table, summarise_clv, summarise_clv_by, ClvSettings, TrackedOpportunity},
+    devig::DevigMethod,
+    event::PricingModel,
+    market::MarketType,
+    odds::Odds,
+};
+
+fn get_tracked(
+    bookmaker_key: &str,
+    offered_odds: f64,
+    closing_odds: Option<f64>,
+) -> TrackedOpportunity {
+    return TrackedOpportunity {
+        id: None,
+        event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+        sport_key: String::from("aussierules_afl"),
+        commence_time: Utc.with_ymd_and_hms(2024, 8, 4, 6, 40, 0).unwrap(),
+        market_key: MarketType::H2h,
+        outcome_key: String::from("St Kilda Saints"),
+        point: None,
+        bookmaker_key: bookmaker_key.to_string(),
+        model: PricingModel::Consensus,
+        devig_method: DevigMethod::Multiplicative,
+        offered_odds: Odds::Decimal(offered_odds),
+        true_odds: Odds::Decimal(2.0),
+        percent_ev: 100.0 * (offered_odds / 2.0) - 100.0,
+        found_at: Utc.with_ymd_and_hms(2024, 8, 4, 2, 0, 0).unwrap(),
+        closing_odds: closing_odds.map(Odds::Decimal),
+        closed_at: None,
+    };
+}
+
+#[test]
+fn test_clv_percent() {
+    assert_eq!(get_tracked("sportsbet", 2.2, None).clv_percent(), None);
+
+    let beat = get_tracked("sportsbet", 2.2, Some(2.0))
+        .clv_percent()
+        .unwrap();
+    assert!((beat - 10.0).abs() < 1e-9);
+
+    let missed = get_tracked("sportsbet", 2.2, Some(2.75))
+        .clv_percent()
+        .unwrap();
+    assert!((missed + 20.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_summarise_clv() {
+    let opportunities = [
+        get_tracked("sportsbet", 2.2, Some(2.0)),
+        get_tracked("sportsbet", 2.2, Some(2.75)),
+        get_tracked("tab", 2.1, Some(1.75)),
+        get_tracked("tab", 2.1, None),
+    ];
+
+    let summary = summarise_clv(&opportunities.iter().collect::<Vec<_>>());
+    assert_eq!(summary.tracked, 4);
+    assert_eq!(summary.closed, 3);
+    assert_eq!(summary.beat_close, 2);
+    // (10 - 20 + 20) / 3
+    assert!((summary.mean_clv_percent - 10.0 / 3.0).abs() < 1e-9);
+    assert!((summary.mean_expected_ev - (10.0 + 10.0 + 5.0) / 3.0).abs() < 1e-9);
+
+    let by_bookie = summarise_clv_by(&opportunities, |x| x.bookmaker_key.clone());
+    assert_eq!(by_bookie["sportsbet"].closed, 2);
+    assert!((by_bookie["sportsbet"].beat_close_percent() - 50.0).abs() < 1e-9);
+    assert_eq!(by_bookie["tab"].tracked, 2);
+    assert!((by_bookie["tab"].mean_clv_percent - 20.0).abs() < 1e-9);
+
+    let table = format_clv_table("bookmaker", &by_bookie);
+    assert_eq!(table.lines().count(), 3);
+    assert!(table.lines().nth(2).unwrap().starts_with("tab"));
+}
+
+#[test]
+fn test_closing_window() {
+    let settings = ClvSettings::default();
+    let commence_time = Utc.with_ymd_and_hms(2024, 8, 4, 6, 40, 0).unwrap();
+
+    assert!(settings.is_closing(commence_time, commence_time - Duration::minutes(5)));
+    assert!(!settings.is_closing(commence_time, commence_time - Duration::minutes(30)));
+    // too late once it has started
+    assert!(!settings.is_closing(commence_time, commence_time + Duration::minutes(1)));
+}
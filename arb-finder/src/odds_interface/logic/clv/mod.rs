@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use super::devig::DevigMethod;
+use super::event::PricingModel;
+use super::market::MarketType;
+use super::odds::Odds;
+
+#[cfg(test)]
+mod clv_test;
+
+const DEFAULT_CAPTURE_WINDOW_MINUTES: i64 = 10;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ClvSettings {
+    /// Closing odds are captured for events starting within this many
+    /// minutes, so a capture has to run at least this often
+    pub capture_window_minutes: i64,
+}
+
+impl Default for ClvSettings {
+    fn default() -> Self {
+        return ClvSettings {
+            capture_window_minutes: DEFAULT_CAPTURE_WINDOW_MINUTES,
+        };
+    }
+}
+
+impl ClvSettings {
+    /// Whether an event starting at `commence_time` is close enough to
+    /// `now` for its odds to be the closing line
+    pub fn is_closing(&self, commence_time: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let until_start = commence_time - now;
+        return until_start >= Duration::zero()
+            && until_start <= Duration::minutes(self.capture_window_minutes);
+    }
+}
+
+/// An opportunity we alerted on, kept to compare against the closing line
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedOpportunity {
+    /// Row id once the opportunity is stored
+    pub id: Option<i64>,
+    pub event_id: String,
+    pub sport_key: String,
+    pub commence_time: DateTime<Utc>,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    /// Line for totals and spreads
+    pub point: Option<f64>,
+    pub bookmaker_key: String,
+    pub model: PricingModel,
+    pub devig_method: DevigMethod,
+    pub offered_odds: Odds,
+    /// What we thought the fair price was when we found it
+    pub true_odds: Odds,
+    pub percent_ev: f64,
+    pub found_at: DateTime<Utc>,
+    /// Consensus fair odds just before the event started
+    pub closing_odds: Option<Odds>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl TrackedOpportunity {
+    /// How much better the offered odds were than the closing line, as the
+    /// percentage EV of the offered odds against it
+    pub fn clv_percent(&self) -> Option<f64> {
+        let closing_odds = self.closing_odds?;
+        return Some(self.offered_odds.ev_percentage(&closing_odds));
+    }
+}
+
+/// How a set of opportunities fared against the closing line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClvSummary {
+    pub tracked: usize,
+    /// Opportunities with a closing line
+    pub closed: usize,
+    /// Closed opportunities whose offered odds beat the closing line
+    pub beat_close: usize,
+    pub mean_clv_percent: f64,
+    /// Mean EV we expected when finding the closed opportunities
+    pub mean_expected_ev: f64,
+}
+
+impl ClvSummary {
+    pub fn beat_close_percent(&self) -> f64 {
+        if (self.closed == 0) {
+            return 0.0;
+        }
+        return 100.0 * self.beat_close as f64 / self.closed as f64;
+    }
+}
+
+pub fn summarise_clv(opportunities: &[&TrackedOpportunity]) -> ClvSummary {
+    let mut summary = ClvSummary {
+        tracked: opportunities.len(),
+        ..ClvSummary::default()
+    };
+
+    let mut total_clv = 0.0;
+    let mut total_expected_ev = 0.0;
+    for opportunity in opportunities {
+        let clv = match opportunity.clv_percent() {
+            Some(x) => x,
+            None => continue,
+        };
+
+        summary.closed += 1;
+        if (clv > 0.0) {
+            summary.beat_close += 1;
+        }
+        total_clv += clv;
+        total_expected_ev += opportunity.percent_ev;
+    }
+
+    if (summary.closed > 0) {
+        summary.mean_clv_percent = total_clv / summary.closed as f64;
+        summary.mean_expected_ev = total_expected_ev / summary.closed as f64;
+    }
+    return summary;
+}
+
+/// Summaries of `opportunities` grouped by `group_key`, e.g. sport or
+/// pricing model
+pub fn summarise_clv_by<F: Fn(&TrackedOpportunity) -> String>(
+    opportunities: &[TrackedOpportunity],
+    group_key: F,
+) -> BTreeMap<String, ClvSummary> {
+    let mut groups: BTreeMap<String, Vec<&TrackedOpportunity>> = BTreeMap::new();
+    for opportunity in opportunities {
+        groups
+            .entry(group_key(opportunity))
+            .or_default()
+            .push(opportunity);
+    }
+
+    return groups
+        .into_iter()
+        .map(|(key, group)| (key, summarise_clv(&group)))
+        .collect();
+}
+
+/// Plain text table of `summaries`, one row per group
+pub fn format_clv_table(title: &str, summaries: &BTreeMap<String, ClvSummary>) -> String {
+    let mut table = format!(
+        "{title:<24} {0:>7} {1:>7} {2:>8} {3:>8} {4:>10}\n",
+        "tracked", "closed", "beat %", "clv %", "exp ev %"
+    );
+    for (key, summary) in summaries {
+        table += &format!(
+            "{key:<24} {0:>7} {1:>7} {2:>8.2} {3:>8.2} {4:>10.2}\n",
+            summary.tracked,
+            summary.closed,
+            summary.beat_close_percent(),
+            summary.mean_clv_percent,
+            summary.mean_expected_ev
+        );
+    }
+    return table;
+}
@@ -0,0 +1,95 @@
+use strum::IntoEnumIterator;
+
+use crate::odds_interface::logic::{
+    devig::{DevigMethod, DevigSettings},
+    market::MarketType,
+    odds::Odds,
+};
+
+/// Roosters vs Tigers with a draw, roughly 10% overround
+fn get_three_way_odds() -> Vec<Odds> {
+    return [Odds::Decimal(1.2), Odds::Decimal(5.8), Odds::Decimal(10.2)].to_vec();
+}
+
+#[test]
+fn test_every_method_sums_to_one() {
+    let all_odds = get_three_way_odds();
+
+    for method in DevigMethod::iter() {
+        let total: f64 = all_odds
+            .iter()
+            .map(|x| method.true_probability(x, &all_odds))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-6, "{method} summed to {total}");
+    }
+}
+
+#[test]
+fn test_additive_matches_implied_vignette() {
+    let all_odds = get_three_way_odds();
+    let method = DevigMethod::Additive;
+
+    assert!((method.true_probability(&all_odds[0], &all_odds) - 0.799).abs() < 0.01);
+    assert!((method.true_probability(&all_odds[1], &all_odds) - 0.137).abs() < 0.01);
+    assert!((method.true_probability(&all_odds[2], &all_odds) - 0.063).abs() < 0.01);
+}
+
+#[test]
+fn test_longshot_bias_methods_shorten_favourite() {
+    // power, shin and odds ratio put more of the margin on the longshots
+    // than the multiplicative method does
+    let all_odds = get_three_way_odds();
+    let favourite = &all_odds[0];
+    let multiplicative = DevigMethod::Multiplicative.true_probability(favourite, &all_odds);
+
+    for method in [
+        DevigMethod::Power,
+        DevigMethod::Shin,
+        DevigMethod::OddsRatio,
+    ] {
+        assert!(method.true_probability(favourite, &all_odds) > multiplicative);
+    }
+}
+
+#[test]
+fn test_even_market_is_unchanged_by_method() {
+    let all_odds = [Odds::Decimal(1.9), Odds::Decimal(1.9)].to_vec();
+
+    for method in DevigMethod::iter() {
+        assert!((method.true_probability(&all_odds[0], &all_odds) - 0.5).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_settings_prefer_most_specific_method() {
+    let mut settings = DevigSettings::with_method(DevigMethod::Multiplicative);
+    settings
+        .sport_methods
+        .insert(String::from("aussierules_afl"), DevigMethod::Power);
+    settings
+        .market_methods
+        .insert(MarketType::Totals, DevigMethod::Shin);
+    settings.sport_market_methods.insert(
+        String::from("aussierules_afl"),
+        [(MarketType::H2h, DevigMethod::OddsRatio)]
+            .into_iter()
+            .collect(),
+    );
+
+    assert_eq!(
+        settings.method_for("aussierules_afl", &MarketType::H2h),
+        DevigMethod::OddsRatio
+    );
+    assert_eq!(
+        settings.method_for("aussierules_afl", &MarketType::Totals),
+        DevigMethod::Power
+    );
+    assert_eq!(
+        settings.method_for("baseball_kbo", &MarketType::Totals),
+        DevigMethod::Shin
+    );
+    assert_eq!(
+        settings.method_for("baseball_kbo", &MarketType::H2h),
+        DevigMethod::Multiplicative
+    );
+}
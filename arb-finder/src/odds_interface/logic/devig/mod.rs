@@ -0,0 +1,205 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use strum_macros::{EnumIter, EnumString};
+
+use super::market::MarketType;
+use super::odds::Odds;
+
+#[cfg(test)]
+mod devig_test;
+
+const SOLVER_ITERATIONS: usize = 200;
+const SOLVER_TOLERANCE: f64 = 1e-12;
+
+/// Ways of removing the bookmaker margin from a set of prices covering
+/// every outcome of a market.
+///
+/// https://cran.r-project.org/web/packages/implied/vignettes/introduction.html
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DevigMethod {
+    /// Scale every implied probability by the same factor
+    Multiplicative,
+    /// Take an equal share of the margin off every outcome
+    Additive,
+    /// Raise implied probabilities to a common power
+    Power,
+    /// Shin's insider trading model
+    Shin,
+    /// Apply a common odds ratio between implied and fair probabilities
+    OddsRatio,
+    /// Take the margin off in proportion to the log of each price
+    Logarithmic,
+}
+
+impl fmt::Display for DevigMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DevigMethod::Multiplicative => write!(f, "multiplicative"),
+            DevigMethod::Additive => write!(f, "additive"),
+            DevigMethod::Power => write!(f, "power"),
+            DevigMethod::Shin => write!(f, "shin"),
+            DevigMethod::OddsRatio => write!(f, "odds_ratio"),
+            DevigMethod::Logarithmic => write!(f, "logarithmic"),
+        }
+    }
+}
+
+/// Finds the root of a function that is decreasing on `[lo, hi]`
+pub(super) fn solve_decreasing<F: Fn(f64) -> f64>(f: F, lo: f64, hi: f64) -> f64 {
+    let mut lower = lo;
+    let mut upper = hi;
+
+    for _ in 0..SOLVER_ITERATIONS {
+        let mid = (lower + upper) / 2.0;
+        let val = f(mid);
+        if (val.abs() < SOLVER_TOLERANCE) {
+            return mid;
+        }
+
+        if (val > 0.0) {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+
+    return (lower + upper) / 2.0;
+}
+
+fn shin_probability(implied: f64, total_implied: f64, z: f64) -> f64 {
+    let discriminant = z.powf(2.0) + 4.0 * (1.0 - z) * implied.powf(2.0) / total_implied;
+    return (discriminant.sqrt() - z) / (2.0 * (1.0 - z));
+}
+
+fn odds_ratio_probability(implied: f64, ratio: f64) -> f64 {
+    return implied / (ratio + implied - ratio * implied);
+}
+
+impl DevigMethod {
+    /// Estimates the fair probability of `odds`, where `all_odds` holds the
+    /// prices of every outcome in the same market (including `odds` itself).
+    pub fn true_probability(&self, odds: &Odds, all_odds: &[Odds]) -> f64 {
+        let implied: Vec<f64> = all_odds.iter().map(|x| x.implied_probability()).collect();
+        let total_implied: f64 = implied.iter().sum();
+        let margin = total_implied - 1.0;
+        let n = all_odds.len() as f64;
+        let outcome_implied = odds.implied_probability();
+
+        match *self {
+            DevigMethod::Multiplicative => {
+                return outcome_implied / total_implied;
+            }
+            DevigMethod::Additive => {
+                return outcome_implied - margin / n;
+            }
+            DevigMethod::Power => {
+                let k = solve_decreasing(
+                    |k| implied.iter().map(|x| x.powf(k)).sum::<f64>() - 1.0,
+                    1e-6,
+                    100.0,
+                );
+                return outcome_implied.powf(k);
+            }
+            DevigMethod::Shin => {
+                // Shin's z is only defined for a positive overround
+                if (margin <= 0.0) {
+                    return DevigMethod::Multiplicative.true_probability(odds, all_odds);
+                }
+
+                let z = solve_decreasing(
+                    |z| {
+                        implied
+                            .iter()
+                            .map(|x| shin_probability(*x, total_implied, z))
+                            .sum::<f64>()
+                            - 1.0
+                    },
+                    0.0,
+                    1.0 - 1e-9,
+                );
+                return shin_probability(outcome_implied, total_implied, z);
+            }
+            DevigMethod::OddsRatio => {
+                let ratio = solve_decreasing(
+                    |c| {
+                        implied
+                            .iter()
+                            .map(|x| odds_ratio_probability(*x, c))
+                            .sum::<f64>()
+                            - 1.0
+                    },
+                    1e-6,
+                    1e6,
+                );
+                return odds_ratio_probability(outcome_implied, ratio);
+            }
+            DevigMethod::Logarithmic => {
+                let total_log_odds: f64 = all_odds.iter().map(|x| x.get_decimal().ln()).sum();
+                if (total_log_odds <= 0.0) {
+                    return DevigMethod::Additive.true_probability(odds, all_odds);
+                }
+
+                let weight = odds.get_decimal().ln() / total_log_odds;
+                return outcome_implied - margin * weight;
+            }
+        }
+    }
+}
+
+/// Chooses a de-vig method for a market, falling back from the most
+/// specific override (sport and market) to the default.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DevigSettings {
+    pub default_method: DevigMethod,
+    pub sport_methods: HashMap<String, DevigMethod>,
+    pub market_methods: HashMap<MarketType, DevigMethod>,
+    pub sport_market_methods: HashMap<String, HashMap<MarketType, DevigMethod>>,
+}
+
+impl Default for DevigSettings {
+    fn default() -> Self {
+        let mut settings = DevigSettings::with_method(DevigMethod::Additive);
+        // additive takes as much off a 200/1 shot as the favourite, which
+        // leaves long shots in big fields with negative probabilities
+        settings
+            .market_methods
+            .insert(MarketType::Outrights, DevigMethod::Shin);
+        return settings;
+    }
+}
+
+impl DevigSettings {
+    pub fn with_method(method: DevigMethod) -> Self {
+        return DevigSettings {
+            default_method: method,
+            sport_methods: HashMap::new(),
+            market_methods: HashMap::new(),
+            sport_market_methods: HashMap::new(),
+        };
+    }
+
+    pub fn method_for(&self, sport_key: &str, market: &MarketType) -> DevigMethod {
+        if let Some(method) = self
+            .sport_market_methods
+            .get(sport_key)
+            .and_then(|x| x.get(market))
+        {
+            return *method;
+        }
+
+        if let Some(method) = self.sport_methods.get(sport_key) {
+            return *method;
+        }
+
+        if let Some(method) = self.market_methods.get(market) {
+            return *method;
+        }
+
+        return self.default_method;
+    }
+}
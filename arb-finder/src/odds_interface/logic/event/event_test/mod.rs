@@ -1,11 +1,18 @@
-use std::fs;
+use std::{collections::HashMap, fs};
+
+use chrono::{DateTime, Duration, Utc};
 
 use serde::de::Unexpected;
 
 use crate::odds_interface::logic::{
-    event::Event,
+    devig::DevigMethod,
+    event::{apply_staking, matchup, Event, Opportunity, PricingModel},
     market::{MarketType, Outcome, OVER_OUTCOME, UNDER_OUTCOME},
-    odds::Odds,
+    movement::MoveDirection,
+    odds::{Odds, OddsFormat},
+    settings::ScanSettings,
+    staking::{kelly_fraction, StakingSettings, StakingStrategy},
+    weights::BookmakerWeights,
 };
 
 /// ### Input data:
@@ -39,6 +46,39 @@ fn get_kbo_event() -> Event {
     return serde_json::from_str::<Event>(&raw_file_string).expect("JSON was not well-formatted");
 }
 
+fn get_afl_exchange_event() -> Event {
+    let raw_file_string = fs::read_to_string(
+        "./src/odds_interface/logic/event/event_test/testdata_afl_exchange.json",
+    )
+    .expect("Unable to read file");
+    return serde_json::from_str::<Event>(&raw_file_string).expect("JSON was not well-formatted");
+}
+
+fn get_equal_weight_settings() -> ScanSettings {
+    return ScanSettings {
+        bookmaker_weights: BookmakerWeights::equal(),
+        ..Default::default()
+    };
+}
+
+/// The AFL event fetched at `fetched_at`, with `bookie_key` last updating
+/// its prices at `last_update`
+fn get_afl_event_fetched_at(fetched_at: &str, bookie_key: &str, last_update: &str) -> Event {
+    let raw_file_string =
+        fs::read_to_string("./src/odds_interface/logic/event/event_test/testdata_afl.json")
+            .expect("Unable to read file");
+    let mut raw: serde_json::Value = serde_json::from_str(&raw_file_string).unwrap();
+    for bookie in raw["bookmakers"].as_array_mut().unwrap() {
+        if (bookie["key"] == bookie_key) {
+            bookie["last_update"] = serde_json::Value::from(last_update);
+        }
+    }
+
+    let mut event = serde_json::from_value::<Event>(raw).unwrap();
+    event.set_fetched_at(fetched_at.parse::<DateTime<Utc>>().unwrap());
+    return event;
+}
+
 impl Event {
     fn update_line(&mut self, bookie_key: &str, new_line: f64) {
         let bookie = self
@@ -64,6 +104,30 @@ impl Event {
             .collect();
     }
 
+    /// Moves `bookie_key`'s handicap to `home_point`, and the away team's to
+    /// the opposite
+    fn update_spread(&mut self, bookie_key: &str, home_point: f64) {
+        let home_team = self.home_team.clone();
+        let bookie = self
+            .bookmakers
+            .iter_mut()
+            .find(|x| x.key == bookie_key)
+            .unwrap();
+
+        let market = bookie
+            .markets
+            .iter_mut()
+            .find(|x| x.key == MarketType::Spreads)
+            .unwrap();
+
+        for outcome in market.outcomes.iter_mut() {
+            outcome.point = match outcome.name == home_team {
+                true => Some(home_point),
+                false => Some(-home_point),
+            };
+        }
+    }
+
     fn update_odds(
         &mut self,
         bookie_key: &str,
@@ -146,8 +210,10 @@ fn test_get_totals_opportunities_high_score() {
 
 #[test]
 fn test_get_totals_opportunities_simple() {
+    // these check the totals line logic, not bookmaker sharpness
+    let settings = get_equal_weight_settings();
     let mut event = get_kbo_event();
-    let opportunities = event.identify_opportunities();
+    let opportunities = event.identify_opportunities_using(&settings);
 
     // NO OPPORTUNITIES
     assert_eq!(opportunities.len(), 0);
@@ -156,7 +222,7 @@ fn test_get_totals_opportunities_simple() {
     event.update_odds("fanduel", &MarketType::Totals, OVER_OUTCOME, 1.6);
     event.update_odds("fanduel", &MarketType::Totals, UNDER_OUTCOME, 2.2);
 
-    let opportunities = event.identify_opportunities();
+    let opportunities = event.identify_opportunities_using(&settings);
 
     assert_eq!(opportunities.len(), 1);
     let first_opp = opportunities.first().unwrap();
@@ -166,8 +232,10 @@ fn test_get_totals_opportunities_simple() {
 
 #[test]
 fn test_get_totals_opportunities_low_score() {
+    // these check the totals line logic, not bookmaker sharpness
+    let settings = get_equal_weight_settings();
     let mut event = get_kbo_event();
-    let opportunities = event.identify_opportunities();
+    let opportunities = event.identify_opportunities_using(&settings);
 
     // NO OPPORTUNITIES
     assert_eq!(opportunities.len(), 0);
@@ -177,7 +245,7 @@ fn test_get_totals_opportunities_low_score() {
     event.update_line("bovada", 7.5);
     event.update_line("coolbet", 7.5);
 
-    let opportunities = event.identify_opportunities();
+    let opportunities = event.identify_opportunities_using(&settings);
 
     assert_eq!(opportunities.len(), 1);
     let first_opp = opportunities.first().unwrap();
@@ -188,10 +256,447 @@ fn test_get_totals_opportunities_low_score() {
     event.update_line("bovada", 10.5);
     event.update_line("coolbet", 10.5);
 
-    let opportunities = event.identify_opportunities();
+    let opportunities = event.identify_opportunities_using(&settings);
 
     assert_eq!(opportunities.len(), 1);
     let first_opp = opportunities.first().unwrap();
     assert_eq!(first_opp.bookie_name, "FanDuel");
     assert_eq!(first_opp.outcome_key, OVER_OUTCOME);
 }
+
+#[test]
+fn test_get_spreads_opportunities_different_points() {
+    let mut event = get_afl_event();
+
+    // the other books all think the lions win by a lot more
+    event.update_spread("tab", 21.5);
+    event.update_spread("unibet", 21.5);
+    event.update_spread("pointsbetau", 21.5);
+
+    let opportunities: Vec<Opportunity> = event
+        .identify_opportunities()
+        .into_iter()
+        .filter(|x| x.market_key == MarketType::Spreads)
+        .collect();
+
+    assert_eq!(opportunities.len(), 1);
+    let first_opp = opportunities.first().unwrap();
+    assert_eq!(first_opp.bookie_name, "SportsBet");
+    assert_eq!(first_opp.outcome_key, "Brisbane Lions");
+    // lions by 18 on average across the four books
+    assert!(first_opp.message.contains("Brisbane Lions -18.0"));
+}
+
+#[test]
+fn test_find_h2h_arbitrage() {
+    let mut event = get_afl_event();
+    assert_eq!(
+        event.find_arbitrages(100.0, &ScanSettings::default()).len(),
+        0
+    );
+
+    // best lions price at tab, best saints price stays at 2.3 everywhere
+    event.update_odds("tab", &MarketType::H2h, "Brisbane Lions", 1.9);
+
+    let arbitrages = event.find_arbitrages(100.0, &ScanSettings::default());
+    assert_eq!(arbitrages.len(), 1);
+
+    let arb = arbitrages.first().unwrap();
+    assert_eq!(arb.market_key, MarketType::H2h);
+    assert_eq!(arb.legs.len(), 2);
+    assert_eq!(arb.matchup(), "St Kilda Saints vs Brisbane Lions");
+    assert_eq!(matchup("", ""), "outright");
+
+    let expected_margin = 100.0 * (1.0 / (1.0 / 1.9 + 1.0 / 2.3) - 1.0);
+    assert!((arb.profit_margin - expected_margin).abs() < 1e-9);
+
+    // every leg pays out the same and the stakes use the whole bankroll
+    let total_stake: f64 = arb.legs.iter().map(|x| x.stake).sum();
+    assert!((total_stake - 100.0).abs() < 1e-9);
+    for leg in &arb.legs {
+        let payout = leg.stake * leg.odds.get_decimal();
+        assert!((payout - (100.0 + arb.guaranteed_profit)).abs() < 1e-9);
+    }
+
+    let lions_leg = arb
+        .legs
+        .iter()
+        .find(|x| x.outcome_key == "Brisbane Lions")
+        .unwrap();
+    assert_eq!(lions_leg.bookie_name, "TAB");
+}
+
+#[test]
+fn test_find_totals_arbitrage_needs_matching_points() {
+    let mut event = get_kbo_event();
+    event.update_odds("fanduel", &MarketType::Totals, OVER_OUTCOME, 2.05);
+    event.update_odds("draftkings", &MarketType::Totals, UNDER_OUTCOME, 2.05);
+
+    let arbitrages = event.find_arbitrages(100.0, &ScanSettings::default());
+    assert_eq!(arbitrages.len(), 1);
+    assert_eq!(arbitrages.first().unwrap().market_key, MarketType::Totals);
+
+    // the best over is now at a different line to the best under
+    event.update_line("fanduel", 10.5);
+    assert_eq!(
+        event.find_arbitrages(100.0, &ScanSettings::default()).len(),
+        0
+    );
+}
+
+#[test]
+fn test_apply_staking() {
+    let mut event = get_afl_event();
+    event.update_odds("tab", &MarketType::H2h, "St Kilda Saints", 1.9);
+    event.update_odds("tab", &MarketType::H2h, "Brisbane Lions", 1.9);
+    event.update_odds("unibet", &MarketType::H2h, "St Kilda Saints", 1.9);
+    event.update_odds("unibet", &MarketType::H2h, "Brisbane Lions", 1.9);
+    event.update_odds("pointsbetau", &MarketType::H2h, "St Kilda Saints", 1.9);
+    event.update_odds("pointsbetau", &MarketType::H2h, "Brisbane Lions", 1.9);
+
+    let mut opportunities = event.identify_opportunities();
+    let staking = StakingSettings {
+        bankroll: 1000.0,
+        strategy: StakingStrategy::FullKelly,
+    };
+    apply_staking(&mut opportunities, &staking);
+
+    let opportunity = opportunities.first().unwrap();
+    let stake = opportunity.recommended_stake().unwrap();
+    let expected = 1000.0 * kelly_fraction(&opportunity.as_kelly_bet());
+    assert!(stake > 0.0);
+    assert!((stake - expected).abs() < 1e-9);
+    assert!(opportunity.to_string().contains("Recommended stake"));
+}
+
+/// An opportunity on the AFL event at `offered` against fair odds of 2.0
+fn get_opportunity(
+    bookie_key: &str,
+    market_key: MarketType,
+    outcome_key: &str,
+    point: Option<f64>,
+    offered: f64,
+) -> Opportunity {
+    return Opportunity {
+        event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+        sport_key: String::from("aussierules_afl"),
+        commence_time: get_afl_event().commence_time(),
+        bookie_key: bookie_key.to_string(),
+        bookie_name: bookie_key.to_string(),
+        offered_odds: Odds::Decimal(offered),
+        sport_title: String::from("AFL"),
+        home_team: String::from("St Kilda Saints"),
+        away_team: String::from("Brisbane Lions"),
+        true_odds: Odds::Decimal(2.0),
+        message: String::new(),
+        outcome_key: outcome_key.to_string(),
+        point,
+        market_key,
+        percent_ev: (offered / 2.0 - 1.0) * 100.0,
+        devig_method: DevigMethod::Multiplicative,
+        model: PricingModel::Consensus,
+        recommended_stake: None,
+        quote_age: None,
+    };
+}
+
+#[test]
+fn test_apply_staking_by_line() {
+    let staking = StakingSettings {
+        bankroll: 1000.0,
+        strategy: StakingStrategy::FullKelly,
+    };
+    let totals = MarketType::Totals;
+    // Over 166.5 and Under 170.5 can both win, so they aren't sized as one
+    // set of outcomes
+    let mut opportunities = vec![
+        get_opportunity("tab", totals.clone(), OVER_OUTCOME, Some(166.5), 2.2),
+        get_opportunity("unibet", totals.clone(), UNDER_OUTCOME, Some(170.5), 2.2),
+        get_opportunity("sportsbet", totals.clone(), OVER_OUTCOME, Some(166.5), 2.1),
+    ];
+    apply_staking(&mut opportunities, &staking);
+
+    // each line is staked alone, then both are scaled back to the larger
+    let alone = 1000.0 * kelly_fraction(&opportunities[0].as_kelly_bet());
+    for opportunity in &opportunities[..2] {
+        let stake = opportunity.recommended_stake().unwrap();
+        assert!((stake - alone / 2.0).abs() < 1e-9);
+    }
+    // the same outcome at a worse price isn't backed
+    assert_eq!(opportunities[2].recommended_stake(), None);
+    assert!(!opportunities[2].to_string().contains("Recommended stake"));
+
+    // both sides of a spread are on the same line
+    let home = get_opportunity(
+        "tab",
+        MarketType::Spreads,
+        "St Kilda Saints",
+        Some(7.5),
+        2.2,
+    );
+    let away = get_opportunity(
+        "tab",
+        MarketType::Spreads,
+        "Brisbane Lions",
+        Some(-7.5),
+        2.2,
+    );
+    let other = get_opportunity("tab", MarketType::Spreads, "Brisbane Lions", Some(7.5), 2.2);
+    assert_eq!(home.line(), away.line());
+    assert_ne!(away.line(), other.line());
+}
+
+#[test]
+fn test_exchange_mid_price_anchors_true_odds() {
+    let mut event = get_afl_exchange_event();
+    event.update_odds("sportsbet", &MarketType::H2h, "St Kilda Saints", 2.65);
+
+    let opportunities =
+        event.identify_opportunities_in_market(&MarketType::H2h, &ScanSettings::default());
+    let opportunity = opportunities
+        .iter()
+        .find(|x| x.bookie_name == "SportsBet")
+        .unwrap();
+
+    // middle of back and lay for both teams, normalised
+    let lions_mid = (1.0 / 1.66 + 1.0 / 1.7) / 2.0;
+    let saints_mid = (1.0 / 2.44 + 1.0 / 2.52) / 2.0;
+    let expected = (lions_mid + saints_mid) / saints_mid;
+    assert_eq!(opportunity.outcome_key, "St Kilda Saints");
+    assert!((opportunity.true_odds.get_decimal() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn test_exchange_back_price_is_commission_adjusted() {
+    let mut event = get_afl_exchange_event();
+    event.update_odds("betfair_ex_au", &MarketType::H2h, "St Kilda Saints", 2.9);
+    event.update_odds("betfair_ex_au", &MarketType::H2hLay, "St Kilda Saints", 3.0);
+
+    let mut settings = ScanSettings::default();
+    settings.exchanges.anchor_to_lay = false;
+    let opportunities = event.identify_opportunities_in_market(&MarketType::H2h, &settings);
+    let opportunity = opportunities
+        .iter()
+        .find(|x| x.bookie_name == "Betfair")
+        .unwrap();
+
+    // 5% commission comes off the 1.9 of winnings
+    assert!((opportunity.offered_odds.get_decimal() - 2.805).abs() < 1e-9);
+}
+
+#[test]
+fn test_find_matched_bets() {
+    let mut event = get_afl_exchange_event();
+    event.update_odds("sportsbet", &MarketType::H2h, "Brisbane Lions", 1.68);
+
+    // everything else loses more than the default 5% qualifying loss
+    let matched_bets = event.find_matched_bets(10.0, &ScanSettings::default());
+    assert_eq!(matched_bets.len(), 1);
+
+    let matched_bet = matched_bets.first().unwrap();
+    assert_eq!(matched_bet.bookie_name, "SportsBet");
+    assert_eq!(matched_bet.exchange_name, "Betfair");
+    assert_eq!(matched_bet.outcome_key, "Brisbane Lions");
+
+    let lay_stake = 10.0 * 1.68 / (1.7 - 0.05);
+    assert!((matched_bet.lay_stake - lay_stake).abs() < 1e-9);
+    assert!((matched_bet.liability - lay_stake * 0.7).abs() < 1e-9);
+    assert!((matched_bet.profit - (lay_stake * 0.95 - 10.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_consensus_only_counts_quoting_bookies_by_weight() {
+    let mut event = get_afl_event();
+    for bookie in event.bookmakers.iter_mut() {
+        if (bookie.key == "unibet" || bookie.key == "pointsbetau") {
+            bookie.markets.retain(|x| x.key != MarketType::H2h);
+        }
+    }
+    event.update_odds("sportsbet", &MarketType::H2h, "St Kilda Saints", 2.9);
+
+    let method = DevigMethod::Additive;
+    let saints_probability = |saints: f64| {
+        let all_odds = [Odds::Decimal(1.62), Odds::Decimal(saints)];
+        return method.true_probability(&all_odds[1], &all_odds);
+    };
+    let sportsbet_probability = saints_probability(2.9);
+    let tab_probability = saints_probability(2.3);
+
+    let mut settings = ScanSettings::default();
+    let find_true_odds = |settings: &ScanSettings| {
+        let opportunities = event.identify_opportunities_in_market(&MarketType::H2h, settings);
+        let opportunity = opportunities
+            .iter()
+            .find(|x| x.bookie_name == "SportsBet")
+            .unwrap();
+        return opportunity.true_odds.get_decimal();
+    };
+
+    let expected = 2.0 / (sportsbet_probability + tab_probability);
+    assert!((find_true_odds(&settings) - expected).abs() < 1e-9);
+
+    settings.bookmaker_weights.sport_weights.insert(
+        String::from("aussierules_afl"),
+        HashMap::from([(String::from("tab"), 3.0)]),
+    );
+    let expected = 4.0 / (sportsbet_probability + 3.0 * tab_probability);
+    assert!((find_true_odds(&settings) - expected).abs() < 1e-9);
+}
+
+#[test]
+fn test_find_steam_moves() {
+    let previous = get_afl_event();
+    let mut event = previous.clone();
+    for bookie_key in ["tab", "unibet", "pointsbetau"] {
+        event.update_odds(bookie_key, &MarketType::H2h, "St Kilda Saints", 2.0);
+        event.update_odds(bookie_key, &MarketType::H2h, "Brisbane Lions", 1.8);
+    }
+
+    let settings = ScanSettings::default();
+    let steam_moves = event.find_steam_moves(&previous, Duration::minutes(5), &settings);
+    // the lions drifting is the same move, so only the saints are reported
+    assert_eq!(steam_moves.len(), 1);
+
+    let saints = &steam_moves[0];
+    assert_eq!(saints.outcome_key, "St Kilda Saints");
+    assert_eq!(saints.point, None);
+    assert_eq!(saints.direction, MoveDirection::Shortening);
+    assert_eq!(saints.books_moved.len(), 3);
+    assert!(saints.post_move_fair < saints.pre_move_fair);
+
+    // sportsbet is still at 2.3, well over the new fair price
+    let method = DevigMethod::Additive;
+    let moved_odds = [Odds::Decimal(1.8), Odds::Decimal(2.0)];
+    let post_move_fair = 1.0 / method.true_probability(&moved_odds[1], &moved_odds);
+    assert!((saints.post_move_fair.get_decimal() - post_move_fair).abs() < 1e-9);
+    assert_eq!(saints.stale_quotes.len(), 1);
+    assert_eq!(saints.stale_quotes[0].bookie_name, "SportsBet");
+    assert!(saints
+        .format_message(&OddsFormat::Decimal)
+        .contains("Stale: SportsBet"));
+
+    // too slow to be steam
+    assert!(event
+        .find_steam_moves(&previous, Duration::minutes(30), &settings)
+        .is_empty());
+
+    // unless the books that moved repriced within the window
+    let mut event =
+        get_afl_event_fetched_at("2024-08-04T05:20:00Z", "sportsbet", "2024-08-04T04:00:00Z");
+    for bookie_key in ["tab", "unibet", "pointsbetau"] {
+        event.update_odds(bookie_key, &MarketType::H2h, "St Kilda Saints", 2.0);
+        event.update_odds(bookie_key, &MarketType::H2h, "Brisbane Lions", 1.8);
+    }
+    let steam_moves = event.find_steam_moves(&previous, Duration::minutes(60), &settings);
+    assert_eq!(steam_moves.len(), 1);
+    assert_eq!(steam_moves[0].outcome_key, "St Kilda Saints");
+}
+
+#[test]
+fn test_steam_moves_compare_the_same_line() {
+    let mut previous = get_afl_event();
+    previous.update_line("sportsbet", 170.5);
+    let mut event = previous.clone();
+    for bookie_key in ["tab", "unibet", "pointsbetau"] {
+        event.update_odds(bookie_key, &MarketType::Totals, "Over", 1.7);
+        event.update_odds(bookie_key, &MarketType::Totals, "Under", 2.1);
+    }
+
+    let settings = ScanSettings::default();
+    let steam_moves = event.find_steam_moves(&previous, Duration::minutes(5), &settings);
+    assert_eq!(steam_moves.len(), 1);
+
+    // sportsbet is on another line, so it hasn't missed the move
+    let over = &steam_moves[0];
+    assert_eq!(over.outcome_key, "Over");
+    assert_eq!(over.point, Some(166.5));
+    assert_eq!(over.direction, MoveDirection::Shortening);
+    assert_eq!(over.books_moved.len(), 3);
+    assert!(over.stale_quotes.is_empty());
+    assert!(over
+        .format_message(&OddsFormat::Decimal)
+        .contains("Outcome: Over 166.5 (shortening)"));
+}
+
+#[test]
+fn test_stale_quotes_are_excluded() {
+    let mut event =
+        get_afl_event_fetched_at("2024-08-04T05:20:00Z", "sportsbet", "2024-08-04T04:00:00Z");
+    event.update_odds("sportsbet", &MarketType::H2h, "St Kilda Saints", 2.9);
+    event.update_odds("tab", &MarketType::H2h, "Brisbane Lions", 1.9);
+
+    let mut settings = ScanSettings::default();
+    let opportunities = event.identify_opportunities_in_market(&MarketType::H2h, &settings);
+    assert!(opportunities.iter().all(|x| x.bookie_name != "SportsBet"));
+
+    // tab is still fresh, and says how fresh
+    let tab = opportunities
+        .iter()
+        .find(|x| x.bookie_name == "TAB")
+        .unwrap();
+    assert_eq!(tab.quote_age, Some(Duration::seconds(4 * 60 + 24)));
+    assert!(tab.to_string().contains("Quote age: 4 minutes"));
+
+    settings.max_quote_age_minutes = 0.0;
+    let opportunities = event.identify_opportunities_in_market(&MarketType::H2h, &settings);
+    let sportsbet = opportunities
+        .iter()
+        .find(|x| x.bookie_name == "SportsBet")
+        .unwrap();
+    assert!(sportsbet.to_string().contains("Quote age: 80 minutes"));
+}
+
+#[test]
+fn test_fair_odds_match_the_finders() {
+    let settings = get_equal_weight_settings();
+    let assert_fair_odds_match = |event: &Event| {
+        let opportunities = event.identify_opportunities_using(&settings);
+        assert!(!opportunities.is_empty());
+        for opportunity in opportunities {
+            let fair_odds = event
+                .fair_odds_for(
+                    &opportunity.market_key,
+                    &opportunity.outcome_key,
+                    opportunity.point,
+                    &settings,
+                )
+                .unwrap();
+            assert!((fair_odds.get_decimal() - opportunity.true_odds.get_decimal()).abs() < 1e-9);
+        }
+    };
+
+    // naive h2h consensus
+    let mut event = get_afl_event();
+    for bookie_key in ["tab", "unibet", "pointsbetau"] {
+        event.update_odds(bookie_key, &MarketType::H2h, "St Kilda Saints", 1.9);
+        event.update_odds(bookie_key, &MarketType::H2h, "Brisbane Lions", 1.9);
+    }
+    assert_fair_odds_match(&event);
+
+    // margin model for spreads on different points
+    let mut event = get_afl_event();
+    for bookie_key in ["tab", "unibet", "pointsbetau"] {
+        event.update_spread(bookie_key, 21.5);
+    }
+    assert_fair_odds_match(&event);
+
+    // poisson totals on different lines
+    let mut event = get_kbo_event();
+    for bookie_key in ["draftkings", "bovada", "coolbet"] {
+        event.update_line(bookie_key, 7.5);
+    }
+    assert_fair_odds_match(&event);
+
+    // once every book agrees on a line the naive consensus prices it
+    let event = get_kbo_event();
+    let over = event
+        .fair_odds_for(&MarketType::Totals, OVER_OUTCOME, Some(9.5), &settings)
+        .unwrap();
+    assert!((over.get_decimal() - 2.0).abs() < 1e-9);
+    assert!(event
+        .fair_odds_for(&MarketType::Totals, OVER_OUTCOME, Some(8.5), &settings)
+        .is_some());
+    assert!(event
+        .fair_odds_for(&MarketType::H2h, "Carlton Blues", None, &settings)
+        .is_none());
+}
@@ -2,23 +2,31 @@ use core::num;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
 use statrs::distribution::{ContinuousCDF, DiscreteCDF, Normal, Poisson};
 use strum::IntoEnumIterator;
+use strum_macros::EnumString;
 
 use super::market::{OVER_OUTCOME, UNDER_OUTCOME};
 
 mod event_test;
 
+use super::alerts::{AlertRecord, OpportunityKey};
+use super::arbitrage::{Arbitrage, BestPrice};
 use super::bookmaker::Bookmaker;
+use super::clv::TrackedOpportunity;
+use super::devig::DevigMethod;
+use super::exchange::MatchedBet;
+use super::ledger::{Bet, BetStatus};
 use super::market::MarketType;
-use super::odds::Odds;
-use super::{AUS_ONLY, AU_BOOKS};
-
-// TODO: pass these as parameters
-const MAX_ODDS_CUTOFF: f64 = 10.0;
-const PERCENT_EV_CUTOFF: f64 = 5.0;
+use super::movement::{MoveDirection, StaleQuote, SteamMove};
+use super::odds::{Odds, OddsFormat};
+use super::outrights::consensus_runners;
+use super::settings::ScanSettings;
+use super::spreads::MarginModel;
+use super::staking::{simultaneous_kelly_fractions, KellyBet, StakingSettings};
+use super::weights::BookmakerWeights;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Event {
@@ -26,12 +34,58 @@ pub struct Event {
     sport_key: String,
     sport_title: String,
     commence_time: DateTime<Utc>,
+    /// Empty for outright events, which the api sends with null teams
+    #[serde(default, deserialize_with = "null_as_empty")]
     home_team: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     away_team: String,
+    /// Missing from the `/events` endpoint, which doesn't return odds
+    #[serde(default)]
     bookmakers: Vec<Bookmaker>,
+    /// When the odds were fetched, if known, to judge how stale each
+    /// bookmaker's quotes are
+    #[serde(skip)]
+    fetched_at: Option<DateTime<Utc>>,
+}
+
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let team: Option<String> = Deserialize::deserialize(deserializer)?;
+    return Ok(team.unwrap_or_default());
+}
+
+/// How the true odds of an opportunity were worked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum PricingModel {
+    /// Weighted average of the de-vigged prices of every book
+    Consensus,
+    /// Middle of exchange back and lay prices
+    ExchangeMid,
+    /// Poisson distribution fitted to totals quoted on different lines
+    PoissonTotals,
+    /// Margin distribution fitted to spreads quoted on different lines
+    MarginModel,
+    /// Consensus of every book listing a futures runner
+    OutrightConsensus,
+}
+
+impl fmt::Display for PricingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PricingModel::Consensus => write!(f, "consensus"),
+            PricingModel::ExchangeMid => write!(f, "exchange_mid"),
+            PricingModel::PoissonTotals => write!(f, "poisson_totals"),
+            PricingModel::MarginModel => write!(f, "margin_model"),
+            PricingModel::OutrightConsensus => write!(f, "outright_consensus"),
+        }
+    }
 }
 
 pub struct Opportunity {
+    event_id: String,
+    sport_key: String,
+    commence_time: DateTime<Utc>,
+    bookie_key: String,
     bookie_name: String,
     offered_odds: Odds,
     sport_title: String,
@@ -40,38 +94,297 @@ pub struct Opportunity {
     true_odds: Odds,
     message: String,
     outcome_key: String,
+    /// Line for totals and spreads
+    point: Option<f64>,
     market_key: MarketType,
     percent_ev: f64,
+    devig_method: DevigMethod,
+    model: PricingModel,
+    recommended_stake: Option<f64>,
+    /// How long before the fetch the bookie last updated its prices
+    quote_age: Option<Duration>,
 }
 
-impl fmt::Display for Opportunity {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let markdown_str = format!(
+/// "home vs away", or the outright market for events without teams
+pub fn matchup(home_team: &str, away_team: &str) -> String {
+    if (home_team.is_empty() && away_team.is_empty()) {
+        return String::from("outright");
+    }
+    return format!("{home_team} vs {away_team}");
+}
+
+impl Opportunity {
+    pub fn matchup(&self) -> String {
+        return matchup(&self.home_team, &self.away_team);
+    }
+
+    /// Markdown summary for discord, with prices written in `odds_format`
+    pub fn format_message(&self, odds_format: &OddsFormat) -> String {
+        let mut markdown_str = format!(
             " ### Opportunity found on {0}
-    - {1:.2}%: {2} vs {3} at {4}
-    - Outcome: {5}
-    - Market: {6}
-    - Offered odds: {7}
-    - True odds: {8}
-    - Explanation: {9}
+    - {1:.2}%: {2} at {3}
+    - Outcome: {4}
+    - Market: {5}
+    - Offered odds: {6}
+    - True odds: {7} ({9} de-vig)
+    - Explanation: {8}
 ",
             self.sport_title,
             self.percent_ev,
-            self.home_team,
-            self.away_team,
+            self.matchup(),
             self.bookie_name,
             self.outcome_key,
             self.market_key,
-            self.offered_odds.get_decimal(),
-            self.true_odds.get_decimal(),
+            self.offered_odds.display_as(odds_format),
+            self.true_odds.display_as(odds_format),
             self.message,
+            self.devig_method,
         );
 
-        return write!(f, "{}", markdown_str);
+        if let Some(age) = self.quote_age {
+            markdown_str += &format!("    - Quote age: {0} minutes\n", age.num_minutes());
+        }
+
+        if let Some(stake) = self.recommended_stake {
+            markdown_str += &format!("    - Recommended stake: {stake:.2}\n");
+        }
+
+        return markdown_str;
+    }
+
+    pub fn recommended_stake(&self) -> Option<f64> {
+        return self.recommended_stake;
+    }
+
+    pub fn key(&self) -> OpportunityKey {
+        return OpportunityKey {
+            event_id: self.event_id.clone(),
+            market_key: self.market_key.clone(),
+            outcome_key: self.outcome_key.clone(),
+            point: self.point,
+            bookmaker_key: self.bookie_key.clone(),
+            direction: None,
+        };
+    }
+
+    pub fn percent_ev(&self) -> f64 {
+        return self.percent_ev;
+    }
+
+    /// The record of alerting this opportunity at `alerted_at`
+    pub fn alert(&self, alerted_at: DateTime<Utc>) -> AlertRecord {
+        return AlertRecord {
+            key: self.key(),
+            sport_key: self.sport_key.clone(),
+            commence_time: self.commence_time,
+            matchup: self.matchup(),
+            offered_odds: self.offered_odds,
+            percent_ev: self.percent_ev,
+            alerted_at,
+            gone_at: None,
+        };
+    }
+
+    /// The opportunity as first seen at `found_at`, waiting on its closing
+    /// odds
+    pub fn track(&self, found_at: DateTime<Utc>) -> TrackedOpportunity {
+        return TrackedOpportunity {
+            id: None,
+            event_id: self.event_id.clone(),
+            sport_key: self.sport_key.clone(),
+            commence_time: self.commence_time,
+            market_key: self.market_key.clone(),
+            outcome_key: self.outcome_key.clone(),
+            point: self.point,
+            bookmaker_key: self.bookie_key.clone(),
+            model: self.model,
+            devig_method: self.devig_method,
+            offered_odds: self.offered_odds,
+            true_odds: self.true_odds,
+            percent_ev: self.percent_ev,
+            found_at,
+            closing_odds: None,
+            closed_at: None,
+        };
+    }
+
+    /// A bet of `stake` at the offered odds, as if placed at `placed_at`
+    pub fn as_bet(&self, stake: f64, placed_at: DateTime<Utc>) -> Bet {
+        return Bet {
+            id: None,
+            event_id: self.event_id.clone(),
+            sport_key: self.sport_key.clone(),
+            home_team: self.home_team.clone(),
+            away_team: self.away_team.clone(),
+            commence_time: self.commence_time,
+            market_key: self.market_key.clone(),
+            outcome_key: self.outcome_key.clone(),
+            point: self.point,
+            bookmaker_key: self.bookie_key.clone(),
+            odds: self.offered_odds,
+            stake,
+            placed_at,
+            status: BetStatus::Pending,
+            settled_at: None,
+        };
+    }
+
+    /// The line the opportunity is on, the same for every outcome that
+    /// settles against it: the total, or the home team's spread
+    fn line(&self) -> Option<f64> {
+        if (self.market_key == MarketType::Spreads && self.outcome_key == self.away_team) {
+            return self.point.map(|x| -x);
+        }
+        return self.point;
+    }
+
+    fn as_kelly_bet(&self) -> KellyBet {
+        return KellyBet {
+            probability: self.true_odds.implied_probability(),
+            decimal_odds: self.offered_odds.get_decimal(),
+        };
+    }
+}
+
+/// Sets the recommended stake on opportunities found on the same event.
+///
+/// Outcomes of one line of a market (both sides of a total or a spread, or
+/// every team in h2h) are mutually exclusive, so they are sized together
+/// with simultaneous Kelly, only backing the best price for each outcome.
+/// Prices that aren't backed are left without a stake. Bets on different
+/// lines or markets of the event are correlated in ways we don't model, so
+/// if together they add up to more than the largest single line they are
+/// scaled back down to it.
+pub fn apply_staking(opportunities: &mut [Opportunity], staking: &StakingSettings) {
+    let mut lines: Vec<(MarketType, Option<f64>)> = Vec::new();
+    for opportunity in opportunities.iter() {
+        let line = (opportunity.market_key.clone(), opportunity.line());
+        if (!lines.contains(&line)) {
+            lines.push(line);
+        }
+    }
+
+    let mut kelly_fractions = vec![0.0; opportunities.len()];
+    let mut backed = vec![false; opportunities.len()];
+    let mut line_totals: Vec<f64> = Vec::new();
+
+    for (market, line) in &lines {
+        // best priced opportunity for each outcome of the line
+        let mut best_for_outcome: Vec<usize> = Vec::new();
+        for (i, opportunity) in opportunities.iter().enumerate() {
+            if (opportunity.market_key != *market || opportunity.line() != *line) {
+                continue;
+            }
+
+            let current = best_for_outcome.iter().position(|x| {
+                return opportunities[*x].outcome_key == opportunity.outcome_key
+                    && opportunities[*x].point == opportunity.point;
+            });
+            match current {
+                Some(j) => {
+                    if (opportunity.offered_odds > opportunities[best_for_outcome[j]].offered_odds)
+                    {
+                        best_for_outcome[j] = i;
+                    }
+                }
+                None => best_for_outcome.push(i),
+            }
+        }
+
+        let bets: Vec<KellyBet> = best_for_outcome
+            .iter()
+            .map(|i| opportunities[*i].as_kelly_bet())
+            .collect();
+        let fractions = simultaneous_kelly_fractions(&bets);
+
+        for (i, fraction) in best_for_outcome.iter().zip(&fractions) {
+            kelly_fractions[*i] = *fraction;
+            backed[*i] = true;
+        }
+        line_totals.push(fractions.iter().sum());
+    }
+
+    let event_total: f64 = line_totals.iter().sum();
+    let largest_line = line_totals.iter().cloned().fold(0.0, f64::max);
+    if (event_total > largest_line && event_total > 0.0) {
+        let scale = largest_line / event_total;
+        kelly_fractions = kelly_fractions.iter().map(|x| x * scale).collect();
+    }
+
+    let stakes = staking.stakes_from_kelly(&kelly_fractions);
+    for ((opportunity, stake), backed) in opportunities.iter_mut().zip(stakes).zip(backed) {
+        opportunity.recommended_stake = match backed && stake > 0.0 {
+            true => Some(stake),
+            false => None,
+        };
+    }
+}
+
+impl fmt::Display for Opportunity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.format_message(&OddsFormat::Decimal));
     }
 }
 
 impl Event {
+    pub fn id(&self) -> &str {
+        return &self.id;
+    }
+
+    pub fn sport_key(&self) -> &str {
+        return &self.sport_key;
+    }
+
+    pub fn sport_title(&self) -> &str {
+        return &self.sport_title;
+    }
+
+    pub fn commence_time(&self) -> DateTime<Utc> {
+        return self.commence_time;
+    }
+
+    pub fn home_team(&self) -> &str {
+        return &self.home_team;
+    }
+
+    pub fn away_team(&self) -> &str {
+        return &self.away_team;
+    }
+
+    pub fn bookmakers(&self) -> &[Bookmaker] {
+        return &self.bookmakers;
+    }
+
+    pub fn fetched_at(&self) -> Option<DateTime<Utc>> {
+        return self.fetched_at;
+    }
+
+    pub fn set_fetched_at(&mut self, fetched_at: DateTime<Utc>) {
+        self.fetched_at = Some(fetched_at);
+    }
+
+    /// How long before the fetch `bookie_key` last updated its prices, if
+    /// the fetch time is known
+    pub fn quote_age(&self, bookie_key: &str) -> Option<Duration> {
+        let fetched_at = self.fetched_at?;
+        let bookie = self.bookmakers.iter().find(|x| x.key == bookie_key)?;
+        return Some((fetched_at - bookie.last_update()).max(Duration::zero()));
+    }
+
+    /// Bookies whose quotes are recent enough to trust, for both the
+    /// consensus and alerts
+    fn get_fresh_bookies(&self, settings: &ScanSettings) -> Vec<Bookmaker> {
+        return self
+            .bookmakers
+            .clone()
+            .into_iter()
+            .filter(|x| match self.quote_age(&x.key) {
+                Some(age) => settings.is_fresh(age),
+                None => true,
+            })
+            .collect();
+    }
+
     pub fn get_all_bookies(&self) -> HashSet<String> {
         let mut bookie_name_set = HashSet::new();
         for bookmaker in &self.bookmakers {
@@ -91,7 +404,7 @@ impl Event {
         return (b + term2.sqrt()) / 2.0;
     }
 
-    fn implied_mean_score(&self, bookie: &Bookmaker) -> Option<f64> {
+    fn implied_mean_score(&self, bookie: &Bookmaker, method: &DevigMethod) -> Option<f64> {
         let outcomes = bookie.get_offered_outcomes(&MarketType::Totals);
 
         let both_sides_odds: Vec<Odds> = outcomes.iter().map(|x| x.price).collect();
@@ -105,32 +418,89 @@ impl Event {
         let price = under_outcome.price;
         let point = under_outcome.point.unwrap();
 
-        let implied_prob = price.true_probability_estimate(&both_sides_odds);
+        let implied_prob = price.true_probability_estimate(&both_sides_odds, method);
 
         let lamb_estimate = Self::poisson_rate_estimate(implied_prob, point);
         return Some(lamb_estimate);
     }
 
-    fn get_relevant_bookies(&self) -> Vec<Bookmaker> {
-        if (!AUS_ONLY) {
-            return self.bookmakers.clone();
+    /// Mean total score implied by each of `bookies` quoting totals
+    fn implied_mean_scores(
+        &self,
+        bookies: &[Bookmaker],
+        method: &DevigMethod,
+    ) -> HashMap<String, f64> {
+        let mut lamb_estimates_for_bookies = HashMap::new();
+        for bookie in bookies {
+            let lamb_estimate = self.implied_mean_score(bookie, method);
+            match lamb_estimate {
+                None => continue,
+                Some(x) => lamb_estimates_for_bookies.insert(bookie.key.clone(), x),
+            };
+        }
+        return lamb_estimates_for_bookies;
+    }
+
+    /// Weighted mean of the books' implied mean scores, if at least two
+    /// books quote totals
+    fn consensus_mean_score(
+        &self,
+        lamb_estimates_for_bookies: &HashMap<String, f64>,
+        settings: &ScanSettings,
+    ) -> Option<f64> {
+        let avg_lambda = settings
+            .bookmaker_weights
+            .weighted_mean(
+                &self.sport_key,
+                &MarketType::Totals,
+                lamb_estimates_for_bookies
+                    .iter()
+                    .map(|(bookie_key, rate_estimate)| (bookie_key.as_str(), *rate_estimate)),
+            )
+            .unwrap_or(0.0);
+
+        if (avg_lambda <= 0.0 || lamb_estimates_for_bookies.len() <= 1) {
+            return None;
+        }
+        return Some(avg_lambda);
+    }
+
+    fn poisson_true_odds(avg_lambda: f64, outcome_key: &str, offered_line: f64) -> Odds {
+        // get true odds of this line -- use normal approximation to estimate lambda
+        // but use poisson cdf to calculate
+        let poisson_dist = Poisson::new(avg_lambda).unwrap();
+
+        // round down for under probability
+        let line_rounded = offered_line as u64;
+        let mut true_probability = poisson_dist.cdf(line_rounded);
+
+        if (outcome_key == OVER_OUTCOME) {
+            true_probability = 1.0 - true_probability;
         }
 
+        return Odds::Decimal(1.0 / true_probability);
+    }
+
+    /// Fresh bookies we are able to bet with. Every fresh bookie still
+    /// feeds into the consensus true odds.
+    fn get_relevant_bookies(&self, settings: &ScanSettings) -> Vec<Bookmaker> {
         return self
-            .bookmakers
-            .clone()
+            .get_fresh_bookies(settings)
             .into_iter()
-            .filter(|x| AU_BOOKS.contains(&x.key.as_str()))
+            .filter(|x| settings.bookmakers.allows(&x.key))
             .collect();
     }
 
-    fn identify_totals_opportunities(&self) -> Vec<Opportunity> {
+    fn identify_totals_opportunities(&self, settings: &ScanSettings) -> Vec<Opportunity> {
         let mut opps: Vec<Opportunity> = Vec::new();
+        let devig_method = settings
+            .devig
+            .method_for(&self.sport_key, &MarketType::Totals);
+        let percent_ev_cutoff = settings.percent_ev_cutoff_for(&self.sport_key);
 
-        let mut lamb_estimates_for_bookies = HashMap::new();
-
+        let fresh_bookies = self.get_fresh_bookies(settings);
         let mut lines_set: HashSet<i32> = HashSet::new();
-        for bookie in &self.bookmakers {
+        for bookie in &fresh_bookies {
             let maybe_line = bookie.get_over_under_line();
             if let Some(line) = maybe_line {
                 lines_set.insert(line as i32);
@@ -139,30 +509,18 @@ impl Event {
 
         // if every line is the same then no need to worry
         if (lines_set.len() < 2) {
-            return self.identify_opportunities_naive(&MarketType::Totals);
+            return self.identify_opportunities_naive(&MarketType::Totals, settings);
         }
 
-        for bookie in &self.bookmakers {
-            let lamb_estimate = self.implied_mean_score(bookie);
-            match lamb_estimate {
-                None => continue,
-                Some(x) => lamb_estimates_for_bookies.insert(bookie.key.clone(), x),
-            };
-        }
-
-        let sum_lambda = lamb_estimates_for_bookies
-            .iter()
-            .fold(0.0, |acc, (bookie_key, rate_estimate)| acc + rate_estimate);
+        let lamb_estimates_for_bookies = self.implied_mean_scores(&fresh_bookies, &devig_method);
         let bookies_offering_totals: Vec<String> =
             lamb_estimates_for_bookies.keys().cloned().collect();
-        let num_bookies_offering = bookies_offering_totals.len();
-        let avg_lambda = sum_lambda as f64 / num_bookies_offering as f64;
-
-        if (avg_lambda <= 0.0 || num_bookies_offering <= 1) {
-            return Vec::new();
-        }
+        let avg_lambda = match self.consensus_mean_score(&lamb_estimates_for_bookies, settings) {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
 
-        let event_au_books: Vec<Bookmaker> = self.get_relevant_bookies();
+        let event_au_books: Vec<Bookmaker> = self.get_relevant_bookies(settings);
 
         for bookie in event_au_books {
             if (!bookies_offering_totals.contains(&bookie.key)) {
@@ -173,32 +531,31 @@ impl Event {
             let outcomes = bookie.get_offered_outcomes(&MarketType::Totals);
             for outcome in outcomes {
                 let offered_line = outcome.point.unwrap();
-                let bookie_odds = outcome.price;
-
-                // get true odds of this line -- use normal approximation to estimate lambda
-                // but use poisson cdf to calculate
-                let poisson_dist = Poisson::new(avg_lambda).unwrap();
+                let bookie_odds = settings
+                    .exchanges
+                    .effective_back_odds(&bookie.key, &outcome.price);
 
-                // round down for under probability
-                let line_rounded = offered_line as u64;
-                let mut true_probability = poisson_dist.cdf(line_rounded);
-
-                if (outcome.name == OVER_OUTCOME) {
-                    true_probability = 1.0 - true_probability;
-                }
-
-                let true_odds = Odds::Decimal(1.0 / true_probability);
+                let true_odds = Self::poisson_true_odds(avg_lambda, &outcome.name, offered_line);
 
                 let percent_ev = bookie_odds.ev_percentage(&true_odds);
 
-                if (percent_ev > PERCENT_EV_CUTOFF) {
+                if (percent_ev > percent_ev_cutoff) {
                     let opportunity = Opportunity {
+                        event_id: self.id.clone(),
+                        sport_key: self.sport_key.clone(),
+                        commence_time: self.commence_time,
+                        bookie_key: bookie.key.clone(),
                         bookie_name: bookie.title.clone(),
                         offered_odds: bookie_odds,
                         outcome_key: outcome.name.clone(),
+                        point: Some(offered_line),
                         market_key: MarketType::Totals.clone(),
                         true_odds,
                         percent_ev,
+                        devig_method,
+                        model: PricingModel::PoissonTotals,
+                        recommended_stake: None,
+                        quote_age: self.quote_age(&bookie.key),
                         sport_title: self.sport_title.clone(),
                         home_team: self.home_team.clone(),
                         away_team: self.away_team.clone(),
@@ -216,44 +573,322 @@ impl Event {
         return opps;
     }
 
-    fn identify_opportunities_naive(&self, market: &MarketType) -> Vec<Opportunity> {
+    /// Mean home margin implied by `bookie`'s spread line and prices
+    fn implied_mean_margin(
+        &self,
+        bookie: &Bookmaker,
+        model: &MarginModel,
+        method: &DevigMethod,
+    ) -> Option<f64> {
+        let outcomes = bookie.get_offered_outcomes(&MarketType::Spreads);
+        let home_outcome = outcomes.iter().find(|x| x.name == self.home_team)?;
+        let point = home_outcome.point?;
+
+        let both_sides_odds: Vec<Odds> = outcomes.iter().map(|x| x.price).collect();
+        if (both_sides_odds.len() != 2) {
+            return None;
+        }
+
+        let cover_probability = home_outcome
+            .price
+            .true_probability_estimate(&both_sides_odds, method);
+        return Some(model.fit_mean_margin(point, cover_probability));
+    }
+
+    /// Weighted mean of the home margins implied by fresh books' spread
+    /// lines, if at least two books quote spreads
+    fn consensus_mean_margin(
+        &self,
+        model: &MarginModel,
+        method: &DevigMethod,
+        settings: &ScanSettings,
+    ) -> Option<f64> {
+        let fresh_bookies = self.get_fresh_bookies(settings);
+        let mean_margins: Vec<(&str, f64)> = fresh_bookies
+            .iter()
+            .filter_map(|x| {
+                let margin = self.implied_mean_margin(x, model, method)?;
+                return Some((x.key.as_str(), margin));
+            })
+            .collect();
+        if (mean_margins.len() <= 1) {
+            return None;
+        }
+        return settings.bookmaker_weights.weighted_mean(
+            &self.sport_key,
+            &MarketType::Spreads,
+            mean_margins,
+        );
+    }
+
+    /// Fits a margin distribution to every book's spread line, averages them
+    /// into a consensus and prices each offered handicap against it, so books
+    /// quoting different points can still be compared
+    fn identify_spreads_opportunities(&self, settings: &ScanSettings) -> Vec<Opportunity> {
+        let mut opps: Vec<Opportunity> = Vec::new();
+        let devig_method = settings
+            .devig
+            .method_for(&self.sport_key, &MarketType::Spreads);
+        let percent_ev_cutoff = settings.percent_ev_cutoff_for(&self.sport_key);
+        let model = settings.spread_model_for(&self.sport_key);
+
+        let mean_margin = match self.consensus_mean_margin(&model, &devig_method, settings) {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
+
+        for bookie in self.get_relevant_bookies(settings) {
+            for outcome in bookie.get_offered_outcomes(&MarketType::Spreads) {
+                let point = match outcome.point {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let (cover, fair_line) = if (outcome.name == self.home_team) {
+                    (model.home_cover(mean_margin, point), -mean_margin)
+                } else if (outcome.name == self.away_team) {
+                    (model.away_cover(mean_margin, point), mean_margin)
+                } else {
+                    continue;
+                };
+                if (cover.win <= 0.0) {
+                    continue;
+                }
+
+                let true_odds = Odds::Decimal(cover.fair_decimal_odds());
+                if (true_odds.get_decimal() > settings.max_odds_cutoff) {
+                    continue;
+                }
+
+                let bookie_odds = settings
+                    .exchanges
+                    .effective_back_odds(&bookie.key, &outcome.price);
+                let percent_ev = bookie_odds.ev_percentage(&true_odds);
+                if (percent_ev > percent_ev_cutoff) {
+                    let opportunity = Opportunity {
+                        event_id: self.id.clone(),
+                        sport_key: self.sport_key.clone(),
+                        commence_time: self.commence_time,
+                        bookie_key: bookie.key.clone(),
+                        bookie_name: bookie.title.clone(),
+                        offered_odds: bookie_odds,
+                        outcome_key: outcome.name.clone(),
+                        point: Some(point),
+                        market_key: MarketType::Spreads,
+                        true_odds,
+                        percent_ev,
+                        devig_method,
+                        model: PricingModel::MarginModel,
+                        recommended_stake: None,
+                        quote_age: self.quote_age(&bookie.key),
+                        sport_title: self.sport_title.clone(),
+                        home_team: self.home_team.clone(),
+                        away_team: self.away_team.clone(),
+                        message: format!(
+                            "Fair line {0} {1:+.1}. Offered line {2:+.1}",
+                            outcome.name, fair_line, point
+                        ),
+                    };
+
+                    opps.push(opportunity);
+                }
+            }
+        }
+
+        return opps;
+    }
+
+    /// Prices every runner of a futures market against the consensus of
+    /// all books, allowing for books listing different runners
+    fn identify_outrights_opportunities(&self, settings: &ScanSettings) -> Vec<Opportunity> {
+        let mut opps: Vec<Opportunity> = Vec::new();
+        let devig_method = settings
+            .devig
+            .method_for(&self.sport_key, &MarketType::Outrights);
+        let percent_ev_cutoff = settings.percent_ev_cutoff_for(&self.sport_key);
+        let outright_settings = &settings.outrights;
+
+        for runner in consensus_runners(&self.get_fresh_bookies(settings), &devig_method) {
+            let true_odds = runner.fair_odds();
+            if (runner.prices.len() < outright_settings.min_books
+                || true_odds.get_decimal() > outright_settings.max_fair_odds)
+            {
+                continue;
+            }
+
+            let outliers = runner.outliers(outright_settings);
+            for price in &runner.prices {
+                if (!settings.bookmakers.allows(&price.bookie_key)) {
+                    continue;
+                }
+
+                let offered_odds = settings
+                    .exchanges
+                    .effective_back_odds(&price.bookie_key, &price.odds);
+                let percent_ev = offered_odds.ev_percentage(&true_odds);
+                if (percent_ev <= percent_ev_cutoff) {
+                    continue;
+                }
+
+                let mut message = format!(
+                    "Consensus of {0} books quoting this runner",
+                    runner.prices.len()
+                );
+                if (outliers.iter().any(|x| x.bookie_key == price.bookie_key)) {
+                    message += &format!(
+                        ". Outlier at {0:.2}x the consensus price, check it isn't stale",
+                        runner.price_ratio(price)
+                    );
+                }
+
+                opps.push(Opportunity {
+                    event_id: self.id.clone(),
+                    sport_key: self.sport_key.clone(),
+                    commence_time: self.commence_time,
+                    bookie_key: price.bookie_key.clone(),
+                    bookie_name: price.bookie_name.clone(),
+                    offered_odds,
+                    outcome_key: runner.name.clone(),
+                    point: None,
+                    market_key: MarketType::Outrights,
+                    true_odds,
+                    percent_ev,
+                    devig_method,
+                    model: PricingModel::OutrightConsensus,
+                    recommended_stake: None,
+                    quote_age: self.quote_age(&price.bookie_key),
+                    sport_title: self.sport_title.clone(),
+                    home_team: self.home_team.clone(),
+                    away_team: self.away_team.clone(),
+                    message,
+                });
+            }
+        }
+
+        return opps;
+    }
+
+    /// True probabilities from the middle of exchange back and lay prices,
+    /// averaged across exchanges. Only given when exchanges cover every
+    /// outcome of the market, as the mid prices are then normalised.
+    fn exchange_fair_probabilities(
+        &self,
+        market: &MarketType,
+        settings: &ScanSettings,
+    ) -> HashMap<String, f64> {
+        let mut mids_by_outcome: HashMap<String, Vec<f64>> = HashMap::new();
+
+        for bookie in &self.get_fresh_bookies(settings) {
+            if (settings.exchanges.commission_for(&bookie.key).is_none()) {
+                continue;
+            }
+
+            for outcome in bookie.get_offered_outcomes(market) {
+                let lay_odds = match bookie.get_lay_odds(market, &outcome.name) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let mid =
+                    (outcome.price.implied_probability() + lay_odds.implied_probability()) / 2.0;
+                mids_by_outcome.entry(outcome.name).or_default().push(mid);
+            }
+        }
+
+        let all_outcomes = self.get_all_outcomes(market);
+        if (mids_by_outcome.is_empty()
+            || all_outcomes
+                .iter()
+                .any(|x| !mids_by_outcome.contains_key(x)))
+        {
+            return HashMap::new();
+        }
+
+        let averages: HashMap<String, f64> = mids_by_outcome
+            .into_iter()
+            .map(|(outcome, mids)| (outcome, mids.iter().sum::<f64>() / mids.len() as f64))
+            .collect();
+        let total: f64 = averages.values().sum();
+        return averages
+            .into_iter()
+            .map(|(outcome, probability)| (outcome, probability / total))
+            .collect();
+    }
+
+    fn identify_opportunities_naive(
+        &self,
+        market: &MarketType,
+        settings: &ScanSettings,
+    ) -> Vec<Opportunity> {
         let all_outcomes = self.get_all_outcomes(market);
+        let devig_method = settings.devig.method_for(&self.sport_key, market);
+        let percent_ev_cutoff = settings.percent_ev_cutoff_for(&self.sport_key);
 
         let mut opportunities_vec: Vec<Opportunity> = Vec::new();
 
+        let exchange_probabilities = match settings.exchanges.anchor_to_lay {
+            true => self.exchange_fair_probabilities(market, settings),
+            false => HashMap::new(),
+        };
+
         for outcome_key in &all_outcomes {
-            let true_odds = self.get_true_odds_for_outcome(market, outcome_key.as_str());
+            let (true_odds, model) = match exchange_probabilities.get(outcome_key) {
+                Some(probability) => (Odds::Decimal(1.0 / probability), PricingModel::ExchangeMid),
+                None => (
+                    self.get_true_odds_for_outcome(
+                        market,
+                        outcome_key.as_str(),
+                        &devig_method,
+                        settings,
+                    ),
+                    PricingModel::Consensus,
+                ),
+            };
 
-            if (true_odds.get_decimal() > MAX_ODDS_CUTOFF) {
+            if (true_odds.get_decimal() > settings.max_odds_cutoff) {
                 // only want to consider likely outcomes
                 // as odds for unlikely outcomes are skewed
                 continue;
             }
 
-            let event_au_books: Vec<Bookmaker> = self.get_relevant_bookies();
+            let event_au_books: Vec<Bookmaker> = self.get_relevant_bookies(settings);
 
             for bookie in event_au_books {
                 let maybe_bookie_odds = bookie.get_odds(market, outcome_key.as_str());
+                let point = bookie
+                    .get_offered_outcomes(market)
+                    .into_iter()
+                    .find(|x| x.name == *outcome_key)
+                    .and_then(|x| x.point);
 
                 let bookie_odds = match maybe_bookie_odds {
-                    Some(x) => x,
+                    Some(x) => settings.exchanges.effective_back_odds(&bookie.key, &x),
                     None => continue,
                 };
 
                 let percent_ev = bookie_odds.ev_percentage(&true_odds);
 
-                if (bookie_odds > true_odds && percent_ev > PERCENT_EV_CUTOFF) {
+                if (bookie_odds > true_odds && percent_ev > percent_ev_cutoff) {
                     let opportunity = Opportunity {
+                        event_id: self.id.clone(),
+                        sport_key: self.sport_key.clone(),
+                        commence_time: self.commence_time,
+                        bookie_key: bookie.key.clone(),
                         bookie_name: bookie.title.clone(),
                         sport_title: self.sport_title.clone(),
                         home_team: self.home_team.clone(),
                         away_team: self.away_team.clone(),
                         offered_odds: bookie_odds,
                         outcome_key: outcome_key.clone(),
+                        point,
                         market_key: market.clone(),
                         message: format!("Hello"),
                         true_odds,
                         percent_ev,
+                        devig_method,
+                        model,
+                        recommended_stake: None,
+                        quote_age: self.quote_age(&bookie.key),
                     };
 
                     opportunities_vec.push(opportunity);
@@ -263,30 +898,483 @@ impl Event {
         return opportunities_vec;
     }
 
-    fn identify_h2h_opportunities(&self) -> Vec<Opportunity> {
+    fn identify_h2h_opportunities(&self, settings: &ScanSettings) -> Vec<Opportunity> {
         const MARKET_KEY: MarketType = MarketType::H2h;
-        return self.identify_opportunities_naive(&MARKET_KEY);
+        return self.identify_opportunities_naive(&MARKET_KEY, settings);
     }
 
-    pub fn identify_opportunities_in_market(&self, market: &MarketType) -> Vec<Opportunity> {
+    pub fn identify_opportunities_in_market(
+        &self,
+        market: &MarketType,
+        settings: &ScanSettings,
+    ) -> Vec<Opportunity> {
         if (*market == MarketType::H2h) {
-            return self.identify_h2h_opportunities();
+            return self.identify_h2h_opportunities(settings);
         } else if (*market == MarketType::Totals) {
-            return self.identify_totals_opportunities();
+            return self.identify_totals_opportunities(settings);
+        } else if (*market == MarketType::Spreads) {
+            return self.identify_spreads_opportunities(settings);
+        } else if (*market == MarketType::Outrights) {
+            return self.identify_outrights_opportunities(settings);
         }
 
         return Vec::new();
     }
 
     pub fn identify_opportunities(&self) -> Vec<Opportunity> {
+        return self.identify_opportunities_using(&ScanSettings::default());
+    }
+
+    /// Same as `identify_opportunities`, but with the de-vig methods,
+    /// cutoffs and bookmakers from `settings`
+    pub fn identify_opportunities_using(&self, settings: &ScanSettings) -> Vec<Opportunity> {
         let mut all_opportunities = Vec::new();
         for market_type in MarketType::iter() {
-            let mut market_opps = self.identify_opportunities_in_market(&market_type);
+            let mut market_opps = self.identify_opportunities_in_market(&market_type, settings);
             all_opportunities.append(&mut market_opps);
         }
         return all_opportunities;
     }
 
+    /// Consensus fair odds of an outcome as the market stands, e.g. just
+    /// before the event starts for its closing line. Totals and spreads are
+    /// priced at `point` even if the books have since moved their lines.
+    pub fn fair_odds_for(
+        &self,
+        market: &MarketType,
+        outcome_key: &str,
+        point: Option<f64>,
+        settings: &ScanSettings,
+    ) -> Option<Odds> {
+        let devig_method = settings.devig.method_for(&self.sport_key, market);
+        let fresh_bookies = self.get_fresh_bookies(settings);
+
+        let true_odds = match (market, point) {
+            (MarketType::Totals, Some(point))
+                if fresh_bookies
+                    .iter()
+                    .any(|x| x.get_over_under_line().is_some_and(|line| line != point)) =>
+            {
+                let lamb_estimates = self.implied_mean_scores(&fresh_bookies, &devig_method);
+                let avg_lambda = self.consensus_mean_score(&lamb_estimates, settings)?;
+                Self::poisson_true_odds(avg_lambda, outcome_key, point)
+            }
+            (MarketType::Spreads, Some(point)) => {
+                let model = settings.spread_model_for(&self.sport_key);
+                let mean_margin = self.consensus_mean_margin(&model, &devig_method, settings)?;
+                let cover = if (outcome_key == self.home_team) {
+                    model.home_cover(mean_margin, point)
+                } else if (outcome_key == self.away_team) {
+                    model.away_cover(mean_margin, point)
+                } else {
+                    return None;
+                };
+                if (cover.win <= 0.0) {
+                    return None;
+                }
+                Odds::Decimal(cover.fair_decimal_odds())
+            }
+            (MarketType::Outrights, _) => consensus_runners(&fresh_bookies, &devig_method)
+                .into_iter()
+                .find(|x| x.name == outcome_key)?
+                .fair_odds(),
+            _ => {
+                let exchange_probabilities = match settings.exchanges.anchor_to_lay {
+                    true => self.exchange_fair_probabilities(market, settings),
+                    false => HashMap::new(),
+                };
+                match exchange_probabilities.get(outcome_key) {
+                    Some(probability) => Odds::Decimal(1.0 / probability),
+                    None => {
+                        self.get_true_odds_for_outcome(market, outcome_key, &devig_method, settings)
+                    }
+                }
+            }
+        };
+
+        if (!true_odds.get_decimal().is_finite() || true_odds.get_decimal() <= 1.0) {
+            return None;
+        }
+        return Some(true_odds);
+    }
+
+    /// Looks for sure bets in the h2h, totals and spreads markets, using the
+    /// best price for each outcome across all relevant bookies. Totals and
+    /// spreads are only combined when the points match up.
+    pub fn find_arbitrages(&self, bankroll: f64, settings: &ScanSettings) -> Vec<Arbitrage> {
+        let mut arbitrages = Vec::new();
+
+        for market in [MarketType::H2h, MarketType::Totals, MarketType::Spreads] {
+            for best_prices in self.get_best_prices(&market, settings).values() {
+                let maybe_arb = Arbitrage::from_best_prices(
+                    &self.sport_title,
+                    &self.home_team,
+                    &self.away_team,
+                    &market,
+                    best_prices,
+                    bankroll,
+                );
+
+                if let Some(arb) = maybe_arb {
+                    arbitrages.push(arb);
+                }
+            }
+        }
+
+        arbitrages.sort_by(|a, b| b.profit_margin.total_cmp(&a.profit_margin));
+        return arbitrages;
+    }
+
+    /// Back at a bookie and lay at an exchange, for every pair that loses no
+    /// more than `max_qualifying_loss` percent of `back_stake`. Only the best
+    /// exchange for each bookie price is kept.
+    pub fn find_matched_bets(&self, back_stake: f64, settings: &ScanSettings) -> Vec<MatchedBet> {
+        let mut matched_bets = Vec::new();
+        let relevant_bookies = self.get_relevant_bookies(settings);
+        let exchanges: Vec<&Bookmaker> = relevant_bookies
+            .iter()
+            .filter(|x| settings.exchanges.commission_for(&x.key).is_some())
+            .collect();
+
+        for market in [MarketType::H2h, MarketType::Outrights] {
+            for bookie in &relevant_bookies {
+                if (settings.exchanges.commission_for(&bookie.key).is_some()) {
+                    continue;
+                }
+
+                for outcome in bookie.get_offered_outcomes(&market) {
+                    let mut best: Option<MatchedBet> = None;
+
+                    for exchange in &exchanges {
+                        let lay_odds = match exchange.get_lay_odds(&market, &outcome.name) {
+                            Some(x) => x,
+                            None => continue,
+                        };
+                        let commission = settings.exchanges.commission_for(&exchange.key).unwrap();
+
+                        let lay_stake = MatchedBet::lay_stake_for(
+                            back_stake,
+                            &outcome.price,
+                            &lay_odds,
+                            commission,
+                        );
+                        let matched_bet = MatchedBet {
+                            sport_title: self.sport_title.clone(),
+                            home_team: self.home_team.clone(),
+                            away_team: self.away_team.clone(),
+                            market_key: market.clone(),
+                            outcome_key: outcome.name.clone(),
+                            bookie_name: bookie.title.clone(),
+                            back_odds: outcome.price,
+                            back_stake,
+                            exchange_name: exchange.title.clone(),
+                            lay_odds,
+                            commission,
+                            lay_stake,
+                            liability: lay_stake * (lay_odds.get_decimal() - 1.0),
+                            profit: lay_stake * (1.0 - commission) - back_stake,
+                        };
+
+                        let is_better = match &best {
+                            Some(current) => matched_bet.profit > current.profit,
+                            None => true,
+                        };
+                        if (is_better) {
+                            best = Some(matched_bet);
+                        }
+                    }
+
+                    if let Some(matched_bet) = best {
+                        if (matched_bet.rating() >= -settings.exchanges.max_qualifying_loss) {
+                            matched_bets.push(matched_bet);
+                        }
+                    }
+                }
+            }
+        }
+
+        matched_bets.sort_by(|a, b| b.profit.total_cmp(&a.profit));
+        return matched_bets;
+    }
+
+    /// Compares this fetch of the event with an earlier one, `elapsed`
+    /// before it, for lines several books moved the same way. Books that
+    /// didn't follow are listed when they now offer value against the books
+    /// that did.
+    pub fn find_steam_moves(
+        &self,
+        previous: &Event,
+        elapsed: Duration,
+        settings: &ScanSettings,
+    ) -> Vec<SteamMove> {
+        let movement = &settings.movement;
+        if (previous.id != self.id || elapsed <= Duration::zero()) {
+            return Vec::new();
+        }
+
+        // a book moved quickly enough if the snapshots are within the window,
+        // or if it last repriced within the window before this fetch
+        let window = Duration::minutes(movement.window_minutes);
+        let moved_in_window = |bookie_key: &str| {
+            return elapsed <= window
+                || self.quote_age(bookie_key).is_some_and(|age| age <= window);
+        };
+
+        let mut steam_moves = Vec::new();
+        let percent_ev_cutoff = settings.percent_ev_cutoff_for(&self.sport_key);
+
+        for market in [
+            MarketType::H2h,
+            MarketType::Totals,
+            MarketType::Spreads,
+            MarketType::Outrights,
+        ] {
+            let devig_method = settings.devig.method_for(&self.sport_key, &market);
+
+            // every (outcome, point) quoted, so books on different lines of
+            // totals and spreads aren't compared with each other
+            let mut lines: Vec<(String, Option<f64>)> = Vec::new();
+            for bookie in &self.bookmakers {
+                for outcome in bookie.get_offered_outcomes(&market) {
+                    let line = (outcome.name, outcome.point);
+                    if (!lines.contains(&line)) {
+                        lines.push(line);
+                    }
+                }
+            }
+            lines.sort_by(|a, b| {
+                return a
+                    .0
+                    .cmp(&b.0)
+                    .then(a.1.unwrap_or(0.0).total_cmp(&b.1.unwrap_or(0.0)));
+            });
+
+            let mut market_moves: Vec<SteamMove> = Vec::new();
+            for (outcome_key, point) in lines {
+                let quotes_line = |event: &Event, bookie_key: &str| {
+                    return event
+                        .bookmakers
+                        .iter()
+                        .find(|x| x.key == bookie_key)
+                        .is_some_and(|bookie| {
+                            return bookie
+                                .get_offered_outcomes(&market)
+                                .iter()
+                                .any(|x| x.name == outcome_key && x.point == point);
+                        });
+                };
+
+                // (bookie, probability before, probability after), for books
+                // quoting this point both times
+                let mut changes: Vec<(&Bookmaker, f64, f64)> = Vec::new();
+                for bookie in &self.bookmakers {
+                    if (!quotes_line(self, &bookie.key) || !quotes_line(previous, &bookie.key)) {
+                        continue;
+                    }
+
+                    let before = previous.get_adjusted_probability(
+                        &bookie.key,
+                        &market,
+                        &outcome_key,
+                        &devig_method,
+                    );
+                    let after = self.get_adjusted_probability(
+                        &bookie.key,
+                        &market,
+                        &outcome_key,
+                        &devig_method,
+                    );
+                    if let (Some(before), Some(after)) = (before, after) {
+                        changes.push((bookie, before, after));
+                    }
+                }
+
+                let shortened: Vec<&(&Bookmaker, f64, f64)> = changes
+                    .iter()
+                    .filter(|(bookie, before, after)| {
+                        return after - before >= movement.min_probability_move
+                            && moved_in_window(&bookie.key);
+                    })
+                    .collect();
+                let drifted: Vec<&(&Bookmaker, f64, f64)> = changes
+                    .iter()
+                    .filter(|(bookie, before, after)| {
+                        return before - after >= movement.min_probability_move
+                            && moved_in_window(&bookie.key);
+                    })
+                    .collect();
+                let (direction, moved) = if (shortened.len() >= drifted.len()) {
+                    (MoveDirection::Shortening, shortened)
+                } else {
+                    (MoveDirection::Drifting, drifted)
+                };
+                if (moved.len() < movement.min_books.max(1)) {
+                    continue;
+                }
+
+                let weights = &settings.bookmaker_weights;
+                let pre_move = weights.weighted_mean(
+                    &self.sport_key,
+                    &market,
+                    changes
+                        .iter()
+                        .map(|(bookie, before, _)| (bookie.key.as_str(), *before)),
+                );
+                let post_move = weights.weighted_mean(
+                    &self.sport_key,
+                    &market,
+                    moved
+                        .iter()
+                        .map(|(bookie, _, after)| (bookie.key.as_str(), *after)),
+                );
+                let (pre_move, post_move) = match (pre_move, post_move) {
+                    (Some(x), Some(y)) if x > 0.0 && y > 0.0 => (x, y),
+                    _ => continue,
+                };
+                let post_move_fair = Odds::Decimal(1.0 / post_move);
+
+                let mut stale_quotes = Vec::new();
+                for (bookie, before, after) in &changes {
+                    let followed = (after - before).abs() >= movement.min_probability_move / 2.0;
+                    if (followed || !settings.bookmakers.allows(&bookie.key)) {
+                        continue;
+                    }
+
+                    let offered_odds = match bookie.get_odds(&market, &outcome_key) {
+                        Some(x) => settings.exchanges.effective_back_odds(&bookie.key, &x),
+                        None => continue,
+                    };
+                    let percent_ev = offered_odds.ev_percentage(&post_move_fair);
+                    if (percent_ev > percent_ev_cutoff) {
+                        stale_quotes.push(StaleQuote {
+                            bookie_key: bookie.key.clone(),
+                            bookie_name: bookie.title.clone(),
+                            offered_odds,
+                            percent_ev,
+                        });
+                    }
+                }
+
+                market_moves.push(SteamMove {
+                    event_id: self.id.clone(),
+                    sport_key: self.sport_key.clone(),
+                    sport_title: self.sport_title.clone(),
+                    commence_time: self.commence_time,
+                    home_team: self.home_team.clone(),
+                    away_team: self.away_team.clone(),
+                    market_key: market.clone(),
+                    outcome_key: outcome_key.clone(),
+                    point,
+                    direction,
+                    books_moved: moved
+                        .iter()
+                        .map(|(bookie, _, _)| bookie.title.clone())
+                        .collect(),
+                    pre_move_fair: Odds::Decimal(1.0 / pre_move),
+                    post_move_fair,
+                    elapsed_minutes: elapsed.num_seconds() as f64 / 60.0,
+                    stale_quotes,
+                });
+            }
+
+            // one side of a line shortening drifts the others, so each line
+            // is reported once, by the side that shortened the most books
+            let rank = |x: &SteamMove| {
+                return (
+                    x.direction == MoveDirection::Shortening,
+                    x.books_moved.len(),
+                );
+            };
+            let mut by_line: Vec<(Option<i64>, SteamMove)> = Vec::new();
+            for steam_move in market_moves {
+                let line_key = self.line_key(&market, &steam_move.outcome_key, steam_move.point);
+                match by_line.iter_mut().find(|(x, _)| *x == line_key) {
+                    Some((_, current)) => {
+                        if (rank(&steam_move) > rank(current)) {
+                            *current = steam_move;
+                        }
+                    }
+                    None => by_line.push((line_key, steam_move)),
+                }
+            }
+            steam_moves.extend(by_line.into_iter().map(|(_, x)| x));
+        }
+
+        return steam_moves;
+    }
+
+    /// Key identifying which outcomes can be combined into a complete book:
+    /// every h2h outcome belongs together, totals need the same point and
+    /// spreads need opposite handicaps
+    fn line_key(&self, market: &MarketType, outcome_key: &str, point: Option<f64>) -> Option<i64> {
+        let point = match (market, point) {
+            (MarketType::H2h, _) => return Some(0),
+            (_, None) => return None,
+            (_, Some(x)) => x,
+        };
+
+        if (*market == MarketType::Spreads && outcome_key != self.home_team) {
+            return Some((-point * 100.0).round() as i64);
+        }
+        return Some((point * 100.0).round() as i64);
+    }
+
+    fn get_best_prices(
+        &self,
+        market: &MarketType,
+        settings: &ScanSettings,
+    ) -> HashMap<i64, Vec<BestPrice>> {
+        let mut best_by_line: HashMap<i64, HashMap<String, BestPrice>> = HashMap::new();
+
+        for bookie in self.get_relevant_bookies(settings) {
+            for outcome in bookie.get_offered_outcomes(market) {
+                let line_key = match self.line_key(market, &outcome.name, outcome.point) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let line_prices = best_by_line.entry(line_key).or_default();
+                let odds = settings
+                    .exchanges
+                    .effective_back_odds(&bookie.key, &outcome.price);
+                let is_better = match line_prices.get(&outcome.name) {
+                    Some(current) => odds > current.odds,
+                    None => true,
+                };
+
+                if (is_better) {
+                    line_prices.insert(
+                        outcome.name.clone(),
+                        BestPrice {
+                            outcome_key: outcome.name.clone(),
+                            point: outcome.point,
+                            bookie_name: bookie.title.clone(),
+                            odds,
+                        },
+                    );
+                }
+            }
+        }
+
+        // a line is only a complete book if it covers every outcome of the market
+        let all_outcomes = self.get_all_outcomes(market);
+        let required_outcomes = match market {
+            MarketType::H2h => all_outcomes.len(),
+            _ => 2,
+        };
+
+        return best_by_line
+            .into_iter()
+            .filter(|(_, prices)| prices.len() == required_outcomes)
+            .map(|(line_key, prices)| {
+                // sorted so the same prices always give the same legs
+                let mut prices: Vec<BestPrice> = prices.into_values().collect();
+                prices.sort_by(|a, b| a.outcome_key.cmp(&b.outcome_key));
+                return (line_key, prices);
+            })
+            .collect();
+    }
+
     fn get_all_outcomes(&self, market: &MarketType) -> HashSet<String> {
         let mut outcome_set: HashSet<String> = HashSet::new();
 
@@ -298,10 +1386,25 @@ impl Event {
         return outcome_set;
     }
 
-    fn get_true_odds_for_outcome(&self, market: &MarketType, outcome_key: &str) -> Odds {
-        let all_bookie_keys = self.get_all_bookies();
-        let outcome_avg_probability =
-            self.get_average_probability(all_bookie_keys, &market, outcome_key);
+    fn get_true_odds_for_outcome(
+        &self,
+        market: &MarketType,
+        outcome_key: &str,
+        method: &DevigMethod,
+        settings: &ScanSettings,
+    ) -> Odds {
+        let fresh_bookie_keys = self
+            .get_fresh_bookies(settings)
+            .into_iter()
+            .map(|x| x.key)
+            .collect();
+        let outcome_avg_probability = self.get_average_probability(
+            fresh_bookie_keys,
+            &market,
+            outcome_key,
+            method,
+            &settings.bookmaker_weights,
+        );
         return Odds::Decimal(1.0 / outcome_avg_probability);
     }
 
@@ -310,20 +1413,18 @@ impl Event {
         bookies: HashSet<String>,
         market: &MarketType,
         outcome_key: &str,
+        method: &DevigMethod,
+        weights: &BookmakerWeights,
     ) -> f64 {
-        // sharps vs nonsharps: 50-50 weighting
-        return bookies
-            .iter()
-            .filter(|bookie| {
-                self.get_adjusted_probability(bookie, market, outcome_key)
-                    .is_some()
-            })
-            .fold(0.0, |sum, bookie_key| {
-                sum + self
-                    .get_adjusted_probability(bookie_key, market, outcome_key)
-                    .unwrap()
-            })
-            / (bookies.len() as f64);
+        // only bookies quoting the market count, weighted by sharpness
+        let probabilities = bookies.iter().filter_map(|bookie_key| {
+            let probability =
+                self.get_adjusted_probability(bookie_key, market, outcome_key, method)?;
+            return Some((bookie_key.as_str(), probability));
+        });
+        return weights
+            .weighted_mean(&self.sport_key, market, probabilities)
+            .unwrap_or(0.0);
     }
 
     fn get_adjusted_probability(
@@ -331,6 +1432,7 @@ impl Event {
         bookie_key: &str,
         market: &MarketType,
         outcome: &str,
+        method: &DevigMethod,
     ) -> Option<f64> {
         let bookie_object = match self
             .bookmakers
@@ -347,6 +1449,6 @@ impl Event {
             None => return None,
         };
 
-        return market.true_probability_for_outcome(outcome);
+        return market.true_probability_for_outcome(outcome, method);
     }
 }
@@ -0,0 +1,35 @@
+use crate::odds_interface::logic::{
+    exchange::{commission_adjusted, ExchangeSettings, MatchedBet},
+    odds::Odds,
+};
+
+#[test]
+fn test_commission_adjusted_back_odds() {
+    let settings = ExchangeSettings::default();
+
+    // 5% off the 1.5 units of winnings
+    let adjusted = settings.effective_back_odds("betfair_ex_au", &Odds::Decimal(2.5));
+    assert!((adjusted.get_decimal() - 2.425).abs() < 1e-9);
+
+    // bookies keep their price
+    let unchanged = settings.effective_back_odds("sportsbet", &Odds::Decimal(2.5));
+    assert_eq!(unchanged.get_decimal(), 2.5);
+
+    let free = commission_adjusted(&Odds::Decimal(3.0), 0.0);
+    assert_eq!(free.get_decimal(), 3.0);
+}
+
+#[test]
+fn test_lay_stake_levels_profit() {
+    let back_odds = Odds::Decimal(3.0);
+    let lay_odds = Odds::Decimal(3.2);
+    let commission = 0.05;
+
+    let lay_stake = MatchedBet::lay_stake_for(100.0, &back_odds, &lay_odds, commission);
+    let liability = lay_stake * (lay_odds.get_decimal() - 1.0);
+
+    let profit_if_back_wins = 100.0 * (back_odds.get_decimal() - 1.0) - liability;
+    let profit_if_lay_wins = lay_stake * (1.0 - commission) - 100.0;
+    assert!((profit_if_back_wins - profit_if_lay_wins).abs() < 1e-9);
+    assert!(profit_if_back_wins < 0.0);
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, fmt};
+
+use serde::Deserialize;
+
+use super::event::matchup;
+use super::market::MarketType;
+use super::odds::Odds;
+
+#[cfg(test)]
+mod exchange_test;
+
+const DEFAULT_MAX_QUALIFYING_LOSS: f64 = 5.0;
+
+/// Commission on net winnings charged by each exchange we know of
+fn default_commissions() -> HashMap<String, f64> {
+    return HashMap::from([
+        (String::from("betfair_ex_au"), 0.05),
+        (String::from("betfair_ex_uk"), 0.05),
+        (String::from("betfair_ex_eu"), 0.05),
+        (String::from("matchbook"), 0.02),
+        (String::from("smarkets"), 0.02),
+    ]);
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExchangeSettings {
+    /// Commission rate (0.05 for 5%) keyed by bookmaker key. Any bookie
+    /// listed here is treated as an exchange.
+    pub commissions: HashMap<String, f64>,
+    /// Use the middle of exchange back and lay prices as the true odds
+    /// when an exchange quotes both sides of every outcome
+    pub anchor_to_lay: bool,
+    /// Most a matched bet may lose, as a percentage of the back stake
+    pub max_qualifying_loss: f64,
+}
+
+impl Default for ExchangeSettings {
+    fn default() -> Self {
+        return ExchangeSettings {
+            commissions: default_commissions(),
+            anchor_to_lay: true,
+            max_qualifying_loss: DEFAULT_MAX_QUALIFYING_LOSS,
+        };
+    }
+}
+
+impl ExchangeSettings {
+    pub fn commission_for(&self, bookie_key: &str) -> Option<f64> {
+        return self.commissions.get(bookie_key).copied();
+    }
+
+    /// What a back price is really worth once commission comes off the
+    /// winnings. Bookies that aren't exchanges are returned as is.
+    pub fn effective_back_odds(&self, bookie_key: &str, odds: &Odds) -> Odds {
+        return match self.commission_for(bookie_key) {
+            Some(commission) => commission_adjusted(odds, commission),
+            None => *odds,
+        };
+    }
+}
+
+pub fn commission_adjusted(odds: &Odds, commission: f64) -> Odds {
+    return Odds::Decimal(1.0 + (odds.get_decimal() - 1.0) * (1.0 - commission));
+}
+
+/// Lay market matching a back market, for markets exchanges lay
+pub fn lay_market_for(market: &MarketType) -> Option<MarketType> {
+    match market {
+        MarketType::H2h => return Some(MarketType::H2hLay),
+        MarketType::Outrights => return Some(MarketType::OutrightsLay),
+        _ => return None,
+    }
+}
+
+/// Back at a bookie and lay the same outcome at an exchange, so the result
+/// is (nearly) the same whoever wins. Used to turn bookie promotions and
+/// bonus bets into cash, at a small qualifying loss.
+#[derive(Debug, Clone)]
+pub struct MatchedBet {
+    pub sport_title: String,
+    pub home_team: String,
+    pub away_team: String,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    pub bookie_name: String,
+    pub back_odds: Odds,
+    pub back_stake: f64,
+    pub exchange_name: String,
+    pub lay_odds: Odds,
+    pub commission: f64,
+    pub lay_stake: f64,
+    /// What we pay out at the exchange if the outcome wins
+    pub liability: f64,
+    /// Profit whichever way the event goes, usually slightly negative
+    pub profit: f64,
+}
+
+impl MatchedBet {
+    /// Sizes the lay so the profit is the same whether or not the outcome
+    /// wins
+    pub fn lay_stake_for(
+        back_stake: f64,
+        back_odds: &Odds,
+        lay_odds: &Odds,
+        commission: f64,
+    ) -> f64 {
+        return back_stake * back_odds.get_decimal() / (lay_odds.get_decimal() - commission);
+    }
+
+    /// Profit as a percentage of the back stake
+    pub fn rating(&self) -> f64 {
+        return 100.0 * self.profit / self.back_stake;
+    }
+
+    pub fn matchup(&self) -> String {
+        return matchup(&self.home_team, &self.away_team);
+    }
+}
+
+impl fmt::Display for MatchedBet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            " ### Matched bet found on {0}
+    - {1:.2}%: {2}
+    - Outcome: {3}
+    - Market: {4}
+    - Back {5:.2} at {6} with {7}
+    - Lay {8:.2} at {9} with {10} ({11:.1}% commission)
+    - Liability: {12:.2}
+    - Profit either way: {13:.2}
+",
+            self.sport_title,
+            self.rating(),
+            self.matchup(),
+            self.outcome_key,
+            self.market_key,
+            self.back_stake,
+            self.back_odds,
+            self.bookie_name,
+            self.lay_stake,
+            self.lay_odds,
+            self.exchange_name,
+            100.0 * self.commission,
+            self.liability,
+            self.profit,
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+
+use chrono::{TimeZone, Utc};
+
+use crate::odds_interface::logic::{
+    ledger::{format_summary_table, summarise, summarise_by, Bet, BetStatus, DRAW_OUTCOME},
+    market::{MarketType, OVER_OUTCOME, UNDER_OUTCOME},
+    odds::Odds,
+    score::EventScore,
+};
+
+/// St Kilda Saints 88, Brisbane Lions 81
+fn get_afl_score() -> EventScore {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_scores.json")
+        .expect("Unable to read file");
+    let scores = serde_json::from_str::<Vec<EventScore>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+    return scores.into_iter().next().unwrap();
+}
+
+fn get_afl_bet(market_key: MarketType, outcome_key: &str, point: Option<f64>) -> Bet {
+    return Bet {
+        id: None,
+        event_id: String::from("132905fc8e35fe3551638c40c80fe9e6"),
+        sport_key: String::from("aussierules_afl"),
+        home_team: String::from("St Kilda Saints"),
+        away_team: String::from("Brisbane Lions"),
+        commence_time: Utc.with_ymd_and_hms(2024, 8, 4, 6, 40, 0).unwrap(),
+        market_key,
+        outcome_key: outcome_key.to_string(),
+        point,
+        bookmaker_key: String::from("sportsbet"),
+        odds: Odds::Decimal(2.5),
+        stake: 10.0,
+        placed_at: Utc.with_ymd_and_hms(2024, 8, 4, 5, 20, 0).unwrap(),
+        status: BetStatus::Pending,
+        settled_at: None,
+    };
+}
+
+#[test]
+fn test_grade_h2h() {
+    let score = get_afl_score();
+    let grade = |outcome_key: &str| get_afl_bet(MarketType::H2h, outcome_key, None).grade(&score);
+
+    assert_eq!(grade("St Kilda Saints"), BetStatus::Won);
+    assert_eq!(grade("Brisbane Lions"), BetStatus::Lost);
+    assert_eq!(grade(DRAW_OUTCOME), BetStatus::Lost);
+    assert_eq!(grade("Carlton Blues"), BetStatus::Void);
+}
+
+#[test]
+fn test_grade_totals_and_spreads() {
+    let score = get_afl_score();
+    let grade = |market_key: MarketType, outcome_key: &str, point: f64| {
+        return get_afl_bet(market_key, outcome_key, Some(point)).grade(&score);
+    };
+
+    // 169 total points
+    assert_eq!(
+        grade(MarketType::Totals, OVER_OUTCOME, 160.5),
+        BetStatus::Won
+    );
+    assert_eq!(
+        grade(MarketType::Totals, UNDER_OUTCOME, 160.5),
+        BetStatus::Lost
+    );
+    assert_eq!(
+        grade(MarketType::Totals, OVER_OUTCOME, 169.0),
+        BetStatus::Push
+    );
+
+    // saints won by 7
+    assert_eq!(
+        grade(MarketType::Spreads, "Brisbane Lions", 7.5),
+        BetStatus::Won
+    );
+    assert_eq!(
+        grade(MarketType::Spreads, "St Kilda Saints", -7.5),
+        BetStatus::Lost
+    );
+    assert_eq!(
+        grade(MarketType::Spreads, "Brisbane Lions", 7.0),
+        BetStatus::Push
+    );
+
+    let no_line = get_afl_bet(MarketType::Totals, OVER_OUTCOME, None);
+    assert_eq!(no_line.grade(&score), BetStatus::Void);
+}
+
+#[test]
+fn test_grade_unfinished_event() {
+    let mut score = get_afl_score();
+    score.completed = false;
+    let bet = get_afl_bet(MarketType::H2h, "St Kilda Saints", None);
+    assert_eq!(bet.grade(&score), BetStatus::Pending);
+}
+
+#[test]
+fn test_summarise() {
+    let mut won = get_afl_bet(MarketType::H2h, "St Kilda Saints", None);
+    won.status = BetStatus::Won;
+    let mut lost = get_afl_bet(MarketType::Totals, OVER_OUTCOME, Some(180.5));
+    lost.status = BetStatus::Lost;
+    lost.bookmaker_key = String::from("tab");
+    let mut push = get_afl_bet(MarketType::Spreads, "Brisbane Lions", Some(7.0));
+    push.status = BetStatus::Push;
+    let pending = get_afl_bet(MarketType::H2h, "Brisbane Lions", None);
+    let bets = [won, lost, push, pending];
+
+    let summary = summarise(&bets.iter().collect::<Vec<&Bet>>(), 1000.0);
+    assert_eq!(summary.settled, 3);
+    assert_eq!(summary.pending, 1);
+    assert_eq!((summary.won, summary.lost), (1, 1));
+    // +15 on the win, -10 on the loss and the push returned
+    assert!((summary.profit - 5.0).abs() < 1e-9);
+    assert!((summary.turnover - 30.0).abs() < 1e-9);
+    assert!((summary.roi - 0.5).abs() < 1e-9);
+    assert!((summary.yield_percent - 100.0 * 5.0 / 30.0).abs() < 1e-9);
+
+    let by_bookmaker = summarise_by(&bets, 1000.0, |x| x.bookmaker_key.clone());
+    assert!((by_bookmaker["tab"].profit + 10.0).abs() < 1e-9);
+    assert!((by_bookmaker["sportsbet"].profit - 15.0).abs() < 1e-9);
+
+    let table = format_summary_table("bookmaker", &by_bookmaker);
+    assert_eq!(table.lines().count(), 3);
+    assert!(table.lines().nth(1).unwrap().starts_with("sportsbet"));
+}
@@ -0,0 +1,237 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use strum_macros::EnumString;
+
+use super::market::{MarketType, OVER_OUTCOME, UNDER_OUTCOME};
+use super::odds::Odds;
+use super::score::EventScore;
+
+#[cfg(test)]
+mod ledger_test;
+
+pub const DRAW_OUTCOME: &str = "Draw";
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum BetStatus {
+    /// Not settled yet, or in a market we can't grade from scores
+    Pending,
+    Won,
+    Lost,
+    /// Landed exactly on the line, so the stake is returned
+    Push,
+    /// The result couldn't be worked out, so the stake is returned
+    Void,
+}
+
+impl fmt::Display for BetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BetStatus::Pending => write!(f, "pending"),
+            BetStatus::Won => write!(f, "won"),
+            BetStatus::Lost => write!(f, "lost"),
+            BetStatus::Push => write!(f, "push"),
+            BetStatus::Void => write!(f, "void"),
+        }
+    }
+}
+
+/// A bet we actually placed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bet {
+    /// Row id once the bet is in the ledger
+    pub id: Option<i64>,
+    pub event_id: String,
+    pub sport_key: String,
+    pub home_team: String,
+    pub away_team: String,
+    pub commence_time: DateTime<Utc>,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    /// Line for totals and spreads bets
+    pub point: Option<f64>,
+    pub bookmaker_key: String,
+    pub odds: Odds,
+    pub stake: f64,
+    pub placed_at: DateTime<Utc>,
+    pub status: BetStatus,
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+/// Won when above zero, lost when below and a push at zero
+fn status_from_margin(margin: f64) -> BetStatus {
+    if (margin > 0.0) {
+        return BetStatus::Won;
+    } else if (margin < 0.0) {
+        return BetStatus::Lost;
+    }
+    return BetStatus::Push;
+}
+
+/// Grades an outcome of a market against the final score of its event.
+/// Two-way h2h outcomes on a tied game are a push.
+pub fn grade_outcome(
+    market: &MarketType,
+    outcome_key: &str,
+    point: Option<f64>,
+    score: &EventScore,
+) -> BetStatus {
+    if (!score.completed) {
+        return BetStatus::Pending;
+    }
+
+    let (home_score, away_score) = match (
+        score.score_for(&score.home_team),
+        score.score_for(&score.away_team),
+    ) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return BetStatus::Void,
+    };
+
+    // the outcome's team first, then its opponent
+    let team_scores = if (outcome_key == score.home_team) {
+        Some((home_score, away_score))
+    } else if (outcome_key == score.away_team) {
+        Some((away_score, home_score))
+    } else {
+        None
+    };
+
+    match market {
+        MarketType::H2h => {
+            if (outcome_key == DRAW_OUTCOME) {
+                return match home_score == away_score {
+                    true => BetStatus::Won,
+                    false => BetStatus::Lost,
+                };
+            }
+            return match team_scores {
+                Some((team, opponent)) => status_from_margin(team - opponent),
+                None => BetStatus::Void,
+            };
+        }
+        MarketType::Totals => {
+            let total = home_score + away_score;
+            return match (point, outcome_key) {
+                (Some(point), OVER_OUTCOME) => status_from_margin(total - point),
+                (Some(point), UNDER_OUTCOME) => status_from_margin(point - total),
+                _ => BetStatus::Void,
+            };
+        }
+        MarketType::Spreads => {
+            return match (point, team_scores) {
+                (Some(point), Some((team, opponent))) => {
+                    status_from_margin(team + point - opponent)
+                }
+                _ => BetStatus::Void,
+            };
+        }
+        _ => return BetStatus::Pending,
+    }
+}
+
+impl Bet {
+    /// Grades the bet against the final score of its event
+    pub fn grade(&self, score: &EventScore) -> BetStatus {
+        return grade_outcome(&self.market_key, &self.outcome_key, self.point, score);
+    }
+
+    /// Profit once settled, stakes on pushes and voids being returned
+    pub fn profit(&self) -> Option<f64> {
+        match self.status {
+            BetStatus::Pending => return None,
+            BetStatus::Won => return Some(self.stake * (self.odds.get_decimal() - 1.0)),
+            BetStatus::Lost => return Some(-self.stake),
+            BetStatus::Push | BetStatus::Void => return Some(0.0),
+        }
+    }
+}
+
+/// How a set of bets went
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PerformanceSummary {
+    pub settled: usize,
+    pub pending: usize,
+    pub won: usize,
+    pub lost: usize,
+    /// Total staked on settled bets
+    pub turnover: f64,
+    pub profit: f64,
+    /// Profit as a percentage of the bankroll
+    pub roi: f64,
+    /// Profit as a percentage of turnover
+    pub yield_percent: f64,
+}
+
+pub fn summarise(bets: &[&Bet], bankroll: f64) -> PerformanceSummary {
+    let mut summary = PerformanceSummary::default();
+    for bet in bets {
+        let profit = match bet.profit() {
+            Some(x) => x,
+            None => {
+                summary.pending += 1;
+                continue;
+            }
+        };
+
+        summary.settled += 1;
+        summary.turnover += bet.stake;
+        summary.profit += profit;
+        match bet.status {
+            BetStatus::Won => summary.won += 1,
+            BetStatus::Lost => summary.lost += 1,
+            _ => (),
+        }
+    }
+
+    if (bankroll > 0.0) {
+        summary.roi = 100.0 * summary.profit / bankroll;
+    }
+    if (summary.turnover > 0.0) {
+        summary.yield_percent = 100.0 * summary.profit / summary.turnover;
+    }
+    return summary;
+}
+
+/// Summaries of `bets` grouped by `group_key`, e.g. sport or bookmaker
+pub fn summarise_by<F: Fn(&Bet) -> String>(
+    bets: &[Bet],
+    bankroll: f64,
+    group_key: F,
+) -> BTreeMap<String, PerformanceSummary> {
+    let mut groups: BTreeMap<String, Vec<&Bet>> = BTreeMap::new();
+    for bet in bets {
+        groups.entry(group_key(bet)).or_default().push(bet);
+    }
+
+    return groups
+        .into_iter()
+        .map(|(key, group)| (key, summarise(&group, bankroll)))
+        .collect();
+}
+
+/// Plain text table of `summaries`, one row per group
+pub fn format_summary_table(
+    title: &str,
+    summaries: &BTreeMap<String, PerformanceSummary>,
+) -> String {
+    let mut table = format!(
+        "{title:<24} {0:>7} {1:>7} {2:>5} {3:>5} {4:>10} {5:>10} {6:>8} {7:>8}\n",
+        "settled", "pending", "won", "lost", "turnover", "profit", "roi %", "yield %"
+    );
+    for (key, summary) in summaries {
+        table += &format!(
+            "{key:<24} {0:>7} {1:>7} {2:>5} {3:>5} {4:>10.2} {5:>10.2} {6:>8.2} {7:>8.2}\n",
+            summary.settled,
+            summary.pending,
+            summary.won,
+            summary.lost,
+            summary.turnover,
+            summary.profit,
+            summary.roi,
+            summary.yield_percent
+        );
+    }
+    return table;
+}
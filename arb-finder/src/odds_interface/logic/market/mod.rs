@@ -1,8 +1,9 @@
 use core::fmt;
 use serde::Deserialize;
 use std::collections::HashSet;
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, EnumString};
 
+use super::devig::DevigMethod;
 use super::odds::Odds;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -11,8 +12,9 @@ pub struct Market {
     pub outcomes: Vec<Outcome>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, EnumIter)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash, EnumIter, EnumString)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum MarketType {
     H2h,
     H2hLay,
@@ -73,17 +75,20 @@ impl Market {
             .fold(0.0, |sum, outcome| sum + outcome.implied_probability());
     }
 
-    pub fn true_probability_for_outcome(&self, outcome_key: &str) -> Option<f64> {
+    pub fn true_probability_for_outcome(
+        &self,
+        outcome_key: &str,
+        method: &DevigMethod,
+    ) -> Option<f64> {
         match self.find_outcome(outcome_key) {
-            Some(outcome) => return Some(self.true_probability_estimate(&outcome.price)),
+            Some(outcome) => return Some(self.true_probability_estimate(&outcome.price, method)),
             None => return None,
         }
     }
 
-    // https://cran.r-project.org/web/packages/implied/vignettes/introduction.html
-    pub fn true_probability_estimate(&self, odds: &Odds) -> f64 {
+    pub fn true_probability_estimate(&self, odds: &Odds, method: &DevigMethod) -> f64 {
         let all_odds: Vec<Odds> = self.outcomes.iter().map(|x| x.price.clone()).collect();
-        return odds.true_probability_estimate(&all_odds);
+        return odds.true_probability_estimate(&all_odds, method);
     }
 }
 
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use crate::odds_interface::logic::{
+    devig::DevigMethod,
     market::{Market, MarketType, Outcome},
     odds::Odds,
 };
@@ -85,17 +86,32 @@ fn test_total_probability() {
 #[test]
 fn test_true_probability_for_outcome() {
     let market = get_market(true);
+    let method = DevigMethod::Additive;
 
     assert!(
         (market
-            .true_probability_for_outcome("Sydney Roosters")
+            .true_probability_for_outcome("Sydney Roosters", &method)
             .unwrap()
             - 0.799)
             .abs()
             < 0.01
     );
 
-    assert!((market.true_probability_for_outcome("Wests Tigers").unwrap() - 0.137).abs() < 0.01);
+    assert!(
+        (market
+            .true_probability_for_outcome("Wests Tigers", &method)
+            .unwrap()
+            - 0.137)
+            .abs()
+            < 0.01
+    );
 
-    assert!((market.true_probability_for_outcome("Draw").unwrap() - 0.063).abs() < 0.01);
+    assert!(
+        (market
+            .true_probability_for_outcome("Draw", &method)
+            .unwrap()
+            - 0.063)
+            .abs()
+            < 0.01
+    );
 }
@@ -1,26 +1,24 @@
+pub mod alerts;
+pub mod arbitrage;
+pub mod backtest;
 pub mod bookmaker;
+pub mod calibration;
+pub mod clv;
+pub mod devig;
 pub mod event;
+pub mod exchange;
+pub mod ledger;
 pub mod market;
+pub mod movement;
 pub mod odds;
+pub mod outrights;
+pub mod score;
+pub mod settings;
+pub mod sharpness;
 pub mod sport;
+pub mod spreads;
+pub mod staking;
+pub mod weights;
 
 #[cfg(test)]
 mod test;
-
-const AU_BOOKS: [&str; 13] = [
-    "betfair_ex_au",
-    "betr_au",
-    "betright",
-    "bluebet",
-    "ladbrokes_au",
-    "neds",
-    "playup",
-    "pointsbetau",
-    "sportsbet",
-    "tab",
-    "tabtouch",
-    "topsport",
-    "unibet",
-];
-
-const AUS_ONLY: bool = false;
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use serde::Deserialize;
+use strum_macros::EnumString;
+
+use super::alerts::{AlertRecord, OpportunityKey};
+use super::event::matchup;
+use super::market::MarketType;
+use super::odds::{Odds, OddsFormat};
+
+const DEFAULT_WINDOW_MINUTES: i64 = 15;
+const DEFAULT_MIN_PROBABILITY_MOVE: f64 = 0.02;
+const DEFAULT_MIN_BOOKS: usize = 3;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MovementSettings {
+    /// Books have to move within this long for it to be steam, as slow
+    /// drift isn't
+    pub window_minutes: i64,
+    /// Smallest change in a book's fair probability that counts as a move,
+    /// e.g. 0.02 for two percentage points
+    pub min_probability_move: f64,
+    /// How many books have to move the same way for it to be steam
+    pub min_books: usize,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        return MovementSettings {
+            window_minutes: DEFAULT_WINDOW_MINUTES,
+            min_probability_move: DEFAULT_MIN_PROBABILITY_MOVE,
+            min_books: DEFAULT_MIN_BOOKS,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum MoveDirection {
+    /// The outcome got more likely and its price shorter
+    Shortening,
+    /// The outcome got less likely and its price longer
+    Drifting,
+}
+
+impl fmt::Display for MoveDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MoveDirection::Shortening => write!(f, "shortening"),
+            MoveDirection::Drifting => write!(f, "drifting"),
+        }
+    }
+}
+
+/// A book that hasn't followed the move, and still offers value against
+/// the post-move fair price
+#[derive(Debug, Clone)]
+pub struct StaleQuote {
+    pub bookie_key: String,
+    pub bookie_name: String,
+    pub offered_odds: Odds,
+    pub percent_ev: f64,
+}
+
+/// Several books moving one outcome the same way between two fetches of
+/// an event
+#[derive(Debug, Clone)]
+pub struct SteamMove {
+    pub event_id: String,
+    pub sport_key: String,
+    pub sport_title: String,
+    pub commence_time: DateTime<Utc>,
+    pub home_team: String,
+    pub away_team: String,
+    pub market_key: MarketType,
+    pub outcome_key: String,
+    /// Line for totals and spreads
+    pub point: Option<f64>,
+    pub direction: MoveDirection,
+    /// Titles of the books that moved
+    pub books_moved: Vec<String>,
+    /// Consensus of every book before the move
+    pub pre_move_fair: Odds,
+    /// Consensus of the books that moved, after the move
+    pub post_move_fair: Odds,
+    pub elapsed_minutes: f64,
+    pub stale_quotes: Vec<StaleQuote>,
+}
+
+impl SteamMove {
+    pub fn matchup(&self) -> String {
+        return matchup(&self.home_team, &self.away_team);
+    }
+
+    /// The same move in later scans has the same key, whichever books
+    /// moved
+    pub fn key(&self) -> OpportunityKey {
+        return OpportunityKey {
+            event_id: self.event_id.clone(),
+            market_key: self.market_key.clone(),
+            outcome_key: self.outcome_key.clone(),
+            point: self.point,
+            bookmaker_key: String::new(),
+            direction: Some(self.direction),
+        };
+    }
+
+    /// How far the fair probability moved, in percentage points
+    pub fn probability_move(&self) -> f64 {
+        let before = self.pre_move_fair.implied_probability();
+        let after = self.post_move_fair.implied_probability();
+        return (after - before).abs() * 100.0;
+    }
+
+    /// The record of alerting on this move at `alerted_at`, with its size
+    /// in place of an EV so it's alerted again if it grows
+    pub fn alert(&self, alerted_at: DateTime<Utc>) -> AlertRecord {
+        return AlertRecord {
+            key: self.key(),
+            sport_key: self.sport_key.clone(),
+            commence_time: self.commence_time,
+            matchup: self.matchup(),
+            offered_odds: self.post_move_fair,
+            percent_ev: self.probability_move(),
+            alerted_at,
+            gone_at: None,
+        };
+    }
+
+    pub fn format_message(&self, odds_format: &OddsFormat) -> String {
+        let mut outcome = self.outcome_key.clone();
+        if let Some(point) = self.point {
+            outcome += &format!(" {point}");
+        }
+        let mut markdown_str = format!(
+            " ### Steam move on {0}
+    - {1}
+    - Outcome: {2} ({3})
+    - Market: {4}
+    - Fair odds: {5} -> {6} in {7:.0} minutes
+    - Moved: {8}
+",
+            self.sport_title,
+            self.matchup(),
+            outcome,
+            self.direction,
+            self.market_key,
+            self.pre_move_fair.display_as(odds_format),
+            self.post_move_fair.display_as(odds_format),
+            self.elapsed_minutes,
+            self.books_moved.join(", "),
+        );
+
+        for quote in &self.stale_quotes {
+            markdown_str += &format!(
+                "    - Stale: {0} at {1} ({2:.2}%)\n",
+                quote.bookie_name,
+                quote.offered_odds.display_as(odds_format),
+                quote.percent_ev
+            );
+        }
+
+        return markdown_str;
+    }
+}
+
+impl fmt::Display for SteamMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.format_message(&OddsFormat::Decimal));
+    }
+}
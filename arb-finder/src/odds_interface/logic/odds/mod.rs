@@ -1,9 +1,17 @@
 use serde::Deserialize;
 use std::{
     cmp::{self, Ordering},
-    ops,
+    fmt, ops,
+    str::FromStr,
 };
 
+use super::devig::DevigMethod;
+
+#[cfg(test)]
+mod odds_test;
+
+const MAX_FRACTIONAL_DENOMINATOR: u32 = 100;
+
 fn american_to_decimal(american_odds: i32) -> f64 {
     let f_american_odds = f64::from(american_odds);
 
@@ -19,11 +27,126 @@ fn american_to_decimal(american_odds: i32) -> f64 {
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Copy)]
+fn decimal_to_american(decimal_odds: f64) -> i32 {
+    if (decimal_odds >= 2.0) {
+        return ((decimal_odds - 1.0) * 100.0).round() as i32;
+    }
+    return (-100.0 / (decimal_odds - 1.0)).round() as i32;
+}
+
+/// Closest fraction to the decimal profit with a bounded denominator,
+/// found by walking the continued fraction expansion
+fn decimal_to_fractional(decimal_odds: f64) -> (u32, u32) {
+    let target = decimal_odds - 1.0;
+
+    let (mut prev_num, mut num) = (0u64, 1u64);
+    let (mut prev_den, mut den) = (1u64, 0u64);
+    let mut remainder = target;
+
+    loop {
+        let whole = remainder.floor();
+        let next_num = whole as u64 * num + prev_num;
+        let next_den = whole as u64 * den + prev_den;
+
+        if (next_den > MAX_FRACTIONAL_DENOMINATOR as u64) {
+            break;
+        }
+
+        (prev_num, num) = (num, next_num);
+        (prev_den, den) = (den, next_den);
+
+        let fractional_part = remainder - whole;
+        if (fractional_part < 1e-9) {
+            break;
+        }
+        remainder = 1.0 / fractional_part;
+    }
+
+    if (den == 0) {
+        return ((target.round() as u32).max(1), 1);
+    }
+    return (num as u32, den as u32);
+}
+
+/// Malaysian and Indonesian odds are quoted as profit on a unit stake when
+/// positive, and as the stake needed to win one unit when negative
+fn signed_profit_to_decimal(signed_odds: f64) -> f64 {
+    if (signed_odds > 0.0) {
+        return 1.0 + signed_odds;
+    }
+    return 1.0 + 1.0 / -signed_odds;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOddsError {
+    input: String,
+}
+
+impl fmt::Display for ParseOddsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "could not parse {:?} as odds", self.input);
+    }
+}
+
+impl std::error::Error for ParseOddsError {}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OddsFormat {
+    American,
+    Decimal,
+    Fractional,
+    HongKong,
+    Malay,
+    Indonesian,
+}
+
+impl fmt::Display for OddsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OddsFormat::American => write!(f, "american"),
+            OddsFormat::Decimal => write!(f, "decimal"),
+            OddsFormat::Fractional => write!(f, "fractional"),
+            OddsFormat::HongKong => write!(f, "hong_kong"),
+            OddsFormat::Malay => write!(f, "malay"),
+            OddsFormat::Indonesian => write!(f, "indonesian"),
+        }
+    }
+}
+
+/// Shape of a price as it arrives over the wire: the Odds API sends
+/// american prices as integers and decimal prices as floats, anything
+/// else has to be a string we know how to parse
+#[derive(Deserialize)]
 #[serde(untagged)]
+enum RawOdds {
+    American(i32),
+    Decimal(f64),
+    Formatted(String),
+}
+
+impl TryFrom<RawOdds> for Odds {
+    type Error = ParseOddsError;
+
+    fn try_from(raw: RawOdds) -> Result<Self, Self::Error> {
+        return match raw {
+            RawOdds::American(x) => Ok(Odds::American(x)),
+            RawOdds::Decimal(x) => Ok(Odds::Decimal(x)),
+            RawOdds::Formatted(x) => x.parse::<Odds>(),
+        };
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Copy)]
+#[serde(try_from = "RawOdds")]
 pub enum Odds {
     American(i32),
     Decimal(f64),
+    /// numerator, denominator
+    Fractional(u32, u32),
+    HongKong(f64),
+    Malay(f64),
+    Indonesian(f64),
 }
 
 impl Odds {
@@ -31,27 +154,101 @@ impl Odds {
         return Odds::Decimal(self.get_decimal());
     }
 
+    pub fn as_format(self, format: &OddsFormat) -> Self {
+        let decimal = self.get_decimal();
+        return match format {
+            OddsFormat::American => Odds::American(decimal_to_american(decimal)),
+            OddsFormat::Decimal => Odds::Decimal(decimal),
+            OddsFormat::Fractional => {
+                let (numerator, denominator) = decimal_to_fractional(decimal);
+                Odds::Fractional(numerator, denominator)
+            }
+            OddsFormat::HongKong => Odds::HongKong(decimal - 1.0),
+            OddsFormat::Malay => {
+                if (decimal <= 2.0) {
+                    Odds::Malay(decimal - 1.0)
+                } else {
+                    Odds::Malay(-1.0 / (decimal - 1.0))
+                }
+            }
+            OddsFormat::Indonesian => {
+                if (decimal >= 2.0) {
+                    Odds::Indonesian(decimal - 1.0)
+                } else {
+                    Odds::Indonesian(-1.0 / (decimal - 1.0))
+                }
+            }
+        };
+    }
+
     pub fn get_decimal(&self) -> f64 {
         return match self {
             Odds::American(x) => american_to_decimal(*x),
             Odds::Decimal(x) => *x,
+            Odds::Fractional(numerator, denominator) => {
+                1.0 + f64::from(*numerator) / f64::from(*denominator)
+            }
+            Odds::HongKong(x) => 1.0 + *x,
+            Odds::Malay(x) => signed_profit_to_decimal(*x),
+            Odds::Indonesian(x) => signed_profit_to_decimal(*x),
         };
     }
 
-    pub fn implied_probability(&self) -> f64 {
-        return 1.0 / self.get_decimal();
+    /// Reads a price written in a known format, e.g. "5/2" fractional or
+    /// "-0.85" malay
+    pub fn parse_as(raw: &str, format: &OddsFormat) -> Result<Odds, ParseOddsError> {
+        let trimmed = raw.trim();
+        let error = ParseOddsError {
+            input: raw.to_string(),
+        };
+
+        if (*format == OddsFormat::Fractional) {
+            let (numerator, denominator) = match trimmed.split_once('/') {
+                Some(x) => x,
+                None => return Err(error),
+            };
+            return match (
+                numerator.trim().parse::<u32>(),
+                denominator.trim().parse::<u32>(),
+            ) {
+                (Ok(_), Ok(0)) => Err(error),
+                (Ok(n), Ok(d)) => Ok(Odds::Fractional(n, d)),
+                _ => Err(error),
+            };
+        }
+
+        if (*format == OddsFormat::American) {
+            return match trimmed.trim_start_matches('+').parse::<i32>() {
+                Ok(x) if x.abs() >= 100 => Ok(Odds::American(x)),
+                _ => Err(error),
+            };
+        }
+
+        let value = match trimmed.parse::<f64>() {
+            Ok(x) if x.is_finite() => x,
+            _ => return Err(error),
+        };
+
+        return match format {
+            OddsFormat::Decimal if value > 1.0 => Ok(Odds::Decimal(value)),
+            OddsFormat::HongKong if value > 0.0 => Ok(Odds::HongKong(value)),
+            OddsFormat::Malay if value != 0.0 && value.abs() <= 1.0 => Ok(Odds::Malay(value)),
+            OddsFormat::Indonesian if value.abs() >= 1.0 => Ok(Odds::Indonesian(value)),
+            _ => Err(error),
+        };
     }
 
-    pub fn true_probability_estimate(&self, all_odds: &Vec<Odds>) -> f64 {
-        let total_probaility = all_odds
-            .iter()
-            .fold(0.0, |acc, x| acc + x.implied_probability());
+    /// Writes the price out in `format`, regardless of how it was quoted
+    pub fn display_as(&self, format: &OddsFormat) -> String {
+        return self.as_format(format).to_string();
+    }
 
-        let margin = total_probaility - 1.0;
-        let n = all_odds.len() as f64;
-        let raw_odds = self.get_decimal();
+    pub fn implied_probability(&self) -> f64 {
+        return 1.0 / self.get_decimal();
+    }
 
-        return (n - margin * raw_odds) / (n * raw_odds);
+    pub fn true_probability_estimate(&self, all_odds: &Vec<Odds>, method: &DevigMethod) -> f64 {
+        return method.true_probability(self, all_odds);
     }
 
     pub fn ev_percentage(&self, true_odds: &Odds) -> f64 {
@@ -59,6 +256,45 @@ impl Odds {
     }
 }
 
+impl FromStr for Odds {
+    type Err = ParseOddsError;
+
+    /// Guesses the format from the shape of the string: fractions contain a
+    /// slash, american prices are signed whole numbers of at least 100,
+    /// values between -1 and 1 are malay and anything else is decimal
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+
+        if (trimmed.contains('/')) {
+            return Odds::parse_as(trimmed, &OddsFormat::Fractional);
+        }
+
+        if let Ok(american) = Odds::parse_as(trimmed, &OddsFormat::American) {
+            return Ok(american);
+        }
+
+        if let Ok(malay) = Odds::parse_as(trimmed, &OddsFormat::Malay) {
+            return Ok(malay);
+        }
+
+        return Odds::parse_as(trimmed, &OddsFormat::Decimal);
+    }
+}
+
+impl fmt::Display for Odds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Odds::American(x) if *x > 0 => write!(f, "+{x}"),
+            Odds::American(x) => write!(f, "{x}"),
+            Odds::Decimal(x) => write!(f, "{x:.2}"),
+            Odds::Fractional(numerator, denominator) => write!(f, "{numerator}/{denominator}"),
+            Odds::HongKong(x) => write!(f, "{x:.2}"),
+            Odds::Malay(x) => write!(f, "{x:.2}"),
+            Odds::Indonesian(x) => write!(f, "{x:.2}"),
+        }
+    }
+}
+
 impl ops::Add<Odds> for Odds {
     type Output = Odds;
     fn add(self, _rhs: Self) -> Self {
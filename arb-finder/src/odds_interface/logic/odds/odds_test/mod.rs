@@ -0,0 +1,88 @@
+use crate::odds_interface::logic::odds::{Odds, OddsFormat};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_get_decimal_for_every_format() {
+    assert_close(Odds::American(150).get_decimal(), 2.5);
+    assert_close(Odds::American(-200).get_decimal(), 1.5);
+    assert_close(Odds::Fractional(5, 2).get_decimal(), 3.5);
+    assert_close(Odds::HongKong(0.85).get_decimal(), 1.85);
+    assert_close(Odds::Malay(0.5).get_decimal(), 1.5);
+    assert_close(Odds::Malay(-0.5).get_decimal(), 3.0);
+    assert_close(Odds::Indonesian(2.0).get_decimal(), 3.0);
+    assert_close(Odds::Indonesian(-2.0).get_decimal(), 1.5);
+}
+
+#[test]
+fn test_as_format_round_trips() {
+    let formats = [
+        OddsFormat::American,
+        OddsFormat::Decimal,
+        OddsFormat::Fractional,
+        OddsFormat::HongKong,
+        OddsFormat::Malay,
+        OddsFormat::Indonesian,
+    ];
+
+    for decimal in [1.25, 1.5, 2.0, 2.5, 3.5, 11.0] {
+        for format in &formats {
+            let converted = Odds::Decimal(decimal).as_format(format);
+            assert_close(converted.get_decimal(), decimal);
+        }
+    }
+}
+
+#[test]
+fn test_parse_odds_strings() {
+    assert_eq!("5/2".parse::<Odds>(), Ok(Odds::Fractional(5, 2)));
+    assert_eq!("-0.85".parse::<Odds>(), Ok(Odds::Malay(-0.85)));
+    assert_eq!("+150".parse::<Odds>(), Ok(Odds::American(150)));
+    assert_eq!("-110".parse::<Odds>(), Ok(Odds::American(-110)));
+    assert_eq!("1.91".parse::<Odds>(), Ok(Odds::Decimal(1.91)));
+
+    assert_eq!(
+        Odds::parse_as("-1.25", &OddsFormat::Indonesian),
+        Ok(Odds::Indonesian(-1.25))
+    );
+    assert_eq!(
+        Odds::parse_as("0.85", &OddsFormat::HongKong),
+        Ok(Odds::HongKong(0.85))
+    );
+
+    assert!("5/0".parse::<Odds>().is_err());
+    assert!("evens".parse::<Odds>().is_err());
+    assert!(Odds::parse_as("-0.85", &OddsFormat::Indonesian).is_err());
+}
+
+#[test]
+fn test_display_as() {
+    let odds = Odds::Decimal(3.5);
+
+    assert_eq!(odds.display_as(&OddsFormat::Decimal), "3.50");
+    assert_eq!(odds.display_as(&OddsFormat::American), "+250");
+    assert_eq!(odds.display_as(&OddsFormat::Fractional), "5/2");
+    assert_eq!(odds.display_as(&OddsFormat::HongKong), "2.50");
+    assert_eq!(odds.display_as(&OddsFormat::Malay), "-0.40");
+    assert_eq!(odds.display_as(&OddsFormat::Indonesian), "2.50");
+    assert_eq!(Odds::Decimal(1.5).display_as(&OddsFormat::American), "-200");
+}
+
+#[test]
+fn test_deserialize_odds() {
+    let odds: Vec<Odds> = serde_json::from_str(r#"[1.9, -110, "11/10"]"#).unwrap();
+    assert_eq!(
+        odds,
+        [
+            Odds::Decimal(1.9),
+            Odds::American(-110),
+            Odds::Fractional(11, 10)
+        ]
+        .to_vec()
+    );
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::bookmaker::Bookmaker;
+use super::devig::DevigMethod;
+use super::market::MarketType;
+use super::odds::Odds;
+
+#[cfg(test)]
+mod outrights_test;
+
+/// Smallest fair probability we give a runner, as some de-vig methods can
+/// push long shots in big fields below zero
+const MIN_RUNNER_PROBABILITY: f64 = 1e-6;
+
+/// Outright markets have dozens of runners, mostly long shots, so they get
+/// their own limits instead of `max_odds_cutoff`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct OutrightSettings {
+    /// Runners with consensus fair odds above this are skipped
+    pub max_fair_odds: f64,
+    /// Runners quoted by fewer books than this are skipped
+    pub min_books: usize,
+    /// A price at least this many times the consensus fair odds is marked as
+    /// an outlier, which is as likely to be a stale or mistaken price as value
+    pub outlier_ratio: f64,
+}
+
+impl Default for OutrightSettings {
+    fn default() -> Self {
+        return OutrightSettings {
+            max_fair_odds: 101.0,
+            min_books: 2,
+            outlier_ratio: 1.5,
+        };
+    }
+}
+
+/// One book's price for a runner, and its fair probability within that
+/// book's own market
+#[derive(Debug, Clone)]
+pub struct RunnerPrice {
+    pub bookie_key: String,
+    pub bookie_name: String,
+    pub odds: Odds,
+    pub fair_probability: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutrightRunner {
+    pub name: String,
+    /// Average fair probability across the books quoting the runner,
+    /// normalised so every runner in the field adds up to one
+    pub consensus_probability: f64,
+    pub prices: Vec<RunnerPrice>,
+}
+
+impl OutrightRunner {
+    pub fn fair_odds(&self) -> Odds {
+        return Odds::Decimal(1.0 / self.consensus_probability);
+    }
+
+    /// How many times the consensus fair odds `price` pays
+    pub fn price_ratio(&self, price: &RunnerPrice) -> f64 {
+        return price.odds.get_decimal() / self.fair_odds().get_decimal();
+    }
+
+    /// Prices far enough above the consensus to be suspect
+    pub fn outliers(&self, settings: &OutrightSettings) -> Vec<&RunnerPrice> {
+        return self
+            .prices
+            .iter()
+            .filter(|x| self.price_ratio(x) >= settings.outlier_ratio)
+            .collect();
+    }
+}
+
+/// Consensus fair probabilities for every runner any book lists.
+///
+/// Each book's market is de-vigged over the runners it lists. Books often
+/// leave runners out, so a runner's consensus only averages the books that
+/// quote it, and the field is then normalised back to one.
+pub fn consensus_runners(bookmakers: &[Bookmaker], method: &DevigMethod) -> Vec<OutrightRunner> {
+    let mut prices_by_runner: HashMap<String, Vec<RunnerPrice>> = HashMap::new();
+
+    for bookie in bookmakers {
+        let outcomes = bookie.get_offered_outcomes(&MarketType::Outrights);
+        let all_odds: Vec<Odds> = outcomes.iter().map(|x| x.price).collect();
+
+        for outcome in &outcomes {
+            let fair_probability = method
+                .true_probability(&outcome.price, &all_odds)
+                .max(MIN_RUNNER_PROBABILITY);
+
+            prices_by_runner
+                .entry(outcome.name.clone())
+                .or_default()
+                .push(RunnerPrice {
+                    bookie_key: bookie.key.clone(),
+                    bookie_name: bookie.title.clone(),
+                    odds: outcome.price,
+                    fair_probability,
+                });
+        }
+    }
+
+    let mut runners: Vec<OutrightRunner> = prices_by_runner
+        .into_iter()
+        .map(|(name, prices)| {
+            let average =
+                prices.iter().map(|x| x.fair_probability).sum::<f64>() / prices.len() as f64;
+            return OutrightRunner {
+                name,
+                consensus_probability: average,
+                prices,
+            };
+        })
+        .collect();
+
+    let total: f64 = runners.iter().map(|x| x.consensus_probability).sum();
+    if (total > 0.0) {
+        for runner in runners.iter_mut() {
+            runner.consensus_probability /= total;
+        }
+    }
+
+    runners.sort_by(|a, b| b.consensus_probability.total_cmp(&a.consensus_probability));
+    return runners;
+}
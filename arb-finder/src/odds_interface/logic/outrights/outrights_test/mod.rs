@@ -0,0 +1,102 @@
+use std::fs;
+
+use serde_json::Value;
+
+use crate::odds_interface::logic::{
+    bookmaker::Bookmaker,
+    devig::DevigMethod,
+    event::Event,
+    market::MarketType,
+    outrights::{consensus_runners, OutrightSettings},
+    settings::ScanSettings,
+};
+
+/// ### Input data:
+/// - **Market**: Masters Tournament winner, 14 runners
+/// - **Bookies**: *sportsbet*, *unibet* list every runner, *tab* leaves out
+///   Shane Lowry and Cameron Smith, *pointsbetau* leaves out Cameron Smith
+///
+/// Every book prices the same fair field with a 22-28% overround
+fn get_masters_json() -> Value {
+    let raw_file_string = fs::read_to_string(
+        "./src/odds_interface/logic/outrights/outrights_test/testdata_outrights.json",
+    )
+    .expect("Unable to read file");
+    return serde_json::from_str::<Value>(&raw_file_string).expect("JSON was not well-formatted");
+}
+
+fn get_bookmakers(event_json: &Value) -> Vec<Bookmaker> {
+    return serde_json::from_value(event_json["bookmakers"].clone()).unwrap();
+}
+
+fn update_runner(event_json: &mut Value, bookie_key: &str, runner: &str, odds_decimal: f64) {
+    let bookie = event_json["bookmakers"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|x| x["key"] == bookie_key)
+        .unwrap();
+    let outcome = bookie["markets"][0]["outcomes"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|x| x["name"] == runner)
+        .unwrap();
+    outcome["price"] = Value::from(odds_decimal);
+}
+
+#[test]
+fn test_consensus_covers_differing_runner_sets() {
+    let runners = consensus_runners(&get_bookmakers(&get_masters_json()), &DevigMethod::Shin);
+
+    assert_eq!(runners.len(), 14);
+    let total: f64 = runners.iter().map(|x| x.consensus_probability).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+
+    // sorted favourite first
+    assert_eq!(runners.first().unwrap().name, "Scottie Scheffler");
+
+    let smith = runners.iter().find(|x| x.name == "Cameron Smith").unwrap();
+    assert_eq!(smith.prices.len(), 2);
+    assert!((smith.consensus_probability - 0.03).abs() < 0.01);
+}
+
+#[test]
+fn test_long_shots_stay_positive() {
+    let bookmakers = get_bookmakers(&get_masters_json());
+
+    for runner in consensus_runners(&bookmakers, &DevigMethod::Additive) {
+        assert!(runner.consensus_probability > 0.0);
+    }
+}
+
+#[test]
+fn test_outright_outlier_opportunity() {
+    let mut event_json = get_masters_json();
+    let settings = ScanSettings::default();
+
+    let outright_opportunities = |event_json: &Value| {
+        let event = serde_json::from_value::<Event>(event_json.clone()).unwrap();
+        return event.identify_opportunities_in_market(&MarketType::Outrights, &settings);
+    };
+    assert_eq!(outright_opportunities(&event_json).len(), 0);
+
+    // fair odds around 20, well past max_odds_cutoff
+    update_runner(&mut event_json, "tab", "Hideki Matsuyama", 60.0);
+
+    let opportunities = outright_opportunities(&event_json);
+    assert_eq!(opportunities.len(), 1);
+    let message = opportunities.first().unwrap().to_string();
+    assert!(message.contains("Hideki Matsuyama"));
+    assert!(message.contains("outright at TAB"));
+    assert!(message.contains("Outlier"));
+
+    let runners = consensus_runners(&get_bookmakers(&event_json), &DevigMethod::Shin);
+    let matsuyama = runners
+        .iter()
+        .find(|x| x.name == "Hideki Matsuyama")
+        .unwrap();
+    let outliers = matsuyama.outliers(&OutrightSettings::default());
+    assert_eq!(outliers.len(), 1);
+    assert_eq!(outliers.first().unwrap().bookie_key, "tab");
+}
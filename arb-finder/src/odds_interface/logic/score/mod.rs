@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TeamScore {
+    pub name: String,
+    /// The api sends scores as strings, e.g. "102"
+    pub score: String,
+}
+
+/// An event from the `/scores` endpoint. `scores` is empty until the event
+/// has started.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EventScore {
+    pub id: String,
+    pub sport_key: String,
+    pub commence_time: DateTime<Utc>,
+    pub completed: bool,
+    pub home_team: String,
+    pub away_team: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub scores: Vec<TeamScore>,
+    pub last_update: Option<DateTime<Utc>>,
+}
+
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TeamScore>, D::Error> {
+    let scores: Option<Vec<TeamScore>> = Deserialize::deserialize(deserializer)?;
+    return Ok(scores.unwrap_or_default());
+}
+
+impl EventScore {
+    pub fn score_for(&self, team: &str) -> Option<f64> {
+        return self
+            .scores
+            .iter()
+            .find(|x| x.name == team)
+            .and_then(|x| x.score.trim().parse::<f64>().ok());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+use serde::Deserialize;
+
+use super::devig::DevigSettings;
+use super::exchange::ExchangeSettings;
+use super::movement::MovementSettings;
+use super::outrights::OutrightSettings;
+use super::spreads::MarginModel;
+use super::weights::BookmakerWeights;
+
+const DEFAULT_MAX_ODDS_CUTOFF: f64 = 10.0;
+const DEFAULT_PERCENT_EV_CUTOFF: f64 = 5.0;
+const DEFAULT_MAX_QUOTE_AGE_MINUTES: f64 = 30.0;
+
+/// Which bookmakers we are able to bet with. An empty allow list allows
+/// every bookmaker that isn't denied.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BookmakerFilter {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl BookmakerFilter {
+    pub fn allows(&self, bookie_key: &str) -> bool {
+        if (self.deny.iter().any(|x| x == bookie_key)) {
+            return false;
+        }
+        return self.allow.is_empty() || self.allow.iter().any(|x| x == bookie_key);
+    }
+}
+
+/// Everything the opportunity finders need to decide what is worth betting
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Outcomes with fair odds above this are skipped, as prices for
+    /// unlikely outcomes are skewed
+    pub max_odds_cutoff: f64,
+    pub percent_ev_cutoff: f64,
+    /// Bookies that last updated their prices longer than this before the
+    /// fetch are left out of the consensus and alerts. 0 turns this off.
+    pub max_quote_age_minutes: f64,
+    /// Per-sport overrides of `percent_ev_cutoff`, keyed by sport key
+    pub sport_percent_ev_cutoffs: HashMap<String, f64>,
+    pub bookmakers: BookmakerFilter,
+    pub devig: DevigSettings,
+    /// Per-sport overrides of the margin model used to price spreads
+    pub spread_models: HashMap<String, MarginModel>,
+    pub outrights: OutrightSettings,
+    pub exchanges: ExchangeSettings,
+    /// How much each bookmaker counts towards consensus true odds
+    pub bookmaker_weights: BookmakerWeights,
+    /// What counts as a steam move between two fetches of an event
+    pub movement: MovementSettings,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        return ScanSettings {
+            max_odds_cutoff: DEFAULT_MAX_ODDS_CUTOFF,
+            percent_ev_cutoff: DEFAULT_PERCENT_EV_CUTOFF,
+            max_quote_age_minutes: DEFAULT_MAX_QUOTE_AGE_MINUTES,
+            sport_percent_ev_cutoffs: HashMap::new(),
+            bookmakers: BookmakerFilter::default(),
+            devig: DevigSettings::default(),
+            spread_models: HashMap::new(),
+            outrights: OutrightSettings::default(),
+            exchanges: ExchangeSettings::default(),
+            bookmaker_weights: BookmakerWeights::default(),
+            movement: MovementSettings::default(),
+        };
+    }
+}
+
+impl ScanSettings {
+    pub fn percent_ev_cutoff_for(&self, sport_key: &str) -> f64 {
+        return *self
+            .sport_percent_ev_cutoffs
+            .get(sport_key)
+            .unwrap_or(&self.percent_ev_cutoff);
+    }
+
+    pub fn is_fresh(&self, quote_age: Duration) -> bool {
+        if (self.max_quote_age_minutes <= 0.0) {
+            return true;
+        }
+        return quote_age.num_seconds() as f64 <= 60.0 * self.max_quote_age_minutes;
+    }
+
+    pub fn spread_model_for(&self, sport_key: &str) -> MarginModel {
+        return match self.spread_models.get(sport_key) {
+            Some(x) => *x,
+            None => MarginModel::for_sport(sport_key),
+        };
+    }
+}
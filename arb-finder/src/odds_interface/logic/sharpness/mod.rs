@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Deserialize;
+
+use super::calibration::{closing_snapshots, log_loss, predictions_for, CONSENSUS_KEY};
+use super::event::Event;
+use super::market::MarketType;
+use super::score::EventScore;
+use super::settings::ScanSettings;
+use super::weights::BookmakerWeights;
+
+#[cfg(test)]
+mod sharpness_test;
+
+const DEFAULT_MIN_PREDICTIONS: usize = 20;
+const DEFAULT_SENSITIVITY: f64 = 25.0;
+const DEFAULT_LEARNING_RATE: f64 = 0.5;
+
+/// Markets the analysis and learned weights cover
+const SHARPNESS_MARKETS: [MarketType; 3] =
+    [MarketType::H2h, MarketType::Totals, MarketType::Spreads];
+
+/// Each book's de-vigged probability, by market, outcome and point
+type LineProbabilities = HashMap<String, HashMap<String, f64>>;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SharpnessSettings {
+    /// Books with fewer graded closing prices than this aren't given a
+    /// suggested weight
+    pub min_predictions: usize,
+    /// How far log loss against the consensus moves a book's weight: the
+    /// suggested weight is exp(-sensitivity * excess log loss)
+    pub sensitivity: f64,
+    /// How far the configured weights move towards the suggested ones
+    pub learning_rate: f64,
+}
+
+impl Default for SharpnessSettings {
+    fn default() -> Self {
+        return SharpnessSettings {
+            min_predictions: DEFAULT_MIN_PREDICTIONS,
+            sensitivity: DEFAULT_SENSITIVITY,
+            learning_rate: DEFAULT_LEARNING_RATE,
+        };
+    }
+}
+
+/// How well one bookmaker's prices in one market predicted results
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketAccuracy {
+    pub predictions: usize,
+    pub excess_log_loss: f64,
+}
+
+/// Weight for a book `excess_log_loss` above the consensus over
+/// `predictions` graded prices, or None if there aren't enough
+fn weight_from_accuracy(
+    predictions: usize,
+    excess_log_loss: f64,
+    settings: &SharpnessSettings,
+) -> Option<f64> {
+    if (predictions == 0 || predictions < settings.min_predictions) {
+        return None;
+    }
+    return Some((-settings.sensitivity * excess_log_loss).exp());
+}
+
+/// How well one bookmaker's prices predicted results and moves
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookmakerSharpness {
+    pub bookmaker_key: String,
+    /// Graded outcomes from the book's closing prices
+    pub predictions: usize,
+    pub log_loss: f64,
+    /// Mean log loss above the consensus on the same outcomes, negative for
+    /// books sharper than the crowd
+    pub excess_log_loss: f64,
+    /// Times the book moved a price before the rest of the market followed
+    pub leads: usize,
+    /// Times the book followed a move the rest of the market made first
+    pub lags: usize,
+    /// Mean margin of the book's closing markets, as a percentage
+    pub margin_percent: f64,
+    /// Predictions and excess log loss of each market on its own
+    pub markets: HashMap<MarketType, MarketAccuracy>,
+}
+
+impl BookmakerSharpness {
+    pub fn lead_percent(&self) -> f64 {
+        if (self.leads + self.lags == 0) {
+            return 0.0;
+        }
+        return 100.0 * self.leads as f64 / (self.leads + self.lags) as f64;
+    }
+
+    /// Consensus weight the book's accuracy suggests, or None if there
+    /// isn't enough to go on
+    pub fn suggested_weight(&self, settings: &SharpnessSettings) -> Option<f64> {
+        return weight_from_accuracy(self.predictions, self.excess_log_loss, settings);
+    }
+
+    /// Consensus weight the book's accuracy in `market` alone suggests
+    pub fn suggested_market_weight(
+        &self,
+        market: &MarketType,
+        settings: &SharpnessSettings,
+    ) -> Option<f64> {
+        let accuracy = self.markets.get(market)?;
+        return weight_from_accuracy(accuracy.predictions, accuracy.excess_log_loss, settings);
+    }
+}
+
+fn fair_probabilities(event: &Event, settings: &ScanSettings) -> LineProbabilities {
+    let mut probabilities: LineProbabilities = HashMap::new();
+    for bookie in event.bookmakers() {
+        for market in bookie
+            .markets
+            .iter()
+            .filter(|x| SHARPNESS_MARKETS.contains(&x.key))
+        {
+            let method = settings.devig.method_for(event.sport_key(), &market.key);
+            for outcome in &market.outcomes {
+                if let Some(probability) =
+                    market.true_probability_for_outcome(&outcome.name, &method)
+                {
+                    probabilities
+                        .entry(format!(
+                            "{0}|{1}|{2:?}",
+                            market.key, outcome.name, outcome.point
+                        ))
+                        .or_default()
+                        .insert(bookie.key.clone(), probability);
+                }
+            }
+        }
+    }
+    return probabilities;
+}
+
+/// Change in `bookie_key`'s probability between two snapshots, and the mean
+/// change of every other book quoting both times
+fn moves(
+    before: &HashMap<String, f64>,
+    after: &HashMap<String, f64>,
+    bookie_key: &str,
+) -> Option<(f64, f64)> {
+    let own = after.get(bookie_key)? - before.get(bookie_key)?;
+    let others: Vec<f64> = after
+        .iter()
+        .filter(|(key, _)| key.as_str() != bookie_key)
+        .filter_map(|(key, probability)| Some(probability - before.get(key)?))
+        .collect();
+    if (others.is_empty()) {
+        return None;
+    }
+    return Some((own, others.iter().sum::<f64>() / others.len() as f64));
+}
+
+/// Counts each book's leads and lags over every run of three consecutive
+/// pre-game snapshots of an event. A book leads when it moves while the
+/// rest of the market holds, and the rest follows in the next snapshot. It
+/// lags when it holds while the rest moves, then follows. A move counts
+/// once per market, however many of its outcomes it changed.
+fn count_leads_and_lags(
+    snapshots: &[Event],
+    settings: &ScanSettings,
+    sharpness: &mut BTreeMap<String, BookmakerSharpness>,
+) {
+    let threshold = settings.movement.min_probability_move;
+    let mut by_event: BTreeMap<&str, Vec<LineProbabilities>> = BTreeMap::new();
+    for event in snapshots {
+        match event.fetched_at() {
+            Some(x) if x <= event.commence_time() => {}
+            _ => continue,
+        }
+        by_event
+            .entry(event.id())
+            .or_default()
+            .push(fair_probabilities(event, settings));
+    }
+
+    for history in by_event.values() {
+        for window in history.windows(3) {
+            // (bookie, market) that led or lagged in this window
+            let mut led: BTreeSet<(&str, &str)> = BTreeSet::new();
+            let mut lagged: BTreeSet<(&str, &str)> = BTreeSet::new();
+            for (line, first) in &window[0] {
+                let market = line.split('|').next().unwrap_or_default();
+                let (second, third) = match (window[1].get(line), window[2].get(line)) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => continue,
+                };
+
+                for bookie_key in first.keys() {
+                    let (own, others) = match moves(first, second, bookie_key) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    let (own_next, others_next) = match moves(second, third, bookie_key) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+
+                    sharpness.entry(bookie_key.clone()).or_default();
+                    if (own.abs() >= threshold
+                        && others.abs() < threshold
+                        && others_next * own.signum() >= threshold)
+                    {
+                        led.insert((bookie_key, market));
+                    } else if (others.abs() >= threshold
+                        && own.abs() < threshold
+                        && own_next * others.signum() >= threshold)
+                    {
+                        lagged.insert((bookie_key, market));
+                    }
+                }
+            }
+
+            for (bookie_key, _) in &led {
+                sharpness.entry(bookie_key.to_string()).or_default().leads += 1;
+            }
+            for (bookie_key, _) in lagged.difference(&led) {
+                sharpness.entry(bookie_key.to_string()).or_default().lags += 1;
+            }
+        }
+    }
+}
+
+/// Scores every bookmaker quoting a sport from its `snapshots` (oldest
+/// first) and `results`, sharpest first. Accuracy is measured on the last
+/// snapshot before each game, de-vigged with the configured method.
+pub fn analyse_sharpness(
+    snapshots: &[Event],
+    results: &[EventScore],
+    settings: &ScanSettings,
+) -> Vec<BookmakerSharpness> {
+    let mut sharpness: BTreeMap<String, BookmakerSharpness> = BTreeMap::new();
+    let mut margins: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut predictions = Vec::new();
+
+    for event in closing_snapshots(snapshots) {
+        for bookie in event.bookmakers() {
+            for market in bookie
+                .markets
+                .iter()
+                .filter(|x| SHARPNESS_MARKETS.contains(&x.key))
+            {
+                margins
+                    .entry(bookie.key.clone())
+                    .or_default()
+                    .push(100.0 * market.get_vig());
+            }
+        }
+
+        if let Some(score) = results.iter().find(|x| x.id == event.id()) {
+            predictions.append(&mut predictions_for(event, score, settings));
+        }
+    }
+    predictions
+        .retain(|x| x.devig_method == settings.devig.method_for(&x.sport_key, &x.market_key));
+
+    let line = |event_id: &str, market: &MarketType, outcome_key: &str, point: Option<f64>| {
+        return format!("{event_id}|{market}|{outcome_key}|{point:?}");
+    };
+    let consensus_losses: HashMap<String, f64> = predictions
+        .iter()
+        .filter(|x| x.bookmaker_key == CONSENSUS_KEY)
+        .map(|x| {
+            return (
+                line(&x.event_id, &x.market_key, &x.outcome_key, x.point),
+                log_loss(x.probability, x.happened),
+            );
+        })
+        .collect();
+
+    // (log loss total, excess total, outcomes with a consensus)
+    let mut losses: HashMap<&str, (f64, f64, usize)> = HashMap::new();
+    // (excess total, outcomes with a consensus) of each market
+    let mut market_losses: HashMap<(&str, &MarketType), (f64, usize)> = HashMap::new();
+    for prediction in predictions
+        .iter()
+        .filter(|x| x.bookmaker_key != CONSENSUS_KEY)
+    {
+        let loss = log_loss(prediction.probability, prediction.happened);
+        let entry = sharpness
+            .entry(prediction.bookmaker_key.clone())
+            .or_default();
+        entry.predictions += 1;
+        entry
+            .markets
+            .entry(prediction.market_key.clone())
+            .or_default()
+            .predictions += 1;
+
+        let totals = losses.entry(&prediction.bookmaker_key).or_default();
+        totals.0 += loss;
+        if let Some(consensus_loss) = consensus_losses.get(&line(
+            &prediction.event_id,
+            &prediction.market_key,
+            &prediction.outcome_key,
+            prediction.point,
+        )) {
+            totals.1 += loss - consensus_loss;
+            totals.2 += 1;
+
+            let market_totals = market_losses
+                .entry((&prediction.bookmaker_key, &prediction.market_key))
+                .or_default();
+            market_totals.0 += loss - consensus_loss;
+            market_totals.1 += 1;
+        }
+    }
+
+    count_leads_and_lags(snapshots, settings, &mut sharpness);
+
+    for (bookie_key, entry) in sharpness.iter_mut() {
+        entry.bookmaker_key = bookie_key.clone();
+        if let Some((total, excess, compared)) = losses.get(bookie_key.as_str()) {
+            entry.log_loss = total / entry.predictions as f64;
+            if (*compared > 0) {
+                entry.excess_log_loss = excess / *compared as f64;
+            }
+        }
+        for (market, accuracy) in entry.markets.iter_mut() {
+            if let Some((excess, compared)) = market_losses.get(&(bookie_key.as_str(), market)) {
+                if (*compared > 0) {
+                    accuracy.excess_log_loss = excess / *compared as f64;
+                }
+            }
+        }
+        if let Some(book_margins) = margins.get(bookie_key) {
+            entry.margin_percent = book_margins.iter().sum::<f64>() / book_margins.len() as f64;
+        }
+    }
+    for bookie_key in margins.keys() {
+        if (!sharpness.contains_key(bookie_key)) {
+            let book_margins = &margins[bookie_key];
+            sharpness.insert(
+                bookie_key.clone(),
+                BookmakerSharpness {
+                    bookmaker_key: bookie_key.clone(),
+                    margin_percent: book_margins.iter().sum::<f64>() / book_margins.len() as f64,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    // graded books by how much they beat the consensus, then the rest
+    let mut ranked: Vec<BookmakerSharpness> = sharpness.into_values().collect();
+    ranked.sort_by(|a, b| {
+        return (a.predictions == 0)
+            .cmp(&(b.predictions == 0))
+            .then(a.excess_log_loss.total_cmp(&b.excess_log_loss))
+            .then(a.bookmaker_key.cmp(&b.bookmaker_key));
+    });
+    return ranked;
+}
+
+/// Moves `weights` for each analysed market of `sport_key` towards the
+/// weights suggested by that market's prices. Markets without a book that
+/// has enough graded prices in them are left alone.
+pub fn seed_weights(
+    weights: &mut BookmakerWeights,
+    sport_key: &str,
+    ranked: &[BookmakerSharpness],
+    settings: &SharpnessSettings,
+) {
+    for market in &SHARPNESS_MARKETS {
+        let observed: HashMap<String, f64> = ranked
+            .iter()
+            .filter_map(|x| {
+                return Some((
+                    x.bookmaker_key.clone(),
+                    x.suggested_market_weight(market, settings)?,
+                ));
+            })
+            .collect();
+        if (observed.is_empty()) {
+            continue;
+        }
+        weights.learn(sport_key, market, &observed, settings.learning_rate);
+    }
+}
+
+/// Plain text table of `ranked` books for one sport, sharpest first
+pub fn format_sharpness_table(
+    sport_key: &str,
+    ranked: &[BookmakerSharpness],
+    settings: &SharpnessSettings,
+) -> String {
+    let mut table = format!(
+        "{sport_key}\n{0:>4} {1:<20} {2:>6} {3:>8} {4:>8} {5:>6} {6:>6} {7:>7} {8:>9} {9:>7}\n",
+        "rank",
+        "bookmaker",
+        "preds",
+        "log loss",
+        "excess",
+        "leads",
+        "lags",
+        "lead %",
+        "margin %",
+        "weight"
+    );
+    for (i, book) in ranked.iter().enumerate() {
+        let weight = match book.suggested_weight(settings) {
+            Some(x) => format!("{x:.2}"),
+            None => String::from("-"),
+        };
+        table += &format!(
+            "{0:>4} {1:<20} {2:>6} {3:>8.4} {4:>8.4} {5:>6} {6:>6} {7:>7.1} {8:>9.2} {weight:>7}\n",
+            i + 1,
+            book.bookmaker_key,
+            book.predictions,
+            book.log_loss,
+            book.excess_log_loss,
+            book.leads,
+            book.lags,
+            book.lead_percent(),
+            book.margin_percent
+        );
+    }
+    return table;
+}
+
+/// The sport and market weights of `sport_key` as config entries, to paste
+/// under `[scan.bookmaker_weights]`
+pub fn format_weights_toml(sport_key: &str, weights: &BookmakerWeights) -> String {
+    let mut toml = String::new();
+    let markets = match weights.sport_market_weights.get(sport_key) {
+        Some(x) => x,
+        None => return toml,
+    };
+    for market in &SHARPNESS_MARKETS {
+        let market_weights = match markets.get(market) {
+            Some(x) => x,
+            None => continue,
+        };
+        toml += &format!("[scan.bookmaker_weights.sport_market_weights.{sport_key}.{market}]\n");
+        let sorted: BTreeMap<&String, &f64> = market_weights.iter().collect();
+        for (bookie_key, weight) in sorted {
+            toml += &format!("{bookie_key} = {weight:.3}\n");
+        }
+        toml += "\n";
+    }
+    return toml;
+}
@@ -0,0 +1,198 @@
+use std::fs;
+
+use chrono::Duration;
+
+use crate::config::Config;
+use crate::odds_interface::logic::{
+    event::Event,
+    market::MarketType,
+    score::EventScore,
+    settings::ScanSettings,
+    sharpness::{
+        analyse_sharpness, format_sharpness_table, format_weights_toml, seed_weights,
+        BookmakerSharpness, SharpnessSettings,
+    },
+    weights::BookmakerWeights,
+};
+
+/// The AFL event with some books' h2h prices changed to (St Kilda Saints,
+/// Brisbane Lions), fetched `minutes_before` the start
+fn get_afl_event_with(prices: &[(&str, f64, f64)], minutes_before: i64) -> Event {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_odds.json")
+        .expect("Unable to read file");
+    let mut events: serde_json::Value =
+        serde_json::from_str(&raw_file_string).expect("JSON was not well-formatted");
+
+    for bookie in events[0]["bookmakers"].as_array_mut().unwrap() {
+        let (saints, lions) = match prices.iter().find(|x| bookie["key"] == x.0) {
+            Some((_, saints, lions)) => (*saints, *lions),
+            None => continue,
+        };
+        for market in bookie["markets"].as_array_mut().unwrap() {
+            if (market["key"] != "h2h") {
+                continue;
+            }
+            for outcome in market["outcomes"].as_array_mut().unwrap() {
+                outcome["price"] = match outcome["name"].as_str() {
+                    Some("St Kilda Saints") => saints.into(),
+                    _ => lions.into(),
+                };
+            }
+        }
+    }
+
+    let mut event = serde_json::from_value::<Vec<Event>>(events)
+        .unwrap()
+        .remove(0);
+    event.set_fetched_at(event.commence_time() - Duration::minutes(minutes_before));
+    return event;
+}
+
+/// St Kilda Saints 88, Brisbane Lions 81
+fn get_afl_results() -> Vec<EventScore> {
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_scores.json")
+        .expect("Unable to read file");
+    return serde_json::from_str::<Vec<EventScore>>(&raw_file_string)
+        .expect("JSON was not well-formatted");
+}
+
+fn find<'a>(ranked: &'a [BookmakerSharpness], bookie_key: &str) -> &'a BookmakerSharpness {
+    return ranked
+        .iter()
+        .find(|x| x.bookmaker_key == bookie_key)
+        .unwrap();
+}
+
+#[test]
+fn test_books_closer_to_the_result_rank_higher() {
+    // sportsbet had the Saints as outsiders, the others at evens, all
+    // quoted five minutes before this fetch
+    let snapshots = [get_afl_event_with(&[], 80)];
+    let ranked = analyse_sharpness(&snapshots, &get_afl_results(), &ScanSettings::default());
+
+    assert_eq!(ranked.len(), 4);
+    assert_eq!(ranked[3].bookmaker_key, "sportsbet");
+    for book in &ranked[..3] {
+        assert!(book.excess_log_loss < 0.0);
+        assert_eq!(book.log_loss, ranked[0].log_loss);
+    }
+    assert!(ranked[3].excess_log_loss > 0.0);
+
+    // two h2h and two totals outcomes each
+    for book in &ranked {
+        assert_eq!(book.predictions, 4);
+        assert!(book.margin_percent > 0.0);
+        assert_eq!(book.leads + book.lags, 0);
+    }
+    // 1.9 both ways in both markets
+    assert!((find(&ranked, "tab").margin_percent - 5.0).abs() < 1e-9);
+
+    // no results, nothing graded, but margins are still reported
+    let ungraded = analyse_sharpness(&snapshots, &[], &ScanSettings::default());
+    assert_eq!(ungraded.len(), 4);
+    assert!(ungraded
+        .iter()
+        .all(|x| x.predictions == 0 && x.margin_percent > 0.0));
+}
+
+#[test]
+fn test_leads_and_lags() {
+    let snapshots = [
+        get_afl_event_with(&[], 60),
+        // tab moves first
+        get_afl_event_with(&[("tab", 1.6, 2.4)], 30),
+        // and everyone else follows
+        get_afl_event_with(
+            &[
+                ("tab", 1.6, 2.4),
+                ("unibet", 1.6, 2.4),
+                ("pointsbetau", 1.6, 2.4),
+                ("sportsbet", 1.9, 1.9),
+            ],
+            10,
+        ),
+        // in play snapshots don't count
+        get_afl_event_with(&[("sportsbet", 1.2, 4.0)], -10),
+    ];
+    let ranked = analyse_sharpness(&snapshots, &[], &ScanSettings::default());
+
+    // moving both h2h outcomes is one move
+    let tab = find(&ranked, "tab");
+    assert_eq!((tab.leads, tab.lags), (1, 0));
+    assert_eq!(tab.lead_percent(), 100.0);
+    for bookie_key in ["unibet", "pointsbetau", "sportsbet"] {
+        let book = find(&ranked, bookie_key);
+        assert_eq!((book.leads, book.lags), (0, 1));
+        assert_eq!(book.lead_percent(), 0.0);
+    }
+}
+
+#[test]
+fn test_seed_weights_from_suggestions() {
+    let ranked = analyse_sharpness(
+        &[get_afl_event_with(&[], 80)],
+        &get_afl_results(),
+        &ScanSettings::default(),
+    );
+
+    let mut settings = SharpnessSettings::default();
+    assert!(ranked
+        .iter()
+        .all(|x| x.suggested_weight(&settings).is_none()));
+    settings.min_predictions = 4;
+    let suggested = |bookie_key: &str| find(&ranked, bookie_key).suggested_weight(&settings);
+    assert_eq!(
+        ranked
+            .iter()
+            .filter(|x| x.suggested_weight(&settings).is_some())
+            .count(),
+        4
+    );
+    assert!(suggested("tab").unwrap() > 1.0);
+    assert!(suggested("sportsbet").unwrap() < 1.0);
+    let tab = find(&ranked, "tab");
+    let expected = (-settings.sensitivity * tab.excess_log_loss).exp();
+    assert!((suggested("tab").unwrap() - expected).abs() < 1e-9);
+
+    // each market is seeded from its own two outcomes
+    let mut weights = BookmakerWeights::default();
+    seed_weights(&mut weights, "aussierules_afl", &ranked, &settings);
+    assert!(weights.sport_market_weights.is_empty());
+    settings.min_predictions = 2;
+    seed_weights(&mut weights, "aussierules_afl", &ranked, &settings);
+
+    // only the h2h prices differed, so only they tell the books apart
+    let h2h = &tab.markets[&MarketType::H2h];
+    assert_eq!(h2h.predictions, 2);
+    assert!(h2h.excess_log_loss < tab.excess_log_loss);
+    let h2h_weight = tab
+        .suggested_market_weight(&MarketType::H2h, &settings)
+        .unwrap();
+    let expected = 1.0 + settings.learning_rate * (h2h_weight - 1.0);
+    let weight = weights.weight_for("aussierules_afl", &MarketType::H2h, "tab");
+    assert!((weight - expected).abs() < 1e-9);
+    let weight = weights.weight_for("aussierules_afl", &MarketType::Totals, "tab");
+    assert!((weight - 1.0).abs() < 1e-9);
+    // nothing graded in spreads
+    assert!(!weights.sport_market_weights["aussierules_afl"].contains_key(&MarketType::Spreads));
+    assert_eq!(
+        weights.weight_for("baseball_kbo", &MarketType::H2h, "tab"),
+        1.0
+    );
+
+    // the printed weights can be pasted into the config as they are
+    let toml = format_weights_toml("aussierules_afl", &weights);
+    let config = Config::from_toml(&toml).unwrap();
+    let weight =
+        config
+            .scan
+            .bookmaker_weights
+            .weight_for("aussierules_afl", &MarketType::H2h, "tab");
+    assert!((weight - expected).abs() < 1e-3);
+    assert!(format_weights_toml("baseball_kbo", &weights).is_empty());
+
+    let table = format_sharpness_table("aussierules_afl", &ranked, &settings);
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(rows.len(), 2 + 4);
+    assert!(rows[5].contains("sportsbet"));
+}
@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use statrs::distribution::{ContinuousCDF, Discrete, Normal, Poisson};
+
+use super::devig::solve_decreasing;
+
+#[cfg(test)]
+mod spreads_test;
+
+/// Goals beyond this are too unlikely to matter in low scoring sports
+const MAX_SKELLAM_SCORE: u64 = 60;
+
+/// Distribution of the home team's winning margin, parameterised by its mean
+/// so books quoting different handicaps can be compared and averaged.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum MarginModel {
+    /// For high scoring sports, where the margin is roughly normal
+    Normal { std_dev: f64 },
+    /// For low scoring sports: the difference of two Poisson scores that
+    /// add up to `expected_total` on average
+    Skellam { expected_total: f64 },
+}
+
+/// Chances of a handicap bet winning and being refunded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverProbability {
+    pub win: f64,
+    pub push: f64,
+}
+
+impl CoverProbability {
+    pub fn lose(&self) -> f64 {
+        return (1.0 - self.win - self.push).max(0.0);
+    }
+
+    /// Chance of winning given the bet isn't refunded, which is what a
+    /// de-vigged price estimates
+    pub fn win_without_push(&self) -> f64 {
+        return self.win / (self.win + self.lose());
+    }
+
+    /// Decimal odds with zero expected value, counting pushes as refunds
+    pub fn fair_decimal_odds(&self) -> f64 {
+        return (self.win + self.lose()) / self.win;
+    }
+}
+
+impl MarginModel {
+    /// Sensible defaults by sport group, from the start of the sport key
+    pub fn for_sport(sport_key: &str) -> MarginModel {
+        let group = sport_key.split('_').next().unwrap_or_default();
+        match group {
+            "aussierules" => return MarginModel::Normal { std_dev: 36.0 },
+            "basketball" => return MarginModel::Normal { std_dev: 12.0 },
+            "americanfootball" => return MarginModel::Normal { std_dev: 13.5 },
+            "rugbyleague" | "rugbyunion" => return MarginModel::Normal { std_dev: 15.0 },
+            "soccer" => {
+                return MarginModel::Skellam {
+                    expected_total: 2.6,
+                }
+            }
+            "icehockey" => {
+                return MarginModel::Skellam {
+                    expected_total: 6.0,
+                }
+            }
+            "baseball" => {
+                return MarginModel::Skellam {
+                    expected_total: 9.0,
+                }
+            }
+            _ => return MarginModel::Normal { std_dev: 13.0 },
+        }
+    }
+
+    /// Whether the model's parameter describes a distribution at all
+    pub fn is_valid(&self) -> bool {
+        let parameter = match *self {
+            MarginModel::Normal { std_dev } => std_dev,
+            MarginModel::Skellam { expected_total } => expected_total,
+        };
+        return parameter.is_finite() && parameter > 0.0;
+    }
+
+    /// Chance the home margin is above `threshold`, and exactly `threshold`
+    fn margin_probabilities(&self, mean_margin: f64, threshold: f64) -> (f64, f64) {
+        match *self {
+            MarginModel::Normal { std_dev } => {
+                let normal = Normal::new(mean_margin, std_dev).unwrap();
+                return (1.0 - normal.cdf(threshold), 0.0);
+            }
+            MarginModel::Skellam { expected_total } => {
+                // keep both scoring rates positive
+                let mean_margin = mean_margin.clamp(-expected_total + 1e-6, expected_total - 1e-6);
+                let home = Poisson::new((expected_total + mean_margin) / 2.0).unwrap();
+                let away = Poisson::new((expected_total - mean_margin) / 2.0).unwrap();
+
+                let mut above = 0.0;
+                let mut exactly = 0.0;
+                for home_score in 0..=MAX_SKELLAM_SCORE {
+                    for away_score in 0..=MAX_SKELLAM_SCORE {
+                        let margin = home_score as f64 - away_score as f64;
+                        let probability = home.pmf(home_score) * away.pmf(away_score);
+                        if (margin > threshold) {
+                            above += probability;
+                        } else if (margin == threshold) {
+                            exactly += probability;
+                        }
+                    }
+                }
+                return (above, exactly);
+            }
+        }
+    }
+
+    /// Chance the home team covers a handicap of `point`, e.g. -7.5
+    pub fn home_cover(&self, mean_margin: f64, point: f64) -> CoverProbability {
+        let (above, exactly) = self.margin_probabilities(mean_margin, -point);
+        return CoverProbability {
+            win: above,
+            push: exactly,
+        };
+    }
+
+    /// Chance the away team covers a handicap of `point`, e.g. +7.5
+    pub fn away_cover(&self, mean_margin: f64, point: f64) -> CoverProbability {
+        let (above, exactly) = self.margin_probabilities(mean_margin, point);
+        return CoverProbability {
+            win: 1.0 - above - exactly,
+            push: exactly,
+        };
+    }
+
+    /// Mean home margin at which the home team covers `point` with
+    /// probability `cover_probability`, ignoring pushes
+    pub fn fit_mean_margin(&self, point: f64, cover_probability: f64) -> f64 {
+        let (lo, hi) = match *self {
+            MarginModel::Normal { std_dev } => (-point - 10.0 * std_dev, -point + 10.0 * std_dev),
+            MarginModel::Skellam { expected_total } => (-expected_total, expected_total),
+        };
+
+        return solve_decreasing(
+            |mean| cover_probability - self.home_cover(mean, point).win_without_push(),
+            lo,
+            hi,
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::odds_interface::logic::spreads::MarginModel;
+
+#[test]
+fn test_normal_fit_round_trips() {
+    let model = MarginModel::Normal { std_dev: 36.0 };
+
+    // an even money line means the books expect the handicap to be the margin
+    assert!((model.fit_mean_margin(-7.5, 0.5) - 7.5).abs() < 1e-6);
+
+    let cover = model.home_cover(12.0, -7.5);
+    assert_eq!(cover.push, 0.0);
+    assert!((model.fit_mean_margin(-7.5, cover.win) - 12.0).abs() < 1e-6);
+
+    // home and away at the same handicap cover everything between them
+    let away = model.away_cover(12.0, 7.5);
+    assert!((cover.win + away.win - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_skellam_pushes_on_whole_points() {
+    let model = MarginModel::Skellam {
+        expected_total: 2.6,
+    };
+
+    let level = model.home_cover(0.0, 0.0);
+    assert!(level.push > 0.2);
+    assert!((level.win - level.lose()).abs() < 1e-9);
+    // a draw no bet at level scores is a coin flip once pushes are refunded
+    assert!((level.fair_decimal_odds() - 2.0).abs() < 1e-9);
+
+    let half_goal = model.home_cover(0.0, -0.5);
+    assert_eq!(half_goal.push, 0.0);
+    assert!((half_goal.win - level.win).abs() < 1e-9);
+
+    let cover = model.home_cover(0.7, -1.0);
+    let fitted = model.fit_mean_margin(-1.0, cover.win_without_push());
+    assert!((fitted - 0.7).abs() < 1e-6);
+}
+
+#[test]
+fn test_models_for_sports() {
+    assert!(matches!(
+        MarginModel::for_sport("aussierules_afl"),
+        MarginModel::Normal { .. }
+    ));
+    assert!(matches!(
+        MarginModel::for_sport("soccer_epl"),
+        MarginModel::Skellam { .. }
+    ));
+}
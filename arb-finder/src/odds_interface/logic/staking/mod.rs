@@ -0,0 +1,112 @@
+use serde::Deserialize;
+
+#[cfg(test)]
+mod staking_test;
+
+/// How much of the bankroll to put on a +EV bet
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "strategy")]
+pub enum StakingStrategy {
+    /// Stake the growth-optimal fraction of the bankroll
+    FullKelly,
+    /// Stake a fraction of the Kelly stake, e.g. 0.25 for quarter Kelly
+    FractionalKelly { fraction: f64 },
+    /// Stake the same amount on every bet
+    FixedUnit { unit: f64 },
+    /// Stake the Kelly amount, but never more than `percent` of the bankroll
+    CappedPercent { percent: f64 },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct StakingSettings {
+    pub bankroll: f64,
+    #[serde(flatten)]
+    pub strategy: StakingStrategy,
+}
+
+/// A bet to be sized: our estimate of the probability it wins and the
+/// decimal odds on offer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KellyBet {
+    pub probability: f64,
+    pub decimal_odds: f64,
+}
+
+/// Kelly fraction for a single bet taken on its own
+pub fn kelly_fraction(bet: &KellyBet) -> f64 {
+    let net_odds = bet.decimal_odds - 1.0;
+    if (net_odds <= 0.0) {
+        return 0.0;
+    }
+
+    let fraction = (bet.probability * bet.decimal_odds - 1.0) / net_odds;
+    return fraction.max(0.0);
+}
+
+/// Kelly fractions for bets on mutually exclusive outcomes placed at the
+/// same time, e.g. several outcomes of one h2h market.
+///
+/// Smoczynski & Tomkins (2010), "An explicit solution to the problem of
+/// optimizing the allocations of a bettor's wealth when wagering on horse
+/// races"
+pub fn simultaneous_kelly_fractions(bets: &[KellyBet]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..bets.len()).collect();
+    let expected_return = |i: &usize| bets[*i].probability * bets[*i].decimal_odds;
+    order.sort_by(|a, b| expected_return(b).total_cmp(&expected_return(a)));
+
+    // reserve rate: the fraction of wealth kept back, per unit of probability
+    let mut reserve_rate = 1.0;
+    let mut included_probability = 0.0;
+    let mut included_implied = 0.0;
+    let mut num_included = 0;
+
+    for i in &order {
+        if (expected_return(i) <= reserve_rate) {
+            break;
+        }
+
+        let next_implied = included_implied + 1.0 / bets[*i].decimal_odds;
+        if (next_implied >= 1.0) {
+            // the bets so far already form a sure bet, keep what we have
+            break;
+        }
+
+        included_probability += bets[*i].probability;
+        included_implied = next_implied;
+        reserve_rate = (1.0 - included_probability) / (1.0 - included_implied);
+        num_included += 1;
+    }
+
+    let mut fractions = vec![0.0; bets.len()];
+    for i in order.iter().take(num_included) {
+        let bet = &bets[*i];
+        fractions[*i] = (bet.probability - reserve_rate / bet.decimal_odds).max(0.0);
+    }
+    return fractions;
+}
+
+impl StakingSettings {
+    /// Converts Kelly fractions of the bankroll into stakes under this
+    /// strategy. Bets with no edge get a zero stake whatever the strategy.
+    pub fn stakes_from_kelly(&self, kelly_fractions: &[f64]) -> Vec<f64> {
+        return kelly_fractions
+            .iter()
+            .map(|fraction| {
+                if (*fraction <= 0.0) {
+                    return 0.0;
+                }
+
+                return match self.strategy {
+                    StakingStrategy::FullKelly => fraction * self.bankroll,
+                    StakingStrategy::FractionalKelly { fraction: scale } => {
+                        scale * fraction * self.bankroll
+                    }
+                    StakingStrategy::FixedUnit { unit } => unit.min(self.bankroll),
+                    StakingStrategy::CappedPercent { percent } => {
+                        fraction.min(percent / 100.0) * self.bankroll
+                    }
+                };
+            })
+            .collect();
+    }
+}
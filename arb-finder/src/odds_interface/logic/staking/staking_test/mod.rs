@@ -0,0 +1,89 @@
+use crate::odds_interface::logic::staking::{
+    kelly_fraction, simultaneous_kelly_fractions, KellyBet, StakingSettings, StakingStrategy,
+};
+
+fn get_settings(strategy: StakingStrategy) -> StakingSettings {
+    return StakingSettings {
+        bankroll: 1000.0,
+        strategy,
+    };
+}
+
+#[test]
+fn test_kelly_fraction() {
+    // 50% chance at evens + 10%: f = (0.5 * 2.2 - 1) / 1.2
+    let bet = KellyBet {
+        probability: 0.5,
+        decimal_odds: 2.2,
+    };
+    assert!((kelly_fraction(&bet) - 0.1 / 1.2).abs() < 1e-9);
+
+    let no_edge = KellyBet {
+        probability: 0.4,
+        decimal_odds: 2.2,
+    };
+    assert_eq!(kelly_fraction(&no_edge), 0.0);
+}
+
+#[test]
+fn test_simultaneous_kelly_single_bet_matches_kelly() {
+    let bet = KellyBet {
+        probability: 0.5,
+        decimal_odds: 2.2,
+    };
+    let fractions = simultaneous_kelly_fractions(&[bet]);
+    assert!((fractions[0] - kelly_fraction(&bet)).abs() < 1e-9);
+}
+
+#[test]
+fn test_simultaneous_kelly_mutually_exclusive() {
+    let bets = [
+        KellyBet {
+            probability: 0.5,
+            decimal_odds: 2.2,
+        },
+        KellyBet {
+            probability: 0.3,
+            decimal_odds: 3.6,
+        },
+        KellyBet {
+            probability: 0.2,
+            decimal_odds: 3.0,
+        },
+    ];
+    let fractions = simultaneous_kelly_fractions(&bets);
+
+    // the draw-like third outcome has no edge
+    assert_eq!(fractions[2], 0.0);
+    assert!(fractions[0] > 0.0 && fractions[1] > 0.0);
+
+    // backing both outcomes hedges, so each is staked above its lone Kelly
+    // fraction, with the rest of the bankroll kept back at the reserve rate
+    let reserve_rate = (1.0 - 0.5 - 0.3) / (1.0 - 1.0 / 2.2 - 1.0 / 3.6);
+    assert!((fractions[0] - (0.5 - reserve_rate / 2.2)).abs() < 1e-9);
+    assert!((fractions[1] - (0.3 - reserve_rate / 3.6)).abs() < 1e-9);
+    assert!(fractions[1] > kelly_fraction(&bets[1]));
+}
+
+#[test]
+fn test_stakes_for_each_strategy() {
+    let fractions = [0.1, 0.0];
+
+    assert_eq!(
+        get_settings(StakingStrategy::FullKelly).stakes_from_kelly(&fractions),
+        [100.0, 0.0].to_vec()
+    );
+    assert_eq!(
+        get_settings(StakingStrategy::FractionalKelly { fraction: 0.5 })
+            .stakes_from_kelly(&fractions),
+        [50.0, 0.0].to_vec()
+    );
+    assert_eq!(
+        get_settings(StakingStrategy::FixedUnit { unit: 20.0 }).stakes_from_kelly(&fractions),
+        [20.0, 0.0].to_vec()
+    );
+    assert_eq!(
+        get_settings(StakingStrategy::CappedPercent { percent: 2.0 }).stakes_from_kelly(&fractions),
+        [20.0, 0.0].to_vec()
+    );
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::market::MarketType;
+
+#[cfg(test)]
+mod weights_test;
+
+const DEFAULT_WEIGHT: f64 = 1.0;
+const SHARP_WEIGHT: f64 = 3.0;
+const EXCHANGE_WEIGHT: f64 = 2.0;
+const RECREATIONAL_WEIGHT: f64 = 0.5;
+
+/// Starting weights: sharp books and exchanges set the market, recreational
+/// books shade their prices to attract casual money
+fn default_bookmaker_weights() -> HashMap<String, f64> {
+    let mut weights = HashMap::new();
+    for bookie_key in ["pinnacle", "circasports", "lowvig", "betonlineag"] {
+        weights.insert(bookie_key.to_string(), SHARP_WEIGHT);
+    }
+    for bookie_key in [
+        "betfair_ex_au",
+        "betfair_ex_uk",
+        "betfair_ex_eu",
+        "matchbook",
+        "smarkets",
+    ] {
+        weights.insert(bookie_key.to_string(), EXCHANGE_WEIGHT);
+    }
+    for bookie_key in ["betrivers", "bovada", "mybookieag", "betus", "playup"] {
+        weights.insert(bookie_key.to_string(), RECREATIONAL_WEIGHT);
+    }
+    return weights;
+}
+
+/// How much each bookmaker's price counts towards the consensus true odds,
+/// falling back from the most specific override (sport and market) to the
+/// bookmaker's base weight and then `default_weight`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BookmakerWeights {
+    /// Weight of bookmakers with no other entry
+    pub default_weight: f64,
+    pub bookmaker_weights: HashMap<String, f64>,
+    pub sport_weights: HashMap<String, HashMap<String, f64>>,
+    pub market_weights: HashMap<MarketType, HashMap<String, f64>>,
+    pub sport_market_weights: HashMap<String, HashMap<MarketType, HashMap<String, f64>>>,
+}
+
+impl Default for BookmakerWeights {
+    fn default() -> Self {
+        return BookmakerWeights {
+            default_weight: DEFAULT_WEIGHT,
+            bookmaker_weights: default_bookmaker_weights(),
+            sport_weights: HashMap::new(),
+            market_weights: HashMap::new(),
+            sport_market_weights: HashMap::new(),
+        };
+    }
+}
+
+impl BookmakerWeights {
+    /// Every bookmaker counts the same
+    pub fn equal() -> Self {
+        return BookmakerWeights {
+            default_weight: DEFAULT_WEIGHT,
+            bookmaker_weights: HashMap::new(),
+            sport_weights: HashMap::new(),
+            market_weights: HashMap::new(),
+            sport_market_weights: HashMap::new(),
+        };
+    }
+
+    pub fn weight_for(&self, sport_key: &str, market: &MarketType, bookie_key: &str) -> f64 {
+        if let Some(weight) = self
+            .sport_market_weights
+            .get(sport_key)
+            .and_then(|x| x.get(market))
+            .and_then(|x| x.get(bookie_key))
+        {
+            return *weight;
+        }
+
+        if let Some(weight) = self
+            .sport_weights
+            .get(sport_key)
+            .and_then(|x| x.get(bookie_key))
+        {
+            return *weight;
+        }
+
+        if let Some(weight) = self
+            .market_weights
+            .get(market)
+            .and_then(|x| x.get(bookie_key))
+        {
+            return *weight;
+        }
+
+        return *self
+            .bookmaker_weights
+            .get(bookie_key)
+            .unwrap_or(&self.default_weight);
+    }
+
+    /// Weighted mean of per-bookmaker `values`, or None if nothing carries
+    /// any weight
+    pub fn weighted_mean<'a>(
+        &self,
+        sport_key: &str,
+        market: &MarketType,
+        values: impl IntoIterator<Item = (&'a str, f64)>,
+    ) -> Option<f64> {
+        let mut total = 0.0;
+        let mut total_weight = 0.0;
+        for (bookie_key, value) in values {
+            let weight = self.weight_for(sport_key, market, bookie_key).max(0.0);
+            total += weight * value;
+            total_weight += weight;
+        }
+
+        if (total_weight <= 0.0) {
+            return None;
+        }
+        return Some(total / total_weight);
+    }
+
+    /// Moves the sport and market weights `learning_rate` of the way towards
+    /// `observed`, e.g. weights from a sharpness analysis of settled events
+    pub fn learn(
+        &mut self,
+        sport_key: &str,
+        market: &MarketType,
+        observed: &HashMap<String, f64>,
+        learning_rate: f64,
+    ) {
+        let rate = learning_rate.clamp(0.0, 1.0);
+        let current: HashMap<String, f64> = observed
+            .keys()
+            .map(|x| (x.clone(), self.weight_for(sport_key, market, x)))
+            .collect();
+
+        let weights = self
+            .sport_market_weights
+            .entry(sport_key.to_string())
+            .or_default()
+            .entry(market.clone())
+            .or_default();
+        for (bookie_key, target) in observed {
+            let weight = current[bookie_key];
+            weights.insert(bookie_key.clone(), weight + rate * (target - weight));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use crate::odds_interface::logic::{market::MarketType, weights::BookmakerWeights};
+
+#[test]
+fn test_weight_for_falls_back_to_base_weight() {
+    let mut weights = BookmakerWeights::default();
+    weights.sport_weights.insert(
+        String::from("aussierules_afl"),
+        HashMap::from([(String::from("sportsbet"), 2.0)]),
+    );
+    weights.sport_market_weights.insert(
+        String::from("aussierules_afl"),
+        HashMap::from([(
+            MarketType::Totals,
+            HashMap::from([(String::from("sportsbet"), 4.0)]),
+        )]),
+    );
+
+    assert_eq!(
+        weights.weight_for("aussierules_afl", &MarketType::Totals, "sportsbet"),
+        4.0
+    );
+    assert_eq!(
+        weights.weight_for("aussierules_afl", &MarketType::H2h, "sportsbet"),
+        2.0
+    );
+    assert_eq!(
+        weights.weight_for("baseball_kbo", &MarketType::H2h, "sportsbet"),
+        1.0
+    );
+    assert_eq!(
+        weights.weight_for("baseball_kbo", &MarketType::H2h, "pinnacle"),
+        3.0
+    );
+}
+
+#[test]
+fn test_weighted_mean() {
+    let weights = BookmakerWeights::default();
+    let mean = weights
+        .weighted_mean(
+            "baseball_kbo",
+            &MarketType::H2h,
+            [("pinnacle", 0.5), ("sportsbet", 0.6)],
+        )
+        .unwrap();
+    assert!((mean - (3.0 * 0.5 + 0.6) / 4.0).abs() < 1e-12);
+
+    assert!(weights
+        .weighted_mean("baseball_kbo", &MarketType::H2h, [])
+        .is_none());
+}
+
+#[test]
+fn test_learn_moves_towards_observed() {
+    let mut weights = BookmakerWeights::default();
+    let observed = HashMap::from([(String::from("pinnacle"), 1.0)]);
+
+    weights.learn("baseball_kbo", &MarketType::H2h, &observed, 0.5);
+    assert_eq!(
+        weights.weight_for("baseball_kbo", &MarketType::H2h, "pinnacle"),
+        2.0
+    );
+    // other sports keep the base weight
+    assert_eq!(
+        weights.weight_for("aussierules_afl", &MarketType::H2h, "pinnacle"),
+        3.0
+    );
+
+    weights.learn("baseball_kbo", &MarketType::H2h, &observed, 1.0);
+    assert_eq!(
+        weights.weight_for("baseball_kbo", &MarketType::H2h, "pinnacle"),
+        1.0
+    );
+}
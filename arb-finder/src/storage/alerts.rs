@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+
+use super::{parse_column, SnapshotStore, StorageError};
+use crate::odds_interface::logic::{
+    alerts::{AlertRecord, OpportunityKey},
+    market::MarketType,
+    movement::MoveDirection,
+    odds::Odds,
+};
+
+const ALERT_COLUMNS: &str = "event_id, sport_key, commence_time, matchup, market_key,
+    outcome_name, point, bookmaker_key, price, percent_ev, alerted_at, gone_at, direction";
+
+/// Matches the row of one opportunity or steam move, with its key as ?1 to
+/// ?6
+const KEY_FILTER: &str = "event_id = ?1 AND market_key = ?2 AND outcome_name = ?3
+    AND IFNULL(point, 0) = IFNULL(?4, 0) AND bookmaker_key = ?5
+    AND IFNULL(direction, '') = IFNULL(?6, '')";
+
+fn direction_param(key: &OpportunityKey) -> Option<String> {
+    return key.direction.map(|x| x.to_string());
+}
+
+fn alert_from_row(row: &Row) -> rusqlite::Result<AlertRecord> {
+    return Ok(AlertRecord {
+        key: OpportunityKey {
+            event_id: row.get(0)?,
+            market_key: parse_column::<MarketType>(row, 4)?,
+            outcome_key: row.get(5)?,
+            point: row.get(6)?,
+            bookmaker_key: row.get(7)?,
+            direction: match row.get::<_, Option<String>>(12)? {
+                Some(_) => Some(parse_column::<MoveDirection>(row, 12)?),
+                None => None,
+            },
+        },
+        sport_key: row.get(1)?,
+        commence_time: row.get(2)?,
+        matchup: row.get(3)?,
+        offered_odds: Odds::Decimal(row.get(8)?),
+        percent_ev: row.get(9)?,
+        alerted_at: row.get(10)?,
+        gone_at: row.get(11)?,
+    });
+}
+
+impl SnapshotStore {
+    /// The last alert sent for the opportunity `key`, if any
+    pub fn alert_for(&self, key: &OpportunityKey) -> Result<Option<AlertRecord>, StorageError> {
+        let alert = self
+            .conn
+            .query_row(
+                &format!("SELECT {ALERT_COLUMNS} FROM alerts WHERE {KEY_FILTER}"),
+                params![
+                    key.event_id,
+                    key.market_key.to_string(),
+                    key.outcome_key,
+                    key.point,
+                    key.bookmaker_key,
+                    direction_param(key)
+                ],
+                alert_from_row,
+            )
+            .optional()?;
+        return Ok(alert);
+    }
+
+    /// Saves `alert`, replacing any earlier alert for the same opportunity
+    pub fn save_alert(&mut self, alert: &AlertRecord) -> Result<(), StorageError> {
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO alerts ({ALERT_COLUMNS})
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"
+            ),
+            params![
+                alert.key.event_id,
+                alert.sport_key,
+                alert.commence_time,
+                alert.matchup,
+                alert.key.market_key.to_string(),
+                alert.key.outcome_key,
+                alert.key.point,
+                alert.key.bookmaker_key,
+                alert.offered_odds.get_decimal(),
+                alert.percent_ev,
+                alert.alerted_at,
+                alert.gone_at,
+                direction_param(&alert.key)
+            ],
+        )?;
+        return Ok(());
+    }
+
+    /// Opportunity alerts of `sport_key` that haven't gone yet, oldest
+    /// first. Steam moves are over once alerted, so they never go.
+    pub fn active_alerts(&self, sport_key: &str) -> Result<Vec<AlertRecord>, StorageError> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {ALERT_COLUMNS} FROM alerts
+            WHERE sport_key = ?1 AND gone_at IS NULL AND direction IS NULL
+            ORDER BY alerted_at"
+        ))?;
+        let rows = statement.query_map(params![sport_key], alert_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    pub fn set_alert_gone(
+        &mut self,
+        key: &OpportunityKey,
+        gone_at: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        self.conn.execute(
+            &format!("UPDATE alerts SET gone_at = ?7 WHERE {KEY_FILTER}"),
+            params![
+                key.event_id,
+                key.market_key.to_string(),
+                key.outcome_key,
+                key.point,
+                key.bookmaker_key,
+                direction_param(key),
+                gone_at
+            ],
+        )?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Row};
+
+use super::{parse_column, SnapshotStore, StorageError};
+use crate::odds_interface::logic::{
+    clv::TrackedOpportunity, devig::DevigMethod, event::PricingModel, market::MarketType,
+    odds::Odds,
+};
+
+const OPPORTUNITY_COLUMNS: &str = "id, event_id, sport_key, commence_time, market_key,
+    outcome_name, point, bookmaker_key, model, devig_method, price, true_price, percent_ev,
+    found_at, closing_price, closed_at";
+
+fn opportunity_from_row(row: &Row) -> rusqlite::Result<TrackedOpportunity> {
+    return Ok(TrackedOpportunity {
+        id: Some(row.get(0)?),
+        event_id: row.get(1)?,
+        sport_key: row.get(2)?,
+        commence_time: row.get(3)?,
+        market_key: parse_column::<MarketType>(row, 4)?,
+        outcome_key: row.get(5)?,
+        point: row.get(6)?,
+        bookmaker_key: row.get(7)?,
+        model: parse_column::<PricingModel>(row, 8)?,
+        devig_method: parse_column::<DevigMethod>(row, 9)?,
+        offered_odds: Odds::Decimal(row.get(10)?),
+        true_odds: Odds::Decimal(row.get(11)?),
+        percent_ev: row.get(12)?,
+        found_at: row.get(13)?,
+        closing_odds: row.get::<_, Option<f64>>(14)?.map(Odds::Decimal),
+        closed_at: row.get(15)?,
+    });
+}
+
+impl SnapshotStore {
+    /// Starts tracking `opportunity`, returning its id. Only the first
+    /// sighting of the same bookmaker's price on an outcome is kept, so
+    /// `None` is returned if it is already tracked.
+    pub fn record_opportunity(
+        &mut self,
+        opportunity: &TrackedOpportunity,
+    ) -> Result<Option<i64>, StorageError> {
+        let num_inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO opportunities (event_id, sport_key, commence_time, market_key,
+                outcome_name, point, bookmaker_key, model, devig_method, price, true_price,
+                percent_ev, found_at, closing_price, closed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                opportunity.event_id,
+                opportunity.sport_key,
+                opportunity.commence_time,
+                opportunity.market_key.to_string(),
+                opportunity.outcome_key,
+                opportunity.point,
+                opportunity.bookmaker_key,
+                opportunity.model.to_string(),
+                opportunity.devig_method.to_string(),
+                opportunity.offered_odds.get_decimal(),
+                opportunity.true_odds.get_decimal(),
+                opportunity.percent_ev,
+                opportunity.found_at,
+                opportunity.closing_odds.map(|x| x.get_decimal()),
+                opportunity.closed_at
+            ],
+        )?;
+        if (num_inserted == 0) {
+            return Ok(None);
+        }
+        return Ok(Some(self.conn.last_insert_rowid()));
+    }
+
+    /// Every tracked opportunity, in the order they were found
+    pub fn opportunities(&self) -> Result<Vec<TrackedOpportunity>, StorageError> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {OPPORTUNITY_COLUMNS} FROM opportunities ORDER BY found_at, id"
+        ))?;
+        let rows = statement.query_map([], opportunity_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    /// Opportunities still waiting on their closing odds, soonest to start
+    /// first
+    pub fn unclosed_opportunities(&self) -> Result<Vec<TrackedOpportunity>, StorageError> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {OPPORTUNITY_COLUMNS} FROM opportunities
+            WHERE closing_price IS NULL
+            ORDER BY commence_time, id"
+        ))?;
+        let rows = statement.query_map([], opportunity_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    pub fn set_closing_odds(
+        &mut self,
+        opportunity_id: i64,
+        closing_odds: &Odds,
+        closed_at: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        self.conn.execute(
+            "UPDATE opportunities SET closing_price = ?1, closed_at = ?2 WHERE id = ?3",
+            params![closing_odds.get_decimal(), closed_at, opportunity_id],
+        )?;
+        return Ok(());
+    }
+}
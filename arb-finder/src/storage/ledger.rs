@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+
+use super::{parse_column, stored_event_from_row, SnapshotStore, StorageError, StoredEvent};
+use crate::odds_interface::logic::{
+    ledger::{Bet, BetStatus},
+    market::MarketType,
+    odds::Odds,
+};
+
+const BET_COLUMNS: &str = "id, event_id, sport_key, home_team, away_team, commence_time,
+    market_key, outcome_name, point, bookmaker_key, price, stake, placed_at, status, settled_at";
+
+fn bet_from_row(row: &Row) -> rusqlite::Result<Bet> {
+    return Ok(Bet {
+        id: Some(row.get(0)?),
+        event_id: row.get(1)?,
+        sport_key: row.get(2)?,
+        home_team: row.get(3)?,
+        away_team: row.get(4)?,
+        commence_time: row.get(5)?,
+        market_key: parse_column::<MarketType>(row, 6)?,
+        outcome_key: row.get(7)?,
+        point: row.get(8)?,
+        bookmaker_key: row.get(9)?,
+        odds: Odds::Decimal(row.get(10)?),
+        stake: row.get(11)?,
+        placed_at: row.get(12)?,
+        status: parse_column::<BetStatus>(row, 13)?,
+        settled_at: row.get(14)?,
+    });
+}
+
+impl SnapshotStore {
+    /// Adds `bet` to the ledger, returning its id
+    pub fn record_bet(&mut self, bet: &Bet) -> Result<i64, StorageError> {
+        self.conn.execute(
+            "INSERT INTO bets (event_id, sport_key, home_team, away_team, commence_time,
+                market_key, outcome_name, point, bookmaker_key, price, stake, placed_at, status,
+                settled_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                bet.event_id,
+                bet.sport_key,
+                bet.home_team,
+                bet.away_team,
+                bet.commence_time,
+                bet.market_key.to_string(),
+                bet.outcome_key,
+                bet.point,
+                bet.bookmaker_key,
+                bet.odds.get_decimal(),
+                bet.stake,
+                bet.placed_at,
+                bet.status.to_string(),
+                bet.settled_at
+            ],
+        )?;
+        return Ok(self.conn.last_insert_rowid());
+    }
+
+    /// Every bet in the ledger, in the order they were placed
+    pub fn bets(&self) -> Result<Vec<Bet>, StorageError> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {BET_COLUMNS} FROM bets ORDER BY placed_at, id"
+        ))?;
+        let rows = statement.query_map([], bet_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    pub fn pending_bets(&self) -> Result<Vec<Bet>, StorageError> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {BET_COLUMNS} FROM bets WHERE status = ?1 ORDER BY placed_at, id"
+        ))?;
+        let rows = statement.query_map(params![BetStatus::Pending.to_string()], bet_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    pub fn settle_bet(
+        &mut self,
+        bet_id: i64,
+        status: BetStatus,
+        settled_at: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        self.conn.execute(
+            "UPDATE bets SET status = ?1, settled_at = ?2 WHERE id = ?3",
+            params![status.to_string(), settled_at, bet_id],
+        )?;
+        return Ok(());
+    }
+
+    /// An event saved with a snapshot, to fill in the details of a bet on it
+    pub fn stored_event(&self, event_id: &str) -> Result<Option<StoredEvent>, StorageError> {
+        return Ok(self
+            .conn
+            .query_row(
+                "SELECT e.id, e.sport_key, e.sport_title, e.commence_time, e.home_team,
+                    e.away_team, (SELECT COUNT(*) FROM snapshots s WHERE s.event_id = e.id)
+                FROM events e
+                WHERE e.id = ?1",
+                params![event_id],
+                stored_event_from_row,
+            )
+            .optional()?);
+    }
+}
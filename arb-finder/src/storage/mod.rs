@@ -0,0 +1,526 @@
+//! Every fetched event, bookmaker, market and outcome, saved to SQLite with
+//! the time it was fetched, so line movement can be studied after the fact.
+//! The same database holds the ledger of bets we placed, the opportunities
+//! we alerted on, to check against the closing line and to not alert on
+//! twice, final scores, and the requests spent from the quota.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row, Transaction};
+use serde_json::Value;
+
+use crate::odds_interface::logic::{event::Event, market::MarketType, odds::Odds};
+
+mod alerts;
+mod clv;
+mod ledger;
+mod quota;
+mod results;
+
+#[cfg(test)]
+mod storage_test;
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// have been applied, so never edit or reorder one that has shipped: add a
+/// new one at the end instead.
+const MIGRATIONS: [&str; 8] = [
+    "CREATE TABLE events (
+        id TEXT PRIMARY KEY,
+        sport_key TEXT NOT NULL,
+        sport_title TEXT NOT NULL,
+        commence_time TEXT NOT NULL,
+        home_team TEXT NOT NULL,
+        away_team TEXT NOT NULL
+    );
+    CREATE TABLE snapshots (
+        id INTEGER PRIMARY KEY,
+        event_id TEXT NOT NULL REFERENCES events(id),
+        fetched_at TEXT NOT NULL
+    );
+    CREATE TABLE prices (
+        snapshot_id INTEGER NOT NULL REFERENCES snapshots(id),
+        bookmaker_key TEXT NOT NULL,
+        bookmaker_title TEXT NOT NULL,
+        last_update TEXT NOT NULL,
+        market_key TEXT NOT NULL,
+        outcome_name TEXT NOT NULL,
+        point REAL,
+        price REAL NOT NULL
+    );",
+    "CREATE INDEX snapshots_event ON snapshots(event_id, fetched_at);
+    CREATE INDEX prices_outcome ON prices(market_key, outcome_name, bookmaker_key);",
+    "CREATE TABLE bets (
+        id INTEGER PRIMARY KEY,
+        event_id TEXT NOT NULL,
+        sport_key TEXT NOT NULL,
+        home_team TEXT NOT NULL,
+        away_team TEXT NOT NULL,
+        commence_time TEXT NOT NULL,
+        market_key TEXT NOT NULL,
+        outcome_name TEXT NOT NULL,
+        point REAL,
+        bookmaker_key TEXT NOT NULL,
+        price REAL NOT NULL,
+        stake REAL NOT NULL,
+        placed_at TEXT NOT NULL,
+        status TEXT NOT NULL,
+        settled_at TEXT
+    );
+    CREATE INDEX bets_status ON bets(status, sport_key);",
+    "CREATE TABLE opportunities (
+        id INTEGER PRIMARY KEY,
+        event_id TEXT NOT NULL,
+        sport_key TEXT NOT NULL,
+        commence_time TEXT NOT NULL,
+        market_key TEXT NOT NULL,
+        outcome_name TEXT NOT NULL,
+        point REAL,
+        bookmaker_key TEXT NOT NULL,
+        model TEXT NOT NULL,
+        devig_method TEXT NOT NULL,
+        price REAL NOT NULL,
+        true_price REAL NOT NULL,
+        percent_ev REAL NOT NULL,
+        found_at TEXT NOT NULL,
+        closing_price REAL,
+        closed_at TEXT
+    );
+    CREATE UNIQUE INDEX opportunities_identity
+        ON opportunities(event_id, market_key, outcome_name, IFNULL(point, 0), bookmaker_key);
+    CREATE INDEX opportunities_open ON opportunities(closing_price, commence_time);",
+    "CREATE TABLE results (
+        event_id TEXT PRIMARY KEY,
+        sport_key TEXT NOT NULL,
+        commence_time TEXT NOT NULL,
+        home_team TEXT NOT NULL,
+        away_team TEXT NOT NULL,
+        home_score TEXT NOT NULL,
+        away_score TEXT NOT NULL,
+        last_update TEXT
+    );
+    CREATE INDEX results_sport ON results(sport_key, commence_time);",
+    "CREATE TABLE alerts (
+        event_id TEXT NOT NULL,
+        sport_key TEXT NOT NULL,
+        commence_time TEXT NOT NULL,
+        matchup TEXT NOT NULL,
+        market_key TEXT NOT NULL,
+        outcome_name TEXT NOT NULL,
+        point REAL,
+        bookmaker_key TEXT NOT NULL,
+        price REAL NOT NULL,
+        percent_ev REAL NOT NULL,
+        alerted_at TEXT NOT NULL,
+        gone_at TEXT
+    );
+    CREATE UNIQUE INDEX alerts_identity
+        ON alerts(event_id, market_key, outcome_name, IFNULL(point, 0), bookmaker_key);
+    CREATE INDEX alerts_active ON alerts(sport_key, gone_at);",
+    "CREATE TABLE quota_spend (
+        spent_at TEXT NOT NULL,
+        cost INTEGER NOT NULL
+    );
+    CREATE INDEX quota_spend_time ON quota_spend(spent_at);",
+    // steam moves are alerted on once per direction, without a bookmaker
+    "ALTER TABLE alerts ADD COLUMN direction TEXT;
+    DROP INDEX alerts_identity;
+    CREATE UNIQUE INDEX alerts_identity ON alerts(event_id, market_key, outcome_name,
+        IFNULL(point, 0), bookmaker_key, IFNULL(direction, ''));",
+];
+
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    /// The database was written by a newer version of arb-finder
+    UnsupportedSchema {
+        found: i32,
+        supported: i32,
+    },
+    /// A stored snapshot couldn't be turned back into an `Event`
+    Deserialize(serde_json::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(e) => write!(f, "database error: {e}"),
+            StorageError::UnsupportedSchema { found, supported } => write!(
+                f,
+                "database schema version {found} is newer than the supported version {supported}"
+            ),
+            StorageError::Deserialize(e) => write!(f, "invalid stored snapshot: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        return StorageError::Sqlite(e);
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        return StorageError::Deserialize(e);
+    }
+}
+
+/// An event with its odds, as fetched at `fetched_at`
+#[derive(Debug, Clone)]
+pub struct EventSnapshot {
+    pub fetched_at: DateTime<Utc>,
+    pub event: Event,
+}
+
+/// One bookmaker's price for an outcome, as it was at `fetched_at`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceRecord {
+    pub fetched_at: DateTime<Utc>,
+    pub bookmaker_key: String,
+    pub bookmaker_title: String,
+    /// When the bookmaker last changed its prices, per the api
+    pub last_update: DateTime<Utc>,
+    pub market_key: MarketType,
+    pub outcome_name: String,
+    pub point: Option<f64>,
+    pub odds: Odds,
+}
+
+/// An event as it was last stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEvent {
+    pub id: String,
+    pub sport_key: String,
+    pub sport_title: String,
+    pub commence_time: DateTime<Utc>,
+    pub home_team: String,
+    pub away_team: String,
+    pub snapshot_count: usize,
+}
+
+pub struct SnapshotStore {
+    conn: Connection,
+}
+
+impl SnapshotStore {
+    /// Opens (or creates) the database at `path` and migrates it to the
+    /// latest schema
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SnapshotStore, StorageError> {
+        return Self::with_connection(Connection::open(path)?);
+    }
+
+    pub fn open_in_memory() -> Result<SnapshotStore, StorageError> {
+        return Self::with_connection(Connection::open_in_memory()?);
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<SnapshotStore, StorageError> {
+        migrate(&mut conn)?;
+        return Ok(SnapshotStore { conn });
+    }
+
+    /// Saves a snapshot of every event in one transaction, returning the
+    /// number of prices written
+    pub fn save_events(
+        &mut self,
+        events: &[Event],
+        fetched_at: DateTime<Utc>,
+    ) -> Result<usize, StorageError> {
+        let tx = self.conn.transaction()?;
+        let mut num_prices = 0;
+        for event in events {
+            num_prices += save_event(&tx, event, fetched_at)?;
+        }
+        tx.commit()?;
+        return Ok(num_prices);
+    }
+
+    /// Stored events of `sport_key`, soonest first
+    pub fn events_for_sport(&self, sport_key: &str) -> Result<Vec<StoredEvent>, StorageError> {
+        let mut statement = self.conn.prepare(
+            "SELECT e.id, e.sport_key, e.sport_title, e.commence_time, e.home_team, e.away_team,
+                (SELECT COUNT(*) FROM snapshots s WHERE s.event_id = e.id)
+            FROM events e
+            WHERE e.sport_key = ?1
+            ORDER BY e.commence_time, e.id",
+        )?;
+        let rows = statement.query_map(params![sport_key], stored_event_from_row)?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    /// The most recent snapshot of `event_id`, rebuilt into an `Event`
+    pub fn latest_snapshot(&self, event_id: &str) -> Result<Option<EventSnapshot>, StorageError> {
+        let latest: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM snapshots
+                WHERE event_id = ?1
+                ORDER BY fetched_at DESC, id DESC
+                LIMIT 1",
+                params![event_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        return match latest {
+            Some(snapshot_id) => Ok(Some(self.snapshot(snapshot_id)?)),
+            None => Ok(None),
+        };
+    }
+
+    /// The first snapshot of `event_id` fetched at or after `since`, e.g. to
+    /// compare with prices from before a move
+    pub fn earliest_snapshot_since(
+        &self,
+        event_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Option<EventSnapshot>, StorageError> {
+        let earliest: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM snapshots
+                WHERE event_id = ?1 AND fetched_at >= ?2
+                ORDER BY fetched_at, id
+                LIMIT 1",
+                params![event_id, since],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        return match earliest {
+            Some(snapshot_id) => Ok(Some(self.snapshot(snapshot_id)?)),
+            None => Ok(None),
+        };
+    }
+
+    /// Every snapshot of every event in `sport_key`, oldest first, e.g. to
+    /// replay through the opportunity finders
+    pub fn snapshots_for_sport(&self, sport_key: &str) -> Result<Vec<EventSnapshot>, StorageError> {
+        let mut statement = self.conn.prepare(
+            "SELECT s.id
+            FROM snapshots s
+            JOIN events e ON e.id = s.event_id
+            WHERE e.sport_key = ?1
+            ORDER BY s.fetched_at, s.id",
+        )?;
+        let snapshot_ids = statement
+            .query_map(params![sport_key], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return snapshot_ids.into_iter().map(|x| self.snapshot(x)).collect();
+    }
+
+    /// Rebuilds the event as it was in snapshot `snapshot_id`
+    fn snapshot(&self, snapshot_id: i64) -> Result<EventSnapshot, StorageError> {
+        let (fetched_at, mut event) = self.conn.query_row(
+            "SELECT s.fetched_at, e.id, e.sport_key, e.sport_title, e.commence_time,
+                e.home_team, e.away_team
+            FROM snapshots s
+            JOIN events e ON e.id = s.event_id
+            WHERE s.id = ?1",
+            params![snapshot_id],
+            |row| {
+                let fetched_at: DateTime<Utc> = row.get(0)?;
+                let commence_time: DateTime<Utc> = row.get(4)?;
+                let event = serde_json::json!({
+                    "id": row.get::<_, String>(1)?,
+                    "sport_key": row.get::<_, String>(2)?,
+                    "sport_title": row.get::<_, String>(3)?,
+                    "commence_time": commence_time,
+                    "home_team": row.get::<_, String>(5)?,
+                    "away_team": row.get::<_, String>(6)?,
+                });
+                return Ok((fetched_at, event));
+            },
+        )?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT bookmaker_key, bookmaker_title, last_update, market_key, outcome_name,
+                point, price
+            FROM prices
+            WHERE snapshot_id = ?1
+            ORDER BY rowid",
+        )?;
+        let mut rows = statement.query(params![snapshot_id])?;
+
+        // prices were written bookie by bookie and market by market
+        let mut bookmakers: Vec<Value> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let bookie_key: String = row.get(0)?;
+            let market_key: String = row.get(3)?;
+            let outcome = serde_json::json!({
+                "name": row.get::<_, String>(4)?,
+                "point": row.get::<_, Option<f64>>(5)?,
+                "price": row.get::<_, f64>(6)?,
+            });
+
+            if (bookmakers
+                .last()
+                .is_none_or(|x| x["key"] != bookie_key.as_str()))
+            {
+                let last_update: DateTime<Utc> = row.get(2)?;
+                bookmakers.push(serde_json::json!({
+                    "key": bookie_key,
+                    "title": row.get::<_, String>(1)?,
+                    "last_update": last_update,
+                    "markets": [],
+                }));
+            }
+            let markets = bookmakers.last_mut().unwrap()["markets"]
+                .as_array_mut()
+                .unwrap();
+            if (markets
+                .last()
+                .is_none_or(|x| x["key"] != market_key.as_str()))
+            {
+                markets.push(serde_json::json!({ "key": market_key, "outcomes": [] }));
+            }
+            markets.last_mut().unwrap()["outcomes"]
+                .as_array_mut()
+                .unwrap()
+                .push(outcome);
+        }
+        event["bookmakers"] = Value::from(bookmakers);
+
+        let mut event = serde_json::from_value::<Event>(event)?;
+        event.set_fetched_at(fetched_at);
+        return Ok(EventSnapshot { fetched_at, event });
+    }
+
+    /// Every stored price for `outcome_name` of `market` in `event_id`,
+    /// oldest first, optionally for only one bookmaker
+    pub fn price_history(
+        &self,
+        event_id: &str,
+        market: &MarketType,
+        outcome_name: &str,
+        bookmaker_key: Option<&str>,
+    ) -> Result<Vec<PriceRecord>, StorageError> {
+        let mut statement = self.conn.prepare(
+            "SELECT s.fetched_at, p.bookmaker_key, p.bookmaker_title, p.last_update,
+                p.market_key, p.outcome_name, p.point, p.price
+            FROM prices p
+            JOIN snapshots s ON s.id = p.snapshot_id
+            WHERE s.event_id = ?1 AND p.market_key = ?2 AND p.outcome_name = ?3
+                AND (?4 IS NULL OR p.bookmaker_key = ?4)
+            ORDER BY s.fetched_at, p.bookmaker_key",
+        )?;
+        let rows = statement.query_map(
+            params![event_id, market.to_string(), outcome_name, bookmaker_key],
+            |row| {
+                return Ok(PriceRecord {
+                    fetched_at: row.get(0)?,
+                    bookmaker_key: row.get(1)?,
+                    bookmaker_title: row.get(2)?,
+                    last_update: row.get(3)?,
+                    market_key: parse_column::<MarketType>(row, 4)?,
+                    outcome_name: row.get(5)?,
+                    point: row.get(6)?,
+                    odds: Odds::Decimal(row.get(7)?),
+                });
+            },
+        )?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+}
+
+/// Parses a text column stored with `to_string`
+fn parse_column<T: FromStr>(row: &Row, index: usize) -> rusqlite::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    return T::from_str(&row.get::<_, String>(index)?).map_err(|e| {
+        return rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e));
+    });
+}
+
+fn stored_event_from_row(row: &Row) -> rusqlite::Result<StoredEvent> {
+    return Ok(StoredEvent {
+        id: row.get(0)?,
+        sport_key: row.get(1)?,
+        sport_title: row.get(2)?,
+        commence_time: row.get(3)?,
+        home_team: row.get(4)?,
+        away_team: row.get(5)?,
+        snapshot_count: row.get::<_, i64>(6)? as usize,
+    });
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<i32> {
+    return conn.query_row("PRAGMA user_version", [], |row| row.get(0));
+}
+
+/// Applies every migration the database hasn't seen yet
+fn migrate(conn: &mut Connection) -> Result<(), StorageError> {
+    let found = schema_version(conn)?;
+    if (found > SCHEMA_VERSION) {
+        return Err(StorageError::UnsupportedSchema {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    let tx = conn.transaction()?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(found as usize) {
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i as i32 + 1)?;
+    }
+    tx.commit()?;
+    return Ok(());
+}
+
+fn save_event(
+    tx: &Transaction,
+    event: &Event,
+    fetched_at: DateTime<Utc>,
+) -> Result<usize, StorageError> {
+    // events get rescheduled, so the start time follows the latest fetch
+    tx.execute(
+        "INSERT INTO events (id, sport_key, sport_title, commence_time, home_team, away_team)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT(id) DO UPDATE SET commence_time = excluded.commence_time",
+        params![
+            event.id(),
+            event.sport_key(),
+            event.sport_title(),
+            event.commence_time(),
+            event.home_team(),
+            event.away_team()
+        ],
+    )?;
+
+    tx.execute(
+        "INSERT INTO snapshots (event_id, fetched_at) VALUES (?1, ?2)",
+        params![event.id(), fetched_at],
+    )?;
+    let snapshot_id = tx.last_insert_rowid();
+
+    let mut statement = tx.prepare_cached(
+        "INSERT INTO prices (snapshot_id, bookmaker_key, bookmaker_title, last_update,
+            market_key, outcome_name, point, price)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?;
+    let mut num_prices = 0;
+    for bookie in event.bookmakers() {
+        for market in &bookie.markets {
+            for outcome in &market.outcomes {
+                statement.execute(params![
+                    snapshot_id,
+                    bookie.key,
+                    bookie.title,
+                    bookie.last_update(),
+                    market.key.to_string(),
+                    outcome.name,
+                    outcome.point,
+                    outcome.price.get_decimal()
+                ])?;
+                num_prices += 1;
+            }
+        }
+    }
+    return Ok(num_prices);
+}
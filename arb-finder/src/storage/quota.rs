@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use super::{SnapshotStore, StorageError};
+
+impl SnapshotStore {
+    /// Records `cost` requests charged at `spent_at`
+    pub fn save_spend(&mut self, spent_at: DateTime<Utc>, cost: u32) -> Result<(), StorageError> {
+        self.conn.execute(
+            "INSERT INTO quota_spend (spent_at, cost) VALUES (?1, ?2)",
+            params![spent_at, cost],
+        )?;
+        return Ok(());
+    }
+
+    /// Requests charged since `since`, by every run sharing the database
+    pub fn spent_since(&self, since: DateTime<Utc>) -> Result<u32, StorageError> {
+        let spent: u32 = self.conn.query_row(
+            "SELECT IFNULL(SUM(cost), 0) FROM quota_spend WHERE spent_at >= ?1",
+            params![since],
+            |row| row.get(0),
+        )?;
+        return Ok(spent);
+    }
+}
@@ -0,0 +1,84 @@
+use rusqlite::params;
+
+use super::{SnapshotStore, StorageError};
+use crate::odds_interface::logic::score::{EventScore, TeamScore};
+
+impl SnapshotStore {
+    /// Keeps the final score of every completed event in `scores`,
+    /// returning how many were saved. The scores endpoint only goes back
+    /// 3 days, so this is how results outlive it.
+    pub fn save_results(&mut self, scores: &[EventScore]) -> Result<usize, StorageError> {
+        let tx = self.conn.transaction()?;
+        let mut num_saved = 0;
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT OR REPLACE INTO results (event_id, sport_key, commence_time, home_team,
+                    away_team, home_score, away_score, last_update)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for score in scores {
+                let team_score = |team: &str| {
+                    return score
+                        .scores
+                        .iter()
+                        .find(|x| x.name == team)
+                        .map(|x| x.score.clone());
+                };
+                let (home_score, away_score) =
+                    match (team_score(&score.home_team), team_score(&score.away_team)) {
+                        (Some(x), Some(y)) if score.completed => (x, y),
+                        _ => continue,
+                    };
+
+                statement.execute(params![
+                    score.id,
+                    score.sport_key,
+                    score.commence_time,
+                    score.home_team,
+                    score.away_team,
+                    home_score,
+                    away_score,
+                    score.last_update
+                ])?;
+                num_saved += 1;
+            }
+        }
+        tx.commit()?;
+        return Ok(num_saved);
+    }
+
+    /// Saved final scores for `sport_key`, oldest event first
+    pub fn results_for_sport(&self, sport_key: &str) -> Result<Vec<EventScore>, StorageError> {
+        let mut statement = self.conn.prepare(
+            "SELECT event_id, sport_key, commence_time, home_team, away_team, home_score,
+                away_score, last_update
+            FROM results
+            WHERE sport_key = ?1
+            ORDER BY commence_time, event_id",
+        )?;
+        let rows = statement.query_map(params![sport_key], |row| {
+            let home_team: String = row.get(3)?;
+            let away_team: String = row.get(4)?;
+            return Ok(EventScore {
+                id: row.get(0)?,
+                sport_key: row.get(1)?,
+                commence_time: row.get(2)?,
+                completed: true,
+                scores: vec![
+                    TeamScore {
+                        name: home_team.clone(),
+                        score: row.get(5)?,
+                    },
+                    TeamScore {
+                        name: away_team.clone(),
+                        score: row.get(6)?,
+                    },
+                ],
+                home_team,
+                away_team,
+                last_update: row.get(7)?,
+            });
+        })?;
+        return Ok(rows.collect::<Result<Vec<_>, _>>()?);
+    }
+}
@@ -0,0 +1,430 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde_json::Value;
+
+use crate::odds_interface::logic::{
+    alerts::{AlertRecord, OpportunityKey},
+    clv::TrackedOpportunity,
+    devig::DevigMethod,
+    event::{Event, PricingModel},
+    ledger::{Bet, BetStatus},
+    market::MarketType,
+    movement::MoveDirection,
+    odds::Odds,
+    score::EventScore,
+};
+use crate::storage::{schema_version, SnapshotStore, StorageError, SCHEMA_VERSION};
+
+const AFL_EVENT_ID: &str = "132905fc8e35fe3551638c40c80fe9e6";
+
+/// The AFL test event, with tab's Saints price set to `saints_price`
+fn get_afl_event(saints_price: f64) -> Event {
+    let raw_file_string =
+        fs::read_to_string("./src/odds_interface/logic/event/event_test/testdata_afl.json")
+            .expect("Unable to read file");
+    let mut raw: Value = serde_json::from_str(&raw_file_string).unwrap();
+
+    for bookie in raw["bookmakers"].as_array_mut().unwrap() {
+        if (bookie["key"] != "tab") {
+            continue;
+        }
+        for market in bookie["markets"].as_array_mut().unwrap() {
+            if (market["key"] != "h2h") {
+                continue;
+            }
+            for outcome in market["outcomes"].as_array_mut().unwrap() {
+                if (outcome["name"] == "St Kilda Saints") {
+                    outcome["price"] = Value::from(saints_price);
+                }
+            }
+        }
+    }
+    return serde_json::from_value::<Event>(raw).unwrap();
+}
+
+fn get_fetch_time() -> DateTime<Utc> {
+    return DateTime::parse_from_rfc3339("2024-08-04T05:16:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+}
+
+fn get_temp_db_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("arb_finder_{name}_{0}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+    return path;
+}
+
+#[test]
+fn test_new_database_is_migrated() {
+    let store = SnapshotStore::open_in_memory().unwrap();
+    assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+}
+
+#[test]
+fn test_price_history() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let first_fetch = get_fetch_time();
+    let second_fetch = first_fetch + Duration::minutes(5);
+
+    // 4 bookies with 3 markets of 2 outcomes each
+    let num_prices = store
+        .save_events(&[get_afl_event(2.3)], first_fetch)
+        .unwrap();
+    assert_eq!(num_prices, 24);
+    store
+        .save_events(&[get_afl_event(2.45)], second_fetch)
+        .unwrap();
+
+    let history = store
+        .price_history(
+            AFL_EVENT_ID,
+            &MarketType::H2h,
+            "St Kilda Saints",
+            Some("tab"),
+        )
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].fetched_at, first_fetch);
+    assert_eq!(history[0].odds, Odds::Decimal(2.3));
+    assert_eq!(history[1].fetched_at, second_fetch);
+    assert_eq!(history[1].odds, Odds::Decimal(2.45));
+    assert_eq!(history[1].bookmaker_title, "TAB");
+    assert_eq!(history[1].market_key, MarketType::H2h);
+
+    let all_bookies = store
+        .price_history(AFL_EVENT_ID, &MarketType::H2h, "St Kilda Saints", None)
+        .unwrap();
+    assert_eq!(all_bookies.len(), 8);
+
+    let spreads = store
+        .price_history(
+            AFL_EVENT_ID,
+            &MarketType::Spreads,
+            "Brisbane Lions",
+            Some("tab"),
+        )
+        .unwrap();
+    assert!(spreads.iter().all(|x| x.point.is_some()));
+
+    let events = store.events_for_sport("aussierules_afl").unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].home_team, "St Kilda Saints");
+    assert_eq!(events[0].snapshot_count, 2);
+
+    // the event is pushed back a day
+    let rescheduled = get_afl_event(2.45).commence_time() + Duration::days(1);
+    let mut raw: Value = serde_json::from_str(
+        &fs::read_to_string("./src/odds_interface/logic/event/event_test/testdata_afl.json")
+            .unwrap(),
+    )
+    .unwrap();
+    raw["commence_time"] = serde_json::to_value(rescheduled).unwrap();
+    store
+        .save_events(
+            &[serde_json::from_value::<Event>(raw).unwrap()],
+            second_fetch + Duration::minutes(5),
+        )
+        .unwrap();
+    let events = store.events_for_sport("aussierules_afl").unwrap();
+    assert_eq!(events[0].commence_time, rescheduled);
+    assert_eq!(events[0].snapshot_count, 3);
+}
+
+#[test]
+fn test_reopen_keeps_snapshots() {
+    let path = get_temp_db_path("reopen");
+    {
+        let mut store = SnapshotStore::open(&path).unwrap();
+        store
+            .save_events(&[get_afl_event(2.3)], get_fetch_time())
+            .unwrap();
+    }
+
+    let store = SnapshotStore::open(&path).unwrap();
+    assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+    assert_eq!(
+        store.events_for_sport("aussierules_afl").unwrap()[0].snapshot_count,
+        1
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_newer_schema_is_refused() {
+    let path = get_temp_db_path("newer_schema");
+    let conn = Connection::open(&path).unwrap();
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+        .unwrap();
+    drop(conn);
+
+    match SnapshotStore::open(&path) {
+        Err(StorageError::UnsupportedSchema { found, supported }) => {
+            assert_eq!(found, SCHEMA_VERSION + 1);
+            assert_eq!(supported, SCHEMA_VERSION);
+        }
+        other => panic!(
+            "expected an unsupported schema error, got {0:?}",
+            other.err()
+        ),
+    }
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_latest_snapshot() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    assert!(store.latest_snapshot(AFL_EVENT_ID).unwrap().is_none());
+
+    let second_fetch = get_fetch_time() + Duration::minutes(5);
+    store
+        .save_events(&[get_afl_event(2.3)], get_fetch_time())
+        .unwrap();
+    store
+        .save_events(&[get_afl_event(2.45)], second_fetch)
+        .unwrap();
+
+    let snapshot = store.latest_snapshot(AFL_EVENT_ID).unwrap().unwrap();
+    assert_eq!(snapshot.fetched_at, second_fetch);
+    assert_eq!(snapshot.event.id(), AFL_EVENT_ID);
+    assert_eq!(snapshot.event.away_team(), "Brisbane Lions");
+    assert_eq!(snapshot.event.bookmakers().len(), 4);
+
+    let tab = snapshot
+        .event
+        .bookmakers()
+        .iter()
+        .find(|x| x.key == "tab")
+        .unwrap();
+    assert_eq!(tab.markets.len(), 3);
+    assert_eq!(
+        tab.get_odds(&MarketType::H2h, "St Kilda Saints"),
+        Some(Odds::Decimal(2.45))
+    );
+
+    let earliest = store
+        .earliest_snapshot_since(AFL_EVENT_ID, get_fetch_time() - Duration::minutes(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(earliest.fetched_at, get_fetch_time());
+    let earliest = store
+        .earliest_snapshot_since(AFL_EVENT_ID, get_fetch_time() + Duration::minutes(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(earliest.fetched_at, second_fetch);
+    assert!(store
+        .earliest_snapshot_since(AFL_EVENT_ID, second_fetch + Duration::minutes(1))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_bet_ledger() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    store
+        .save_events(&[get_afl_event(2.3)], get_fetch_time())
+        .unwrap();
+
+    let event = store.stored_event(AFL_EVENT_ID).unwrap().unwrap();
+    let bet = Bet {
+        id: None,
+        event_id: event.id,
+        sport_key: event.sport_key,
+        home_team: event.home_team,
+        away_team: event.away_team,
+        commence_time: event.commence_time,
+        market_key: MarketType::Spreads,
+        outcome_key: String::from("Brisbane Lions"),
+        point: Some(7.5),
+        bookmaker_key: String::from("tab"),
+        odds: Odds::Decimal(1.9),
+        stake: 25.0,
+        placed_at: get_fetch_time(),
+        status: BetStatus::Pending,
+        settled_at: None,
+    };
+    let bet_id = store.record_bet(&bet).unwrap();
+
+    let pending = store.pending_bets().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, Some(bet_id));
+    assert_eq!(pending[0].point, Some(7.5));
+    assert_eq!(pending[0].market_key, MarketType::Spreads);
+
+    let settled_at = get_fetch_time() + Duration::hours(4);
+    store
+        .settle_bet(bet_id, BetStatus::Won, settled_at)
+        .unwrap();
+    assert!(store.pending_bets().unwrap().is_empty());
+
+    let bets = store.bets().unwrap();
+    assert_eq!(bets[0].status, BetStatus::Won);
+    assert_eq!(bets[0].settled_at, Some(settled_at));
+}
+
+#[test]
+fn test_track_opportunity_until_close() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let event = get_afl_event(2.45);
+    let opportunity = TrackedOpportunity {
+        id: None,
+        event_id: event.id().to_string(),
+        sport_key: event.sport_key().to_string(),
+        commence_time: event.commence_time(),
+        market_key: MarketType::Totals,
+        outcome_key: String::from("Over"),
+        point: Some(166.5),
+        bookmaker_key: String::from("tab"),
+        model: PricingModel::PoissonTotals,
+        devig_method: DevigMethod::Power,
+        offered_odds: Odds::Decimal(2.05),
+        true_odds: Odds::Decimal(1.95),
+        percent_ev: 5.13,
+        found_at: get_fetch_time(),
+        closing_odds: None,
+        closed_at: None,
+    };
+    let opportunity_id = store.record_opportunity(&opportunity).unwrap().unwrap();
+
+    // seeing the same price again later doesn't track it twice
+    let mut seen_again = opportunity.clone();
+    seen_again.found_at = get_fetch_time() + Duration::minutes(5);
+    assert_eq!(store.record_opportunity(&seen_again).unwrap(), None);
+
+    let unclosed = store.unclosed_opportunities().unwrap();
+    assert_eq!(unclosed.len(), 1);
+    assert_eq!(
+        unclosed[0],
+        TrackedOpportunity {
+            id: Some(opportunity_id),
+            ..opportunity.clone()
+        }
+    );
+
+    let closed_at = event.commence_time() - Duration::minutes(5);
+    store
+        .set_closing_odds(opportunity_id, &Odds::Decimal(1.9), closed_at)
+        .unwrap();
+    assert!(store.unclosed_opportunities().unwrap().is_empty());
+
+    let tracked = store.opportunities().unwrap();
+    assert_eq!(tracked[0].closing_odds, Some(Odds::Decimal(1.9)));
+    assert_eq!(tracked[0].closed_at, Some(closed_at));
+    assert!(tracked[0].clv_percent().unwrap() > 0.0);
+}
+
+#[test]
+fn test_alert_history() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let event = get_afl_event(2.45);
+    let key = OpportunityKey {
+        event_id: event.id().to_string(),
+        market_key: MarketType::Totals,
+        outcome_key: String::from("Over"),
+        point: Some(166.5),
+        bookmaker_key: String::from("tab"),
+        direction: None,
+    };
+    let alert = AlertRecord {
+        key: key.clone(),
+        sport_key: event.sport_key().to_string(),
+        commence_time: event.commence_time(),
+        matchup: String::from("St Kilda Saints vs Brisbane Lions"),
+        offered_odds: Odds::Decimal(2.05),
+        percent_ev: 5.13,
+        alerted_at: get_fetch_time(),
+        gone_at: None,
+    };
+    assert_eq!(store.alert_for(&key).unwrap(), None);
+    store.save_alert(&alert).unwrap();
+    assert_eq!(store.alert_for(&key).unwrap(), Some(alert.clone()));
+
+    // another line is another opportunity
+    let other_line = OpportunityKey {
+        point: Some(170.5),
+        ..key.clone()
+    };
+    assert_eq!(store.alert_for(&other_line).unwrap(), None);
+
+    // alerting again replaces the last alert
+    let realert = AlertRecord {
+        percent_ev: 7.0,
+        alerted_at: get_fetch_time() + Duration::minutes(30),
+        ..alert.clone()
+    };
+    store.save_alert(&realert).unwrap();
+    let active = store.active_alerts("aussierules_afl").unwrap();
+    assert_eq!(active, [realert.clone()]);
+    assert!(store.active_alerts("baseball_kbo").unwrap().is_empty());
+
+    let gone_at = get_fetch_time() + Duration::hours(1);
+    store.set_alert_gone(&key, gone_at).unwrap();
+    assert!(store.active_alerts("aussierules_afl").unwrap().is_empty());
+    assert_eq!(
+        store.alert_for(&key).unwrap().unwrap().gone_at,
+        Some(gone_at)
+    );
+
+    // a steam move on the outcome is kept apart, each way it moves, and
+    // never goes
+    let steam = AlertRecord {
+        key: OpportunityKey {
+            bookmaker_key: String::new(),
+            direction: Some(MoveDirection::Shortening),
+            ..key.clone()
+        },
+        gone_at: None,
+        ..alert.clone()
+    };
+    store.save_alert(&steam).unwrap();
+    assert_eq!(store.alert_for(&steam.key).unwrap(), Some(steam.clone()));
+    let drifting = OpportunityKey {
+        direction: Some(MoveDirection::Drifting),
+        ..steam.key.clone()
+    };
+    assert_eq!(store.alert_for(&drifting).unwrap(), None);
+    assert!(store.alert_for(&key).unwrap().unwrap().gone_at.is_some());
+    assert!(store.active_alerts("aussierules_afl").unwrap().is_empty());
+}
+
+#[test]
+fn test_snapshots_and_results_for_backtests() {
+    let mut store = SnapshotStore::open_in_memory().unwrap();
+    let second_fetch = get_fetch_time() + Duration::minutes(5);
+    store
+        .save_events(&[get_afl_event(2.45)], second_fetch)
+        .unwrap();
+    store
+        .save_events(&[get_afl_event(2.3)], get_fetch_time())
+        .unwrap();
+
+    let snapshots = store.snapshots_for_sport("aussierules_afl").unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].fetched_at, get_fetch_time());
+    assert_eq!(snapshots[0].event.fetched_at(), Some(get_fetch_time()));
+    assert_eq!(snapshots[1].fetched_at, second_fetch);
+    assert!(store
+        .snapshots_for_sport("baseball_kbo")
+        .unwrap()
+        .is_empty());
+
+    let raw_file_string = fs::read_to_string("./example_data/oddsapi/aussierules_afl_scores.json")
+        .expect("Unable to read file");
+    let mut scores = serde_json::from_str::<Vec<EventScore>>(&raw_file_string).unwrap();
+    let mut unfinished = scores[0].clone();
+    unfinished.id = String::from("unfinished");
+    unfinished.completed = false;
+    scores.push(unfinished);
+
+    assert_eq!(store.save_results(&scores).unwrap(), 1);
+    // saving again replaces rather than duplicates
+    assert_eq!(store.save_results(&scores).unwrap(), 1);
+
+    let results = store.results_for_sport("aussierules_afl").unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].completed);
+    assert_eq!(results[0].score_for("St Kilda Saints"), Some(88.0));
+    assert_eq!(results[0].score_for("Brisbane Lions"), Some(81.0));
+    assert_eq!(results[0].last_update, scores[0].last_update);
+}